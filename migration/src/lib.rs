@@ -3,6 +3,19 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table_book;
 mod m20220101_000002_create_table_user;
 mod m20220101_000003_create_table_permissions;
+mod m20220101_000004_create_table_copy;
+mod m20220101_000005_create_table_loan;
+mod m20220101_000006_create_table_hold;
+mod m20220101_000007_create_table_ledger_entry;
+mod m20220101_000008_create_table_loan_policy;
+mod m20220101_000009_create_table_contributor;
+mod m20220101_000010_create_book_search_indexes;
+mod m20220101_000011_create_table_book_subject;
+mod m20220101_000012_add_book_sort_keys;
+mod m20220101_000013_create_table_book_tombstone;
+mod m20220101_000014_add_book_description;
+mod m20220101_000015_create_table_metadata_cache;
+mod m20220101_000016_canonicalize_book_isbns;
 
 pub struct Migrator;
 
@@ -13,6 +26,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table_book::Migration),
             Box::new(m20220101_000002_create_table_user::Migration),
             Box::new(m20220101_000003_create_table_permissions::Migration),
+            Box::new(m20220101_000004_create_table_copy::Migration),
+            Box::new(m20220101_000005_create_table_loan::Migration),
+            Box::new(m20220101_000006_create_table_hold::Migration),
+            Box::new(m20220101_000007_create_table_ledger_entry::Migration),
+            Box::new(m20220101_000008_create_table_loan_policy::Migration),
+            Box::new(m20220101_000009_create_table_contributor::Migration),
+            Box::new(m20220101_000010_create_book_search_indexes::Migration),
+            Box::new(m20220101_000011_create_table_book_subject::Migration),
+            Box::new(m20220101_000012_add_book_sort_keys::Migration),
+            Box::new(m20220101_000013_create_table_book_tombstone::Migration),
+            Box::new(m20220101_000014_add_book_description::Migration),
+            Box::new(m20220101_000015_create_table_metadata_cache::Migration),
+            Box::new(m20220101_000016_canonicalize_book_isbns::Migration),
         ]
     }
 }
@@ -27,6 +53,9 @@ pub enum Book {
     Isbn,
     CreatedAt,
     UpdatedAt,
+    TitleSort,
+    AuthorSort,
+    Description,
 }
 
 #[derive(Iden)]
@@ -41,6 +70,7 @@ pub enum User {
     Token,
     TokenExpiry,
     PermissionId,
+    Category,
 }
 
 #[derive(Iden)]
@@ -51,3 +81,115 @@ pub enum Permissions {
     #[allow(clippy::enum_variant_names)]
     Permissions,
 }
+
+#[derive(Iden)]
+#[iden = "copy"]
+pub enum BookCopy {
+    Table,
+    Id,
+    Book,
+    Barcode,
+    ShelfLocation,
+    Condition,
+    AcquiredOn,
+    Status,
+    ItemType,
+    Branch,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum Loan {
+    Table,
+    Id,
+    Copy,
+    Borrower,
+    CheckedOutAt,
+    DueAt,
+    ReturnedAt,
+    Renewals,
+}
+
+#[derive(Iden)]
+pub enum Hold {
+    Table,
+    Id,
+    Book,
+    Patron,
+    Position,
+    Status,
+    Copy,
+    PlacedAt,
+    PickupExpiresAt,
+}
+
+#[derive(Iden)]
+pub enum LedgerEntry {
+    Table,
+    Id,
+    User,
+    Kind,
+    Amount,
+    Reason,
+    PostedBy,
+    Loan,
+    AccruedOn,
+    PostedAt,
+}
+
+#[derive(Iden)]
+pub enum LoanPolicy {
+    Table,
+    Id,
+    PatronCategory,
+    ItemType,
+    Branch,
+    LoanDays,
+    MaxRenewals,
+    MaxLoans,
+    FinePerDay,
+}
+
+#[derive(Iden)]
+pub enum Contributor {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(Iden)]
+pub enum BookContributor {
+    Table,
+    Id,
+    Book,
+    Contributor,
+    Role,
+    Position,
+}
+
+#[derive(Iden)]
+pub enum BookSubject {
+    Table,
+    Id,
+    Book,
+    Subject,
+}
+
+#[derive(Iden)]
+pub enum BookTombstone {
+    Table,
+    Id,
+    Book,
+    DeletedAt,
+}
+
+#[derive(Iden)]
+pub enum MetadataCache {
+    Table,
+    Id,
+    Provider,
+    Isbn,
+    Response,
+    FetchedAt,
+}
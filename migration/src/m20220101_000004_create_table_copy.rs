@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{Book, BookCopy};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookCopy::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(BookCopy::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(BookCopy::Book).not_null())
+                    .col(string(BookCopy::Barcode).not_null().unique_key())
+                    .col(string(BookCopy::ShelfLocation).not_null())
+                    .col(string_len(BookCopy::Condition, 16).not_null())
+                    .col(date(BookCopy::AcquiredOn).not_null())
+                    .col(string_len(BookCopy::Status, 16).not_null())
+                    .col(timestamp(BookCopy::CreatedAt).not_null())
+                    .col(timestamp(BookCopy::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_copy_book_id")
+                            .from(BookCopy::Table, BookCopy::Book)
+                            .to(Book::Table, Book::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookCopy::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{BookCopy, Loan, User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Loan::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(Loan::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(Loan::Copy).not_null())
+                    .col(integer(Loan::Borrower).not_null())
+                    .col(timestamp(Loan::CheckedOutAt).not_null())
+                    .col(timestamp(Loan::DueAt).not_null())
+                    .col(timestamp_null(Loan::ReturnedAt))
+                    .col(integer(Loan::Renewals).not_null().default(0))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_loan_copy_id")
+                            .from(Loan::Table, Loan::Copy)
+                            .to(BookCopy::Table, BookCopy::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_loan_borrower_id")
+                            .from(Loan::Table, Loan::Borrower)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Loan::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{Book, BookCopy, Hold, User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Hold::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(Hold::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(Hold::Book).not_null())
+                    .col(integer(Hold::Patron).not_null())
+                    .col(integer(Hold::Position).not_null())
+                    .col(string_len(Hold::Status, 16).not_null())
+                    .col(integer_null(Hold::Copy))
+                    .col(timestamp(Hold::PlacedAt).not_null())
+                    .col(timestamp_null(Hold::PickupExpiresAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_hold_book_id")
+                            .from(Hold::Table, Hold::Book)
+                            .to(Book::Table, Book::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_hold_patron_id")
+                            .from(Hold::Table, Hold::Patron)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_hold_copy_id")
+                            .from(Hold::Table, Hold::Copy)
+                            .to(BookCopy::Table, BookCopy::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_hold_book_status_position")
+                    .table(Hold::Table)
+                    .col(Hold::Book)
+                    .col(Hold::Status)
+                    .col(Hold::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Hold::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{LedgerEntry, Loan, User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LedgerEntry::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(LedgerEntry::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(LedgerEntry::User).not_null())
+                    .col(string_len(LedgerEntry::Kind, 16).not_null())
+                    .col(big_unsigned(LedgerEntry::Amount).not_null())
+                    .col(string(LedgerEntry::Reason).not_null())
+                    .col(integer_null(LedgerEntry::PostedBy))
+                    .col(integer_null(LedgerEntry::Loan))
+                    .col(date_null(LedgerEntry::AccruedOn))
+                    .col(timestamp(LedgerEntry::PostedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_ledger_entry_user_id")
+                            .from(LedgerEntry::Table, LedgerEntry::User)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_ledger_entry_posted_by_id")
+                            .from(LedgerEntry::Table, LedgerEntry::PostedBy)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_ledger_entry_loan_id")
+                            .from(LedgerEntry::Table, LedgerEntry::Loan)
+                            .to(Loan::Table, Loan::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_ledger_entry_loan_accrued_on")
+                    .table(LedgerEntry::Table)
+                    .col(LedgerEntry::Loan)
+                    .col(LedgerEntry::AccruedOn)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LedgerEntry::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{BookCopy, LoanPolicy, User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoanPolicy::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(LoanPolicy::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(string_null(LoanPolicy::PatronCategory))
+                    .col(string_null(LoanPolicy::ItemType))
+                    .col(string_null(LoanPolicy::Branch))
+                    .col(integer(LoanPolicy::LoanDays).not_null())
+                    .col(integer(LoanPolicy::MaxRenewals).not_null())
+                    .col(integer(LoanPolicy::MaxLoans).not_null())
+                    .col(big_unsigned(LoanPolicy::FinePerDay).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string(User::Category).not_null().default("standard"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BookCopy::Table)
+                    .add_column(string(BookCopy::ItemType).not_null().default("book"))
+                    .add_column(string(BookCopy::Branch).not_null().default("main"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoanPolicy::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Category)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BookCopy::Table)
+                    .drop_column(BookCopy::ItemType)
+                    .drop_column(BookCopy::Branch)
+                    .to_owned(),
+            )
+            .await
+    }
+}
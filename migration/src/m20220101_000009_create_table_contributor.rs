@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{Book, BookContributor, Contributor};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Data migrations keep their own copy of the application rules they apply, so later changes to
+// the application never change what an old migration does.
+fn split_author(author: &str) -> Vec<String> {
+    author
+        .split(';')
+        .flat_map(|part| part.split(" & "))
+        .flat_map(|part| part.split(" and "))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Contributor::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(Contributor::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(string(Contributor::Name).not_null().unique_key())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookContributor::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(BookContributor::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(BookContributor::Book).not_null())
+                    .col(integer(BookContributor::Contributor).not_null())
+                    .col(string_len(BookContributor::Role, 16).not_null())
+                    .col(integer(BookContributor::Position).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_book_contributor_book_id")
+                            .from(BookContributor::Table, BookContributor::Book)
+                            .to(Book::Table, Book::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_book_contributor_contributor_id")
+                            .from(BookContributor::Table, BookContributor::Contributor)
+                            .to(Contributor::Table, Contributor::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let books = db
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Book::Id, Book::Author])
+                        .from(Book::Table),
+                ),
+            )
+            .await?;
+
+        let mut contributors = HashMap::<String, u64>::new();
+        for row in books {
+            let book: u64 = row.try_get("", &Book::Id.to_string())?;
+            let author: String = row.try_get("", &Book::Author.to_string())?;
+
+            for (position, name) in split_author(&author).into_iter().enumerate() {
+                let contributor = match contributors.get(&name) {
+                    Some(id) => *id,
+                    None => {
+                        let result = db
+                            .execute(
+                                backend.build(
+                                    Query::insert()
+                                        .into_table(Contributor::Table)
+                                        .columns([Contributor::Name])
+                                        .values_panic([name.clone().into()]),
+                                ),
+                            )
+                            .await?;
+                        contributors.insert(name, result.last_insert_id());
+                        result.last_insert_id()
+                    }
+                };
+
+                db.execute(
+                    backend.build(
+                        Query::insert()
+                            .into_table(BookContributor::Table)
+                            .columns([
+                                BookContributor::Book,
+                                BookContributor::Contributor,
+                                BookContributor::Role,
+                                BookContributor::Position,
+                            ])
+                            .values_panic([
+                                book.into(),
+                                contributor.into(),
+                                "author".into(),
+                                (position as u32).into(),
+                            ]),
+                    ),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookContributor::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Contributor::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{Book, BookContributor};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_isbn")
+                    .table(Book::Table)
+                    .col(Book::Isbn)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_title")
+                    .table(Book::Table)
+                    .col(Book::Title)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_publication_year")
+                    .table(Book::Table)
+                    .col(Book::PublicationYear)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_contributor_book_position")
+                    .table(BookContributor::Table)
+                    .col(BookContributor::Book)
+                    .col(BookContributor::Position)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_contributor_contributor_role")
+                    .table(BookContributor::Table)
+                    .col(BookContributor::Contributor)
+                    .col(BookContributor::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, table) in [
+            ("IDX_book_isbn", Book::Table.into_iden()),
+            ("IDX_book_title", Book::Table.into_iden()),
+            ("IDX_book_publication_year", Book::Table.into_iden()),
+            (
+                "IDX_book_contributor_book_position",
+                BookContributor::Table.into_iden(),
+            ),
+            (
+                "IDX_book_contributor_contributor_role",
+                BookContributor::Table.into_iden(),
+            ),
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(table).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
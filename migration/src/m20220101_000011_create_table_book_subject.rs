@@ -0,0 +1,53 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{Book, BookSubject};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookSubject::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(BookSubject::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(integer(BookSubject::Book).not_null())
+                    .col(string_len(BookSubject::Subject, 128).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_book_subject_book_id")
+                            .from(BookSubject::Table, BookSubject::Book)
+                            .to(Book::Table, Book::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_subject_subject_book")
+                    .table(BookSubject::Table)
+                    .col(BookSubject::Subject)
+                    .col(BookSubject::Book)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookSubject::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,153 @@
+use deunicode::deunicode;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SORT_KEY_LEN: usize = 255;
+const LEADING_ARTICLES: [&str; 18] = [
+    "the", "a", "an", "der", "die", "das", "ein", "eine", "le", "la", "les", "un", "une", "el",
+    "los", "las", "il", "lo",
+];
+
+fn fold(text: &str) -> String {
+    let folded = deunicode(text)
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '\'')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>();
+    folded
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(SORT_KEY_LEN)
+        .collect()
+}
+
+fn title_sort_key(title: &str) -> String {
+    if let Some(rest) = title
+        .strip_prefix("L'")
+        .or_else(|| title.strip_prefix("l'"))
+    {
+        if !fold(rest).is_empty() {
+            return fold(rest);
+        }
+    }
+    let folded = fold(title);
+    match folded.split_once(' ') {
+        Some((article, rest)) if LEADING_ARTICLES.contains(&article) => rest.to_string(),
+        _ => folded,
+    }
+}
+
+fn author_sort_key(author: &str) -> String {
+    let Some(first) = author
+        .split(';')
+        .flat_map(|part| part.split(" & "))
+        .flat_map(|part| part.split(" and "))
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+    else {
+        return String::new();
+    };
+    if first.contains(',') {
+        return fold(first);
+    }
+    match first.rsplit_once(' ') {
+        Some((given, surname)) => fold(&format!("{surname} {given}")),
+        None => fold(first),
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Book::Table)
+                    .add_column(string_len(Book::TitleSort, 255).not_null().default(""))
+                    .add_column(string_len(Book::AuthorSort, 255).not_null().default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let books = db
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Book::Id, Book::Title, Book::Author])
+                        .from(Book::Table),
+                ),
+            )
+            .await?;
+
+        for row in books {
+            let book: u64 = row.try_get("", &Book::Id.to_string())?;
+            let title: String = row.try_get("", &Book::Title.to_string())?;
+            let author: String = row.try_get("", &Book::Author.to_string())?;
+
+            db.execute(
+                backend.build(
+                    Query::update()
+                        .table(Book::Table)
+                        .values([
+                            (Book::TitleSort, title_sort_key(&title).into()),
+                            (Book::AuthorSort, author_sort_key(&author).into()),
+                        ])
+                        .and_where(Expr::col(Book::Id).eq(book)),
+                ),
+            )
+            .await?;
+        }
+
+        // Every sorted listing orders by (key, id), so each index carries the id tie-break.
+        for (name, column) in [
+            ("IDX_book_title_sort_id", Book::TitleSort),
+            ("IDX_book_author_sort_id", Book::AuthorSort),
+            ("IDX_book_created_at_id", Book::CreatedAt),
+            ("IDX_book_updated_at_id", Book::UpdatedAt),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Book::Table)
+                        .col(column)
+                        .col(Book::Id)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "IDX_book_title_sort_id",
+            "IDX_book_author_sort_id",
+            "IDX_book_created_at_id",
+            "IDX_book_updated_at_id",
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(Book::Table).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Book::Table)
+                    .drop_column(Book::TitleSort)
+                    .drop_column(Book::AuthorSort)
+                    .to_owned(),
+            )
+            .await
+    }
+}
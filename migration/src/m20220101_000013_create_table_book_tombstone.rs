@@ -0,0 +1,46 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::BookTombstone;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookTombstone::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(BookTombstone::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // No foreign key: the book row is gone by the time its tombstone exists.
+                    .col(integer_uniq(BookTombstone::Book).not_null())
+                    .col(timestamp(BookTombstone::DeletedAt).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_book_tombstone_deleted_at_book")
+                    .table(BookTombstone::Table)
+                    .col(BookTombstone::DeletedAt)
+                    .col(BookTombstone::Book)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookTombstone::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::MetadataCache;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MetadataCache::Table)
+                    .if_not_exists()
+                    .col(
+                        integer_uniq(MetadataCache::Id)
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(string_len(MetadataCache::Provider, 32).not_null())
+                    .col(string_len(MetadataCache::Isbn, 13).not_null())
+                    // JSON; null when the provider knew nothing about the ISBN.
+                    .col(text_null(MetadataCache::Response))
+                    .col(timestamp(MetadataCache::FetchedAt).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_metadata_cache_provider_isbn")
+                    .table(MetadataCache::Table)
+                    .col(MetadataCache::Provider)
+                    .col(MetadataCache::Isbn)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MetadataCache::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use sea_orm_migration::prelude::*;
+
+use crate::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn normalize_isbn(isbn: &str) -> Option<String> {
+    let isbn = isbn
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    match isbn.len() {
+        10 => {
+            if !isbn10_valid(&isbn) {
+                return None;
+            }
+            let body = format!("978{}", &isbn[..9]);
+            let check = isbn13_check_digit(&body);
+            Some(format!("{body}{check}"))
+        }
+        13 => {
+            if !isbn.bytes().all(|c| c.is_ascii_digit())
+                || !(isbn.starts_with("978") || isbn.starts_with("979"))
+                || isbn13_check_digit(&isbn[..12]) != isbn.as_bytes()[12] - b'0'
+            {
+                return None;
+            }
+            Some(isbn)
+        }
+        _ => None,
+    }
+}
+
+fn isbn10_valid(isbn: &str) -> bool {
+    let mut sum = 0;
+    for (index, c) in isbn.bytes().enumerate() {
+        let value = match c {
+            b'0'..=b'9' => (c - b'0') as u32,
+            b'X' if index == 9 => 10,
+            _ => return false,
+        };
+        sum += (10 - index as u32) * value;
+    }
+    sum % 11 == 0
+}
+
+fn isbn13_check_digit(body: &str) -> u8 {
+    let sum: u32 = body
+        .bytes()
+        .enumerate()
+        .map(|(index, c)| (c - b'0') as u32 * if index % 2 == 0 { 1 } else { 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let rows = db
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Book::Id, Book::Isbn])
+                        .from(Book::Table)
+                        .order_by(Book::Id, Order::Asc),
+                ),
+            )
+            .await?;
+        let mut books = vec![];
+        for row in rows {
+            let id: u64 = row.try_get("", &Book::Id.to_string())?;
+            let isbn: String = row.try_get("", &Book::Isbn.to_string())?;
+            books.push((id, isbn));
+        }
+
+        // Rows already in canonical form keep their ISBN; another row that turns out to be the
+        // same book is left as it is for staff to merge, rather than becoming a silent duplicate.
+        let mut taken = books
+            .iter()
+            .filter(|(_, isbn)| normalize_isbn(isbn).as_deref() == Some(isbn.as_str()))
+            .map(|(_, isbn)| isbn.clone())
+            .collect::<HashSet<_>>();
+        for (id, isbn) in books {
+            // Invalid ISBNs cannot be repaired here and stay untouched.
+            let Some(canonical) = normalize_isbn(&isbn) else {
+                continue;
+            };
+            if canonical == isbn || !taken.insert(canonical.clone()) {
+                continue;
+            }
+            db.execute(
+                backend.build(
+                    Query::update()
+                        .table(Book::Table)
+                        .value(Book::Isbn, canonical)
+                        .and_where(Expr::col(Book::Id).eq(id)),
+                ),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The original spelling of each ISBN is not kept, and the canonical form is still valid.
+        Ok(())
+    }
+}
@@ -1,13 +1,24 @@
-use std::{env, net::Ipv4Addr};
+use std::{env, net::Ipv4Addr, path::PathBuf};
 
 use log::trace;
 
-#[derive(Debug)]
+use crate::library::MAX_LEDGER_AMOUNT;
+
+#[derive(Debug, Clone)]
 pub struct Config {
     bind_address: Ipv4Addr,
     bind_port: u16,
     rate_limit_burst: u32,
     rate_limit_per_second: u64,
+    fine_per_day: u64,
+    search_index_dir: PathBuf,
+    max_per_page: usize,
+    cover_dir: PathBuf,
+    public_base_url: String,
+    public_catalog: bool,
+    catalog_name: String,
+    admin_email: String,
+    metadata_url: String,
 }
 
 impl Config {
@@ -16,6 +27,7 @@ impl Config {
 
         let raw_address = env::var("BIND_ADDRESS").unwrap_or("127.0.0.1".to_string());
         let bind_address = raw_address.parse();
+        #[allow(clippy::unnecessary_unwrap)]
         if bind_address.is_err() {
             return Err(format!(
                 "Failed to convert `{raw_address}` to an IP address: `{}`",
@@ -26,6 +38,7 @@ impl Config {
 
         let raw_port = env::var("BIND_PORT").unwrap_or("1337".to_string());
         let bind_port = raw_port.parse();
+        #[allow(clippy::unnecessary_unwrap)]
         if bind_port.is_err() {
             return Err(format!(
                 "Failed to convert `{raw_port}` to a valid port number: `{}`",
@@ -36,6 +49,7 @@ impl Config {
 
         let raw_rlb = env::var("BIND_PORT").unwrap_or("5".to_string());
         let rate_limit_burst = raw_rlb.parse();
+        #[allow(clippy::unnecessary_unwrap)]
         if rate_limit_burst.is_err() {
             return Err(format!(
                 "Failed to convert `{raw_rlb}` to a valid number: `{}`",
@@ -46,6 +60,7 @@ impl Config {
 
         let raw_rls = env::var("BIND_PORT").unwrap_or("1337".to_string());
         let rate_limit_per_second = raw_rls.parse();
+        #[allow(clippy::unnecessary_unwrap)]
         if rate_limit_per_second.is_err() {
             return Err(format!(
                 "Failed to convert `{raw_rls}` to a valid number: `{}`",
@@ -54,11 +69,69 @@ impl Config {
         }
         let rate_limit_per_second = rate_limit_per_second.unwrap();
 
+        let raw_fine = env::var("FINE_PER_DAY").unwrap_or("25".to_string());
+        let fine_per_day = raw_fine.parse();
+        if let Err(error) = &fine_per_day {
+            return Err(format!(
+                "Failed to convert `{raw_fine}` to a valid amount: `{error}`"
+            ));
+        }
+        let fine_per_day = fine_per_day.unwrap();
+        if fine_per_day > MAX_LEDGER_AMOUNT {
+            return Err(format!(
+                "`FINE_PER_DAY` may be at most {MAX_LEDGER_AMOUNT}, got `{fine_per_day}`"
+            ));
+        }
+
+        let search_index_dir =
+            PathBuf::from(env::var("SEARCH_INDEX_DIR").unwrap_or("search_index".to_string()));
+
+        let raw_max_per_page = env::var("MAX_PER_PAGE").unwrap_or("100".to_string());
+        let max_per_page = raw_max_per_page.parse();
+        if let Err(error) = &max_per_page {
+            return Err(format!(
+                "Failed to convert `{raw_max_per_page}` to a valid number: `{error}`"
+            ));
+        }
+        let max_per_page = max_per_page.unwrap();
+
+        let cover_dir = PathBuf::from(env::var("COVER_DIR").unwrap_or("covers".to_string()));
+
+        let public_base_url = env::var("PUBLIC_BASE_URL")
+            .unwrap_or(format!("http://{bind_address}:{bind_port}"))
+            .trim_end_matches('/')
+            .to_string();
+
+        let raw_public_catalog = env::var("PUBLIC_CATALOG").unwrap_or("false".to_string());
+        let public_catalog = raw_public_catalog.parse();
+        if let Err(error) = &public_catalog {
+            return Err(format!(
+                "Failed to convert `{raw_public_catalog}` to `true` or `false`: `{error}`"
+            ));
+        }
+        let public_catalog = public_catalog.unwrap();
+
+        let catalog_name = env::var("CATALOG_NAME").unwrap_or("ABLE".to_string());
+        let admin_email = env::var("ADMIN_EMAIL").unwrap_or("admin@localhost".to_string());
+        let metadata_url = env::var("METADATA_URL")
+            .unwrap_or("https://openlibrary.org".to_string())
+            .trim_end_matches('/')
+            .to_string();
+
         Ok(Self {
             bind_address,
             bind_port,
             rate_limit_burst,
             rate_limit_per_second,
+            fine_per_day,
+            search_index_dir,
+            max_per_page,
+            cover_dir,
+            public_base_url,
+            public_catalog,
+            catalog_name,
+            admin_email,
+            metadata_url,
         })
     }
 
@@ -77,4 +150,44 @@ impl Config {
     pub fn rate_limit_per_second(&self) -> u64 {
         self.rate_limit_per_second
     }
+
+    pub fn fine_per_day(&self) -> u64 {
+        self.fine_per_day
+    }
+
+    pub fn search_index_dir(&self) -> &PathBuf {
+        &self.search_index_dir
+    }
+
+    pub fn max_per_page(&self) -> usize {
+        self.max_per_page
+    }
+
+    pub fn cover_dir(&self) -> &PathBuf {
+        &self.cover_dir
+    }
+
+    /// The externally visible URL of the server, without a trailing slash, used in links that
+    /// leave the API such as OAI-PMH identifiers.
+    pub fn public_base_url(&self) -> &str {
+        &self.public_base_url
+    }
+
+    /// Whether catalog reads such as OAI-PMH harvesting are allowed without a token.
+    pub fn public_catalog(&self) -> bool {
+        self.public_catalog
+    }
+
+    pub fn catalog_name(&self) -> &str {
+        &self.catalog_name
+    }
+
+    pub fn admin_email(&self) -> &str {
+        &self.admin_email
+    }
+
+    /// Where ISBN lookups go: Open Library, or anything answering its Books API.
+    pub fn metadata_url(&self) -> &str {
+        &self.metadata_url
+    }
 }
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use log::{info, trace, warn};
+use tokio::time::interval;
+
+use crate::{config::Config, state::AppState};
+
+const HOLD_EXPIRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const OVERDUE_FINE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub fn spawn_jobs(state: AppState, config: &Config) {
+    tokio::spawn(expire_holds(state.clone()));
+    tokio::spawn(post_overdue_fines(state, config.fine_per_day()));
+}
+
+async fn expire_holds(state: AppState) {
+    let mut ticker = interval(HOLD_EXPIRY_INTERVAL);
+    loop {
+        ticker.tick().await;
+        trace!("Expiring uncollected holds.");
+        if let Err(error) = state.library().expire_holds(&state.db()).await {
+            warn!("Failed to expire holds: {error}");
+        }
+    }
+}
+
+async fn post_overdue_fines(state: AppState, fine_per_day: u64) {
+    let mut ticker = interval(OVERDUE_FINE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        info!("Posting overdue fines.");
+        if let Err(error) = state
+            .library()
+            .post_overdue_fines(fine_per_day, &state.db())
+            .await
+        {
+            warn!("Failed to post overdue fines: {error}");
+        }
+    }
+}
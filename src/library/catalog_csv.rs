@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Utc};
+use futures::{stream, Stream, StreamExt};
+use log::{trace, warn};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::request::import::ImportRequest,
+    orm::book::{self, Book},
+};
+
+use super::{normalize_isbn, Library, LibraryErrorStatus};
+
+/// Uploads larger than this are refused; split bigger catalogs into several files.
+pub const MAX_IMPORT_BYTES: usize = 32 * 1024 * 1024;
+pub(super) const EXPORT_BATCH_SIZE: u64 = 500;
+// Conflict lookups are chunked so the `IN (...)` list stays a reasonable size.
+pub(super) const CONFLICT_BATCH_SIZE: usize = 1000;
+const EXPORT_COLUMNS: [&str; 8] = [
+    "id",
+    "title",
+    "author",
+    "publication_year",
+    "isbn",
+    "subjects",
+    "created_at",
+    "updated_at",
+];
+
+/// The outcome of validating a single CSV row; it is valid when `errors` is empty.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ImportRow {
+    /// The line of a CSV row, counting the header as line 1, or the position of a MARC record.
+    pub line: u64,
+    pub title: String,
+    pub isbn: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Whether the books were written. An import is all or nothing: one bad row rejects the file.
+    pub committed: bool,
+    pub valid: usize,
+    pub invalid: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+struct Columns {
+    title: usize,
+    author: Option<usize>,
+    publication_year: usize,
+    isbn: usize,
+    subjects: Option<usize>,
+}
+
+impl Columns {
+    fn resolve(
+        headers: &csv::StringRecord,
+        request: &ImportRequest,
+    ) -> Result<Self, LibraryErrorStatus> {
+        let find = |mapped: &Option<String>, default: &str, required: bool| {
+            let name = mapped.as_deref().unwrap_or(default);
+            let index = headers
+                .iter()
+                .position(|header| header.trim().eq_ignore_ascii_case(name.trim()));
+            if index.is_none() && (required || mapped.is_some()) {
+                return Err(LibraryErrorStatus::ImportInvalid(format!(
+                    "missing column `{name}`"
+                )));
+            }
+            Ok(index)
+        };
+        Ok(Self {
+            title: find(&request.title_column, "title", true)?.unwrap_or_default(),
+            author: find(&request.author_column, "author", false)?,
+            publication_year: find(&request.publication_year_column, "publication_year", true)?
+                .unwrap_or_default(),
+            isbn: find(&request.isbn_column, "isbn", true)?.unwrap_or_default(),
+            subjects: find(&request.subjects_column, "subjects", false)?,
+        })
+    }
+}
+
+/// Turns one CSV record into a book ready for insertion, collecting every problem with it.
+fn parse_row(record: &csv::StringRecord, columns: &Columns) -> (Book, Vec<String>) {
+    let field = |index: usize| record.get(index).unwrap_or_default().trim();
+    let mut errors = vec![];
+
+    let title = field(columns.title);
+    if title.is_empty() {
+        errors.push("title is empty".to_string());
+    }
+
+    let raw_isbn = field(columns.isbn);
+    let isbn = normalize_isbn(raw_isbn).unwrap_or_else(|| {
+        errors.push(format!(
+            "isbn `{raw_isbn}` is not a valid ISBN-10 or ISBN-13"
+        ));
+        raw_isbn.to_string()
+    });
+
+    let raw_year = field(columns.publication_year);
+    let publication_year = match raw_year.parse::<u64>() {
+        Ok(year) => {
+            errors.extend(publication_year_error(year));
+            year
+        }
+        Err(_) if raw_year.is_empty() => {
+            errors.push("publication year is empty".to_string());
+            0
+        }
+        Err(_) => {
+            errors.push(format!("publication year `{raw_year}` is not a number"));
+            0
+        }
+    };
+
+    let now = Utc::now();
+    let mut book = Book {
+        id: 0,
+        title: title.to_string(),
+        author: columns.author.map(field).unwrap_or_default().to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![],
+        publication_year,
+        subjects: columns
+            .subjects
+            .map(field)
+            .unwrap_or_default()
+            .split(';')
+            .map(str::to_string)
+            .collect(),
+        isbn,
+        description: None,
+        created_at: now,
+        updated_at: now,
+    };
+    book.normalize_contributors();
+    book.normalize_subjects();
+    book.refresh_sort_keys();
+    (book, errors)
+}
+
+/// Parses and validates `csv` on its own, without consulting the catalog.
+fn parse_rows(
+    csv: &[u8],
+    request: &ImportRequest,
+) -> Result<Vec<(ImportRow, Book)>, LibraryErrorStatus> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv);
+    let headers = reader
+        .headers()
+        .map_err(|error| LibraryErrorStatus::ImportInvalid(error.to_string()))?
+        .clone();
+    let columns = Columns::resolve(&headers, request)?;
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let (line, book, errors) = match record {
+            Ok(record) => {
+                let line = record
+                    .position()
+                    .map(|position| position.line())
+                    .unwrap_or_default();
+                let (book, errors) = parse_row(&record, &columns);
+                (line, book, errors)
+            }
+            Err(error) => {
+                let line = error
+                    .position()
+                    .map(|position| position.line())
+                    .unwrap_or_default();
+                let (book, _) = parse_row(&csv::StringRecord::new(), &columns);
+                (line, book, vec![error.to_string()])
+            }
+        };
+        rows.push((
+            ImportRow {
+                line,
+                title: book.title.clone(),
+                isbn: book.isbn.clone(),
+                errors,
+            },
+            book,
+        ));
+    }
+    flag_duplicates(&mut rows, "line");
+    Ok(rows)
+}
+
+/// Reports every row repeating the ISBN of an earlier row in the same upload.
+pub(super) fn flag_duplicates(rows: &mut [(ImportRow, Book)], unit: &str) {
+    let mut first_seen = HashMap::<String, u64>::new();
+    for (row, _) in rows {
+        if row.isbn.is_empty() {
+            continue;
+        }
+        match first_seen.get(&row.isbn) {
+            Some(earlier) => row.errors.push(format!(
+                "isbn {} already appears on {unit} {earlier}",
+                row.isbn
+            )),
+            None => {
+                first_seen.insert(row.isbn.clone(), row.line);
+            }
+        }
+    }
+}
+
+/// Explains why `year` cannot be a publication year, if it cannot.
+pub(super) fn publication_year_error(year: u64) -> Option<String> {
+    // Announced titles may carry next year's date, anything later is a typo.
+    let latest_year = Utc::now().year() as u64 + 1;
+    (year > latest_year).then(|| format!("publication year {year} is in the future"))
+}
+
+fn write_rows(books: &[Book], header: bool) -> Result<Vec<u8>, LibraryErrorStatus> {
+    let write = || -> csv::Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        if header {
+            writer.write_record(EXPORT_COLUMNS)?;
+        }
+        for book in books {
+            writer.write_record([
+                book.id.to_string(),
+                book.title.clone(),
+                book.author.clone(),
+                book.publication_year.to_string(),
+                book.isbn.clone(),
+                book.subjects.join("; "),
+                book.created_at.to_rfc3339(),
+                book.updated_at.to_rfc3339(),
+            ])?;
+        }
+        writer
+            .into_inner()
+            .map_err(|error| error.into_error().into())
+    };
+    write().map_err(|error| {
+        warn!("failed to write csv: {error}");
+        LibraryErrorStatus::ExportError
+    })
+}
+
+impl Library {
+    /// Validates every row of `csv` and, unless this is a dry run or any row is invalid, adds all
+    /// of them in a single transaction.
+    pub async fn import_books(
+        &mut self,
+        csv: &[u8],
+        request: &ImportRequest,
+        database: &DatabaseConnection,
+    ) -> Result<ImportReport, LibraryErrorStatus> {
+        let rows = parse_rows(csv, request)?;
+        self.import_rows(rows, request.dry_run == Some(true), database)
+            .await
+    }
+
+    /// Checks parsed rows against the catalog, then commits them all at once when every row is
+    /// valid and this is not a dry run. Shared by every import format.
+    pub(super) async fn import_rows(
+        &mut self,
+        mut rows: Vec<(ImportRow, Book)>,
+        dry_run: bool,
+        database: &DatabaseConnection,
+    ) -> Result<ImportReport, LibraryErrorStatus> {
+        let isbns = rows
+            .iter()
+            .filter(|(row, _)| row.errors.is_empty())
+            .map(|(row, _)| row.isbn.clone())
+            .collect::<Vec<_>>();
+        for chunk in isbns.chunks(CONFLICT_BATCH_SIZE) {
+            let db_result = book::Entity::find()
+                .filter(book::Column::Isbn.is_in(chunk.to_vec()))
+                .all(database)
+                .await;
+            if let Err(error) = &db_result {
+                warn!("failed to look up conflicting isbns: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            for existing in db_result.unwrap() {
+                for (row, _) in rows.iter_mut().filter(|(row, _)| row.isbn == existing.isbn) {
+                    row.errors.push(format!(
+                        "isbn {} already belongs to book {}",
+                        existing.isbn, existing.id
+                    ));
+                }
+            }
+        }
+
+        let invalid = rows
+            .iter()
+            .filter(|(row, _)| !row.errors.is_empty())
+            .count();
+        let mut report = ImportReport {
+            dry_run,
+            committed: false,
+            valid: rows.len() - invalid,
+            invalid,
+            rows: vec![],
+        };
+        let (report_rows, mut books): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        report.rows = report_rows;
+        if report.dry_run || invalid > 0 || books.is_empty() {
+            return Ok(report);
+        }
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin import transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+        trace!("importing {} books", books.len());
+        for book in &mut books {
+            Self::insert_book(book, &txn).await?;
+        }
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit import: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        report.committed = true;
+
+        self.index_books(&books);
+        let mut cache = self.books.lock().await;
+        for book in books {
+            cache.insert(book.isbn.clone(), book);
+        }
+        Ok(report)
+    }
+
+    pub(super) async fn export_batch(
+        after: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<Book>, LibraryErrorStatus> {
+        let db_result = book::Entity::find()
+            .filter(book::Column::Id.gt(after))
+            .order_by_asc(book::Column::Id)
+            .limit(EXPORT_BATCH_SIZE)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books for export: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+        Ok(books)
+    }
+
+    /// The whole catalog as CSV, header first, fetched in batches as the stream is polled so only
+    /// one batch is held in memory at a time. The columns are accepted by `import_books`.
+    pub fn export_books(
+        database: DatabaseConnection,
+    ) -> impl Stream<Item = Result<Vec<u8>, LibraryErrorStatus>> {
+        let header = stream::once(async { write_rows(&[], true) });
+        let rows = stream::try_unfold(Some(0), move |after| {
+            let database = database.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+                let books = Self::export_batch(after, &database).await?;
+                let Some(last) = books.last() else {
+                    return Ok(None);
+                };
+                let next = (books.len() as u64 == EXPORT_BATCH_SIZE).then_some(last.id);
+                Ok(Some((write_rows(&books, false)?, next)))
+            }
+        });
+        header.chain(rows)
+    }
+}
+
+#[test]
+fn test_parse_rows() {
+    let csv = "\
+Titel,Verfasser,Jahr,ISBN,Schlagworte
+The Hobbit,J. R. R. Tolkien,1937,0-261-10221-4,Fantasy; Fantasy
+,Nobody,19x7,123,
+Der Zauberberg,Thomas Mann,2999,9780261102217,
+\"Unterminated";
+    let request = ImportRequest {
+        dry_run: Some(true),
+        title_column: Some("Titel".to_string()),
+        author_column: Some("Verfasser".to_string()),
+        publication_year_column: Some("Jahr".to_string()),
+        isbn_column: None,
+        subjects_column: Some("Schlagworte".to_string()),
+    };
+    let rows = parse_rows(csv.as_bytes(), &request).expect("headers should resolve");
+    assert_eq!(rows.len(), 4);
+
+    let (row, book) = &rows[0];
+    assert!(row.errors.is_empty());
+    assert_eq!(row.line, 2);
+    assert_eq!(book.isbn, "9780261102217");
+    assert_eq!(book.subjects, vec!["Fantasy"]);
+    assert_eq!(book.contributors[0].name, "J. R. R. Tolkien");
+    assert_eq!(book.title_sort, "hobbit");
+
+    assert_eq!(rows[1].0.errors.len(), 3);
+    assert!(rows[1].0.errors[2].contains("`19x7` is not a number"));
+    assert_eq!(
+        rows[2].0.errors,
+        vec![
+            "publication year 2999 is in the future",
+            "isbn 9780261102217 already appears on line 2"
+        ]
+    );
+    assert_eq!(rows[3].0.line, 5);
+    assert!(!rows[3].0.errors.is_empty());
+
+    let missing = ImportRequest {
+        isbn_column: Some("EAN".to_string()),
+        ..request
+    };
+    assert!(matches!(
+        parse_rows(csv.as_bytes(), &missing),
+        Err(LibraryErrorStatus::ImportInvalid(_))
+    ));
+}
+
+#[test]
+fn test_export_round_trip() {
+    let csv = "title,author,publication_year,isbn,subjects
+\"Gödel, Escher, Bach\",Douglas Hofstadter,1979,9780465026562,Logic; Music
+";
+    let (_, book) = parse_rows(csv.as_bytes(), &ImportRequest::default())
+        .expect("headers should resolve")
+        .remove(0);
+    let exported = write_rows(std::slice::from_ref(&book), true).expect("failed to write csv");
+    let (row, reimported) = parse_rows(&exported, &ImportRequest::default())
+        .expect("exported headers should resolve")
+        .remove(0);
+    assert!(row.errors.is_empty());
+    assert_eq!(reimported.title, book.title);
+    assert_eq!(reimported.author, book.author);
+    assert_eq!(reimported.subjects, book.subjects);
+    assert_eq!(reimported.isbn, book.isbn);
+}
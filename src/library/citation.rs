@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use log::warn;
+use sea_orm::{DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    model::request::search::BookSearch,
+    orm::{
+        book::{self, Book},
+        book_contributor::ContributorRole,
+    },
+};
+
+use super::{contributor::split_name, search_condition, Library, LibraryErrorStatus};
+
+/// A bibliography built from a search may not cite more books than this.
+pub const MAX_BIBLIOGRAPHY_BOOKS: u64 = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationFormat {
+    Bibtex,
+    Ris,
+    CslJson,
+}
+
+impl CitationFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Bibtex => "application/x-bibtex; charset=utf-8",
+            Self::Ris => "application/x-research-info-systems; charset=utf-8",
+            Self::CslJson => "application/vnd.citationstyles.csl+json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Bibtex => "bib",
+            Self::Ris => "ris",
+            Self::CslJson => "json",
+        }
+    }
+}
+
+/// The credited names of `book` in `role`, in credit order.
+fn names(book: &Book, role: ContributorRole) -> impl Iterator<Item = &str> {
+    book.contributors
+        .iter()
+        .filter(move |credit| credit.role == role)
+        .map(|credit| credit.name.as_str())
+}
+
+fn surname_first(name: &str) -> String {
+    match split_name(name) {
+        (Some(given), family) => format!("{family}, {given}"),
+        (None, name) => name.to_string(),
+    }
+}
+
+/// Escapes the characters TeX treats specially; everything else, accents included, is left to
+/// the UTF-8 support of biber and bibtex8.
+fn escape_tex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A key in the customary `surname` `year` `first title word` shape, e.g. `adams1980restaurant`.
+fn bibtex_key(book: &Book) -> String {
+    let surname = book.author_sort.split(' ').next().unwrap_or_default();
+    let word = book.title_sort.split(' ').next().unwrap_or_default();
+    let key = format!("{surname}{}{word}", book.publication_year);
+    key.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+fn bibtex_entry(book: &Book, key: &str) -> String {
+    let mut fields = vec![("title", escape_tex(&book.title))];
+    for (field, role) in [
+        ("author", ContributorRole::Author),
+        ("editor", ContributorRole::Editor),
+        ("translator", ContributorRole::Translator),
+        ("illustrator", ContributorRole::Illustrator),
+    ] {
+        let names = names(book, role)
+            .map(|name| escape_tex(&surname_first(name)))
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            fields.push((field, names.join(" and ")));
+        }
+    }
+    fields.push(("year", book.publication_year.to_string()));
+    fields.push(("isbn", book.isbn.clone()));
+    if !book.subjects.is_empty() {
+        fields.push(("keywords", escape_tex(&book.subjects.join(", "))));
+    }
+
+    let mut entry = format!("@book{{{key},\n");
+    for (field, value) in fields {
+        entry.push_str(&format!("  {field} = {{{value}}},\n"));
+    }
+    entry.push_str("}\n");
+    entry
+}
+
+fn ris_record(book: &Book) -> String {
+    let mut lines = vec![("TY", "BOOK".to_string())];
+    // RIS has no tag for illustrators, so they are left out.
+    for (tag, role) in [
+        ("AU", ContributorRole::Author),
+        ("ED", ContributorRole::Editor),
+        ("A4", ContributorRole::Translator),
+    ] {
+        lines.extend(names(book, role).map(|name| (tag, surname_first(name))));
+    }
+    lines.push(("TI", book.title.clone()));
+    lines.push(("PY", book.publication_year.to_string()));
+    lines.push(("SN", book.isbn.clone()));
+    lines.extend(book.subjects.iter().map(|subject| ("KW", subject.clone())));
+    lines.push(("ID", book.id.to_string()));
+    lines.push(("ER", String::new()));
+
+    lines
+        .into_iter()
+        .map(|(tag, value)| format!("{tag}  - {value}\r\n"))
+        .collect()
+}
+
+fn csl_name(name: &str) -> Value {
+    match split_name(name) {
+        (Some(given), family) => json!({ "family": family, "given": given }),
+        (None, name) => json!({ "literal": name }),
+    }
+}
+
+fn csl_item(book: &Book) -> Value {
+    let mut item = json!({
+        "id": book.id.to_string(),
+        "type": "book",
+        "title": book.title,
+        "issued": { "date-parts": [[book.publication_year]] },
+        "ISBN": book.isbn,
+    });
+    for (variable, role) in [
+        ("author", ContributorRole::Author),
+        ("editor", ContributorRole::Editor),
+        ("translator", ContributorRole::Translator),
+        ("illustrator", ContributorRole::Illustrator),
+    ] {
+        let names = names(book, role).map(csl_name).collect::<Vec<_>>();
+        if !names.is_empty() {
+            item[variable] = names.into();
+        }
+    }
+    if !book.subjects.is_empty() {
+        item["keyword"] = book.subjects.join(", ").into();
+    }
+    item
+}
+
+/// Serializes `books` as one bibliography file in `format`. BibTeX keys that would collide get a
+/// letter appended, as in `adams1980restaurant` and `adams1980restauranta`.
+pub fn bibliography(books: &[Book], format: CitationFormat) -> String {
+    match format {
+        CitationFormat::Bibtex => {
+            let mut seen = HashMap::<String, usize>::new();
+            let entries = books.iter().map(|book| {
+                let key = bibtex_key(book);
+                let count = seen.entry(key.clone()).or_default();
+                let suffix = match *count {
+                    0 => String::new(),
+                    n => char::from_u32('a' as u32 + (n as u32 - 1) % 26)
+                        .map(String::from)
+                        .unwrap_or_default(),
+                };
+                *count += 1;
+                bibtex_entry(book, &format!("{key}{suffix}"))
+            });
+            entries.collect::<Vec<_>>().join("\n")
+        }
+        CitationFormat::Ris => books
+            .iter()
+            .map(ris_record)
+            .collect::<Vec<_>>()
+            .join("\r\n"),
+        CitationFormat::CslJson => {
+            let items = books.iter().map(csl_item).collect::<Vec<_>>();
+            serde_json::to_string_pretty(&items).unwrap_or_default()
+        }
+    }
+}
+
+impl Library {
+    /// The books of a batch citation, in the order given.
+    pub async fn get_books_by_ids(
+        &mut self,
+        ids: &[u64],
+        database: &DatabaseConnection,
+    ) -> Result<Vec<Book>, LibraryErrorStatus> {
+        if ids.len() as u64 > MAX_BIBLIOGRAPHY_BOOKS {
+            return Err(LibraryErrorStatus::BibliographyTooLarge);
+        }
+        let mut books = Vec::with_capacity(ids.len());
+        for id in ids {
+            books.push(self.get_book_by_id(*id, database).await?);
+        }
+        Ok(books)
+    }
+
+    /// Every book matching `search`, ordered the way a bibliography is: by author, then title.
+    pub async fn get_books_for_bibliography(
+        &self,
+        search: &BookSearch,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<Book>, LibraryErrorStatus> {
+        let query = book::Entity::find().filter(search_condition(search)?);
+
+        let db_result = query.clone().count(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to count books for bibliography: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if db_result.unwrap() > MAX_BIBLIOGRAPHY_BOOKS {
+            return Err(LibraryErrorStatus::BibliographyTooLarge);
+        }
+
+        let db_result = query
+            .order_by_asc(book::Column::AuthorSort)
+            .order_by_asc(book::Column::TitleSort)
+            .order_by_asc(book::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books for bibliography: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+        Ok(books)
+    }
+}
+
+#[cfg(test)]
+fn sample_book(id: u64, title: &str, credits: &[(&str, ContributorRole)]) -> Book {
+    use crate::orm::book_contributor::Credit;
+    use chrono::Utc;
+
+    let mut book = Book {
+        id,
+        title: title.to_string(),
+        author: String::new(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: credits
+            .iter()
+            .map(|(name, role)| Credit {
+                name: name.to_string(),
+                role: *role,
+            })
+            .collect(),
+        publication_year: 1980,
+        subjects: vec!["Science fiction".to_string(), "Humor & satire".to_string()],
+        isbn: "9780345391810".to_string(),
+        description: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    book.normalize_contributors();
+    book.refresh_sort_keys();
+    book
+}
+
+#[test]
+fn test_bibtex() {
+    let restaurant = sample_book(
+        1,
+        "The Restaurant at the End of the Universe",
+        &[("Douglas Adams", ContributorRole::Author)],
+    );
+    let books = [
+        restaurant.clone(),
+        restaurant,
+        sample_book(
+            2,
+            "50% of a {Trilogy}",
+            &[
+                ("Homer", ContributorRole::Author),
+                ("Tolkien, J. R. R.", ContributorRole::Editor),
+            ],
+        ),
+    ];
+    let bibtex = bibliography(&books, CitationFormat::Bibtex);
+
+    assert!(bibtex.starts_with(
+        "@book{adams1980restaurant,\n  \
+         title = {The Restaurant at the End of the Universe},\n  \
+         author = {Adams, Douglas},\n  \
+         year = {1980},\n  \
+         isbn = {9780345391810},\n  \
+         keywords = {Science fiction, Humor \\& satire},\n}\n"
+    ));
+    assert!(bibtex.contains("@book{adams1980restauranta,"));
+    assert!(bibtex.contains("@book{homer198050,"));
+    assert!(bibtex.contains("title = {50\\% of a \\{Trilogy\\}},"));
+    assert!(bibtex.contains("editor = {Tolkien, J. R. R.},"));
+}
+
+#[test]
+fn test_ris_and_csl_json() {
+    let book = sample_book(
+        7,
+        "Gödel, Escher, Bach",
+        &[
+            ("Douglas Hofstadter", ContributorRole::Author),
+            ("Homer", ContributorRole::Translator),
+        ],
+    );
+
+    let ris = bibliography(std::slice::from_ref(&book), CitationFormat::Ris);
+    assert_eq!(
+        ris,
+        "TY  - BOOK\r\nAU  - Hofstadter, Douglas\r\nA4  - Homer\r\nTI  - Gödel, Escher, Bach\r\n\
+         PY  - 1980\r\nSN  - 9780345391810\r\nKW  - Science fiction\r\nKW  - Humor & satire\r\n\
+         ID  - 7\r\nER  - \r\n"
+    );
+
+    let csl = bibliography(&[book], CitationFormat::CslJson);
+    let csl = serde_json::from_str::<Value>(&csl).expect("CSL-JSON should parse");
+    assert_eq!(
+        csl,
+        json!([{
+            "id": "7",
+            "type": "book",
+            "title": "Gödel, Escher, Bach",
+            "author": [{ "family": "Hofstadter", "given": "Douglas" }],
+            "translator": [{ "literal": "Homer" }],
+            "issued": { "date-parts": [[1980]] },
+            "ISBN": "9780345391810",
+            "keyword": "Science fiction, Humor & satire",
+        }])
+    );
+}
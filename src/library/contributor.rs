@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use log::{trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+
+use crate::orm::{
+    book::Book,
+    book_contributor::{self, ContributorRole, Credit},
+    contributor,
+};
+
+use super::{Library, LibraryErrorStatus};
+
+/// Splits a free-text author string such as `"Terry Pratchett & Neil Gaiman"` into individual names.
+pub fn split_author(author: &str) -> Vec<String> {
+    author
+        .split(';')
+        .flat_map(|part| part.split(" & "))
+        .flat_map(|part| part.split(" and "))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The single-line author shown alongside the structured credits; editors and others only appear
+/// when a book credits no author at all.
+pub fn display_author(credits: &[Credit]) -> String {
+    let authors = credits
+        .iter()
+        .filter(|credit| credit.role == ContributorRole::Author)
+        .map(|credit| credit.name.as_str())
+        .collect::<Vec<_>>();
+    if !authors.is_empty() {
+        return authors.join("; ");
+    }
+    credits
+        .iter()
+        .map(|credit| credit.name.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Splits a personal name into its given and family parts: "Douglas Adams" and "Adams, Douglas"
+/// both give `(Some("Douglas"), "Adams")`, while a single name such as "Homer" has no given part.
+pub(super) fn split_name(name: &str) -> (Option<&str>, &str) {
+    if let Some((family, given)) = name.split_once(',') {
+        return (Some(given.trim()), family.trim());
+    }
+    match name.trim().rsplit_once(' ') {
+        Some((given, family)) => (Some(given.trim()), family),
+        None => (None, name.trim()),
+    }
+}
+
+impl Book {
+    /// Derives credits from `author` when the client only sent the legacy field, then rebuilds
+    /// `author` from the credits so both stay consistent.
+    pub(super) fn normalize_contributors(&mut self) {
+        if self.contributors.is_empty() {
+            self.contributors = split_author(&self.author)
+                .into_iter()
+                .map(|name| Credit {
+                    name,
+                    role: ContributorRole::Author,
+                })
+                .collect();
+        }
+        for credit in &mut self.contributors {
+            credit.name = credit.name.trim().to_string();
+        }
+        self.contributors.retain(|credit| !credit.name.is_empty());
+        self.author = display_author(&self.contributors);
+    }
+
+    pub fn has_contributor(&self, name: &str, role: Option<ContributorRole>) -> bool {
+        self.contributors
+            .iter()
+            .any(|credit| credit.name.contains(name) && role.is_none_or(|role| credit.role == role))
+    }
+}
+
+impl Library {
+    pub(super) async fn load_contributors<C: ConnectionTrait>(
+        books: &mut [Book],
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        if books.is_empty() {
+            return Ok(());
+        }
+
+        let db_result = book_contributor::Entity::find()
+            .filter(book_contributor::Column::Book.is_in(books.iter().map(|book| book.id)))
+            .order_by_asc(book_contributor::Column::Book)
+            .order_by_asc(book_contributor::Column::Position)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch book contributors: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let links = db_result.unwrap();
+
+        let db_result = contributor::Entity::find()
+            .filter(contributor::Column::Id.is_in(links.iter().map(|link| link.contributor)))
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch contributors: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let names = db_result
+            .unwrap()
+            .into_iter()
+            .map(|contributor| (contributor.id, contributor.name))
+            .collect::<HashMap<_, _>>();
+
+        let mut credits = HashMap::<u64, Vec<Credit>>::new();
+        for link in links {
+            if let Some(name) = names.get(&link.contributor) {
+                credits.entry(link.book).or_default().push(Credit {
+                    name: name.clone(),
+                    role: link.role,
+                });
+            }
+        }
+        for book in books {
+            book.contributors = credits.remove(&book.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    /// Replaces the credits of `book_id`, reusing contributors that already exist by name.
+    pub(super) async fn save_contributors<C: ConnectionTrait>(
+        book_id: u64,
+        credits: &[Credit],
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = book_contributor::Entity::delete_many()
+            .filter(book_contributor::Column::Book.eq(book_id))
+            .exec(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to clear book contributors: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        for (position, credit) in credits.iter().enumerate() {
+            let contributor = Self::find_or_insert_contributor(&credit.name, database).await?;
+
+            trace!("inserting book contributor to db");
+            let db_result = book_contributor::ActiveModel {
+                id: ActiveValue::NotSet,
+                book: ActiveValue::Set(book_id),
+                contributor: ActiveValue::Set(contributor),
+                role: ActiveValue::Set(credit.role),
+                position: ActiveValue::Set(position as u32),
+            }
+            .insert(database)
+            .await;
+            if let Err(error) = &db_result {
+                warn!("failed to add book contributor: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+        }
+        Ok(())
+    }
+
+    async fn find_or_insert_contributor<C: ConnectionTrait>(
+        name: &str,
+        database: &C,
+    ) -> Result<u64, LibraryErrorStatus> {
+        let db_result = contributor::Entity::find()
+            .filter(contributor::Column::Name.eq(name))
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch contributor: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if let Some(contributor) = db_result.unwrap() {
+            return Ok(contributor.id);
+        }
+
+        trace!("inserting contributor to db");
+        let db_result = contributor::ActiveModel {
+            id: ActiveValue::NotSet,
+            name: ActiveValue::Set(name.to_string()),
+        }
+        .insert(database)
+        .await;
+        if let Err(error) = &db_result {
+            warn!("failed to add contributor: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap().id)
+    }
+}
+
+#[test]
+fn test_split_author() {
+    assert_eq!(split_author("Douglas Adams"), vec!["Douglas Adams"]);
+    assert_eq!(
+        split_author("Terry Pratchett & Neil Gaiman"),
+        vec!["Terry Pratchett", "Neil Gaiman"]
+    );
+    assert_eq!(
+        split_author("Kernighan and Ritchie; Pike "),
+        vec!["Kernighan", "Ritchie", "Pike"]
+    );
+    assert!(split_author(" ; ").is_empty());
+}
+
+#[test]
+fn test_split_name() {
+    assert_eq!(split_name("Douglas Adams"), (Some("Douglas"), "Adams"));
+    assert_eq!(
+        split_name("Tolkien, J. R. R."),
+        (Some("J. R. R."), "Tolkien")
+    );
+    assert_eq!(split_name("Homer"), (None, "Homer"));
+}
+
+#[test]
+fn test_display_author() {
+    let credit = |name: &str, role| Credit {
+        name: name.to_string(),
+        role,
+    };
+    assert_eq!(
+        display_author(&[
+            credit("Homer", ContributorRole::Author),
+            credit("Emily Wilson", ContributorRole::Translator),
+        ]),
+        "Homer"
+    );
+    assert_eq!(
+        display_author(&[credit("Ann Editor", ContributorRole::Editor)]),
+        "Ann Editor"
+    );
+}
@@ -0,0 +1,344 @@
+use chrono::{DateTime, Utc};
+use log::{trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::request::copy::CopyRequest,
+    orm::copy::{self, BookCopy, CopyStatus},
+};
+
+use super::{Library, LibraryErrorStatus};
+
+const DEFAULT_ITEM_TYPE: &str = "book";
+const DEFAULT_BRANCH: &str = "main";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopyCounts {
+    pub total: u64,
+    pub available: u64,
+}
+
+/// Whether a barcode is in use, given the ids of the copies carrying it. The copy being edited
+/// may keep its own barcode.
+fn barcode_taken(holders: &[u64], ignore_copy: Option<u64>) -> bool {
+    holders.iter().any(|&id| Some(id) != ignore_copy)
+}
+
+/// Whether a copy saved with `new` status has just joined the shelf, `old` being its status before
+/// or `None` for a new copy. Such a copy goes to the next patron waiting for its book.
+fn became_available(old: Option<CopyStatus>, new: CopyStatus) -> bool {
+    new == CopyStatus::Available && old != Some(CopyStatus::Available)
+}
+
+/// `old` with the edits in `request` applied. Fields the request leaves out keep their value.
+fn updated_copy(old: BookCopy, request: CopyRequest, now: DateTime<Utc>) -> BookCopy {
+    BookCopy {
+        barcode: request.barcode,
+        shelf_location: request.shelf_location,
+        condition: request.condition,
+        acquired_on: request.acquired_on,
+        status: request.status.unwrap_or(old.status),
+        item_type: request.item_type.unwrap_or(old.item_type),
+        branch: request.branch.unwrap_or(old.branch),
+        updated_at: now,
+        ..old
+    }
+}
+
+impl Library {
+    pub async fn get_copies(
+        &mut self,
+        book_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<BookCopy>, LibraryErrorStatus> {
+        self.get_book_by_id(book_id, database).await?;
+
+        let db_result = copy::Entity::find()
+            .filter(copy::Column::Book.eq(book_id))
+            .order_by_asc(copy::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch copies: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn get_copy(
+        &self,
+        book_id: u64,
+        copy_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        let db_result = copy::Entity::find_by_id(copy_id)
+            .filter(copy::Column::Book.eq(book_id))
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let copy = db_result.unwrap();
+        if copy.is_none() {
+            return Err(LibraryErrorStatus::CopyNotFound);
+        }
+        Ok(copy.unwrap())
+    }
+
+    pub async fn add_copy(
+        &mut self,
+        book_id: u64,
+        request: CopyRequest,
+        database: &DatabaseConnection,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        self.get_book_by_id(book_id, database).await?;
+        self.assert_barcode_free(&request.barcode, None, database)
+            .await?;
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin copy transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        let now = Utc::now();
+        trace!("inserting copy to db");
+        let db_result = copy::ActiveModel {
+            id: ActiveValue::NotSet,
+            book: ActiveValue::Set(book_id),
+            barcode: ActiveValue::Set(request.barcode),
+            shelf_location: ActiveValue::Set(request.shelf_location),
+            condition: ActiveValue::Set(request.condition),
+            acquired_on: ActiveValue::Set(request.acquired_on),
+            status: ActiveValue::Set(request.status.unwrap_or(CopyStatus::Available)),
+            item_type: ActiveValue::Set(request.item_type.unwrap_or(DEFAULT_ITEM_TYPE.to_string())),
+            branch: ActiveValue::Set(request.branch.unwrap_or(DEFAULT_BRANCH.to_string())),
+            created_at: ActiveValue::Set(now),
+            updated_at: ActiveValue::Set(now),
+        }
+        .insert(&txn)
+        .await;
+        if let Err(error) = &db_result {
+            warn!("failed to add copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut copy = db_result.unwrap();
+        if became_available(None, copy.status) {
+            copy = Self::offer_to_holds(copy, &txn).await?;
+        }
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit added copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(copy)
+    }
+
+    pub async fn update_copy(
+        &self,
+        book_id: u64,
+        copy_id: u64,
+        request: CopyRequest,
+        database: &DatabaseConnection,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        let old_copy = self.get_copy(book_id, copy_id, database).await?;
+        self.assert_barcode_free(&request.barcode, Some(copy_id), database)
+            .await?;
+
+        let old_status = old_copy.status;
+        let copy = updated_copy(old_copy, request, Utc::now());
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin copy transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        trace!("updating copy in db");
+        let db_result = copy.into_active_model().reset_all().update(&txn).await;
+        if let Err(error) = &db_result {
+            warn!("failed to update copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut copy = db_result.unwrap();
+        if became_available(Some(old_status), copy.status) {
+            copy = Self::offer_to_holds(copy, &txn).await?;
+        }
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit updated copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(copy)
+    }
+
+    /// Hands a newly available copy to the next waiting hold, if any, and returns it as saved.
+    async fn offer_to_holds<C: ConnectionTrait>(
+        copy: BookCopy,
+        database: &C,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        let copy_id = copy.id;
+        match Self::assign_copy_to_next_hold(copy.clone(), database).await? {
+            Some(_) => Self::find_copy(copy_id, database).await,
+            None => Ok(copy),
+        }
+    }
+
+    pub async fn drop_copy(
+        &self,
+        book_id: u64,
+        copy_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = copy::Entity::delete_many()
+            .filter(copy::Column::Id.eq(copy_id))
+            .filter(copy::Column::Book.eq(book_id))
+            .exec(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to drop copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        if db_result.unwrap().rows_affected == 0 {
+            return Err(LibraryErrorStatus::CopyNotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn copy_counts(
+        &self,
+        book_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<CopyCounts, LibraryErrorStatus> {
+        let total = copy::Entity::find()
+            .filter(copy::Column::Book.eq(book_id))
+            .count(database)
+            .await;
+        if let Err(error) = &total {
+            warn!("failed to count copies: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let available = copy::Entity::find()
+            .filter(copy::Column::Book.eq(book_id))
+            .filter(copy::Column::Status.eq(CopyStatus::Available))
+            .count(database)
+            .await;
+        if let Err(error) = &available {
+            warn!("failed to count available copies: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        Ok(CopyCounts {
+            total: total.unwrap(),
+            available: available.unwrap(),
+        })
+    }
+
+    async fn assert_barcode_free(
+        &self,
+        barcode: &str,
+        ignore_copy: Option<u64>,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = copy::Entity::find()
+            .select_only()
+            .column(copy::Column::Id)
+            .filter(copy::Column::Barcode.eq(barcode))
+            .into_tuple::<u64>()
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to query copy barcodes: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if barcode_taken(&db_result.unwrap(), ignore_copy) {
+            warn!("refusing to reuse barcode: {barcode}");
+            return Err(LibraryErrorStatus::BarcodeExists);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_barcode_taken() {
+    assert!(!barcode_taken(&[], None));
+    assert!(barcode_taken(&[4], None));
+    // Saving a copy under its own barcode is not a conflict.
+    assert!(!barcode_taken(&[4], Some(4)));
+    assert!(barcode_taken(&[4, 9], Some(4)));
+}
+
+#[test]
+fn test_became_available() {
+    assert!(became_available(None, CopyStatus::Available));
+    assert!(!became_available(None, CopyStatus::InRepair));
+    assert!(became_available(
+        Some(CopyStatus::InRepair),
+        CopyStatus::Available
+    ));
+    assert!(became_available(
+        Some(CopyStatus::Lost),
+        CopyStatus::Available
+    ));
+    // A copy already on the shelf was offered to the queue when it got there.
+    assert!(!became_available(
+        Some(CopyStatus::Available),
+        CopyStatus::Available
+    ));
+    assert!(!became_available(
+        Some(CopyStatus::Available),
+        CopyStatus::OnLoan
+    ));
+}
+
+#[test]
+fn test_updated_copy() {
+    use chrono::NaiveDate;
+
+    use crate::orm::copy::CopyCondition;
+
+    let created = Utc::now();
+    let old = BookCopy {
+        id: 4,
+        book: 2,
+        barcode: "A0001".to_string(),
+        shelf_location: "FIC ADA".to_string(),
+        condition: CopyCondition::Good,
+        acquired_on: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+        status: CopyStatus::OnLoan,
+        item_type: "dvd".to_string(),
+        branch: "east".to_string(),
+        created_at: created,
+        updated_at: created,
+    };
+    let request = CopyRequest {
+        barcode: "A0002".to_string(),
+        shelf_location: "FIC ADA 2".to_string(),
+        condition: CopyCondition::Fair,
+        acquired_on: NaiveDate::from_ymd_opt(2021, 3, 4).unwrap(),
+        status: None,
+        item_type: None,
+        branch: Some("west".to_string()),
+    };
+    let now = Utc::now();
+    let copy = updated_copy(old, request, now);
+    assert_eq!(copy.id, 4);
+    assert_eq!(copy.book, 2);
+    assert_eq!(copy.barcode, "A0002");
+    assert_eq!(copy.condition, CopyCondition::Fair);
+    assert_eq!(copy.status, CopyStatus::OnLoan);
+    assert_eq!(copy.item_type, "dvd");
+    assert_eq!(copy.branch, "west");
+    assert_eq!(copy.created_at, created);
+    assert_eq!(copy.updated_at, now);
+}
@@ -0,0 +1,242 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use image::{codecs::jpeg::JpegEncoder, ImageFormat, ImageReader};
+use log::warn;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use super::{Library, LibraryErrorStatus};
+
+/// Uploads larger than this are refused before they are decoded.
+pub const MAX_COVER_BYTES: usize = 5 * 1024 * 1024;
+// Thumbnails keep their aspect ratio and fit inside this box.
+const THUMBNAIL_WIDTH: u32 = 200;
+const THUMBNAIL_HEIGHT: u32 = 300;
+const THUMBNAIL_QUALITY: u8 = 80;
+const ORIGINAL_FORMATS: [ImageFormat; 3] = [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverSize {
+    #[default]
+    Original,
+    Thumbnail,
+}
+
+/// A stored cover image and what is needed to serve it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cover {
+    pub bytes: Vec<u8>,
+    pub content_type: &'static str,
+    /// Changes whenever the cover is replaced; suitable as an `ETag`.
+    pub version: String,
+}
+
+/// Cover images on the local filesystem: `{id}.{jpg,png,webp}` holds the upload as received and
+/// `{id}-thumbnail.jpg` the generated thumbnail.
+#[derive(Debug, Clone)]
+pub struct CoverStore {
+    directory: PathBuf,
+}
+
+impl CoverStore {
+    /// Opens the store in `directory`, creating it when missing.
+    pub fn open(directory: &Path) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+        })
+    }
+
+    fn original_path(&self, id: u64, format: ImageFormat) -> PathBuf {
+        self.directory
+            .join(format!("{id}.{}", format.extensions_str()[0]))
+    }
+
+    fn thumbnail_path(&self, id: u64) -> PathBuf {
+        self.directory.join(format!("{id}-thumbnail.jpg"))
+    }
+
+    /// Whether `id` has a cover, judged by its thumbnail, which is always saved alongside it.
+    pub fn contains(&self, id: u64) -> bool {
+        self.thumbnail_path(id).exists()
+    }
+
+    /// Validates `bytes` as a JPEG, PNG or WebP image, then stores it with a fresh thumbnail,
+    /// replacing any previous cover of `id`.
+    pub fn save(&self, id: u64, bytes: &[u8]) -> Result<(), LibraryErrorStatus> {
+        if bytes.len() > MAX_COVER_BYTES {
+            return Err(LibraryErrorStatus::CoverTooLarge);
+        }
+        let format = image::guess_format(bytes).map_err(|_| LibraryErrorStatus::CoverInvalid)?;
+        if !ORIGINAL_FORMATS.contains(&format) {
+            return Err(LibraryErrorStatus::CoverInvalid);
+        }
+        let mut reader = ImageReader::new(io::Cursor::new(bytes));
+        reader.set_format(format);
+        let image = reader
+            .decode()
+            .map_err(|_| LibraryErrorStatus::CoverInvalid)?;
+
+        let mut thumbnail = vec![];
+        let encoded = image
+            .thumbnail(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+            .into_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(
+                &mut thumbnail,
+                THUMBNAIL_QUALITY,
+            ));
+        if let Err(error) = encoded {
+            warn!("failed to encode thumbnail for book {id}: {error}");
+            return Err(LibraryErrorStatus::CoverStoreError);
+        }
+
+        self.remove(id)?;
+        for (path, bytes) in [
+            (self.original_path(id, format), bytes),
+            (self.thumbnail_path(id), thumbnail.as_slice()),
+        ] {
+            if let Err(error) = fs::write(&path, bytes) {
+                warn!("failed to write cover {}: {error}", path.display());
+                return Err(LibraryErrorStatus::CoverStoreError);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(&self, id: u64, size: CoverSize) -> Result<Cover, LibraryErrorStatus> {
+        let candidates = match size {
+            CoverSize::Original => ORIGINAL_FORMATS
+                .into_iter()
+                .map(|format| (self.original_path(id, format), format))
+                .collect(),
+            CoverSize::Thumbnail => vec![(self.thumbnail_path(id), ImageFormat::Jpeg)],
+        };
+        let found = candidates.into_iter().find(|(path, _)| path.exists());
+        let Some((path, format)) = found else {
+            return Err(LibraryErrorStatus::CoverNotFound);
+        };
+
+        let read = fs::read(&path).and_then(|bytes| Ok((bytes, fs::metadata(&path)?.modified()?)));
+        if let Err(error) = &read {
+            warn!("failed to read cover {}: {error}", path.display());
+            return Err(LibraryErrorStatus::CoverStoreError);
+        }
+        let (bytes, modified) = read.unwrap();
+        let modified = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|modified| modified.as_millis())
+            .unwrap_or_default();
+        Ok(Cover {
+            version: format!("{modified:x}-{:x}", bytes.len()),
+            bytes,
+            content_type: format.to_mime_type(),
+        })
+    }
+
+    /// Deletes every file belonging to the cover of `id`; a book without a cover is not an error.
+    pub fn remove(&self, id: u64) -> Result<(), LibraryErrorStatus> {
+        let paths = ORIGINAL_FORMATS
+            .into_iter()
+            .map(|format| self.original_path(id, format))
+            .chain([self.thumbnail_path(id)]);
+        for path in paths {
+            match fs::remove_file(&path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                    warn!("failed to delete cover {}: {error}", path.display());
+                    return Err(LibraryErrorStatus::CoverStoreError);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Library {
+    fn cover_store(&self) -> Result<&CoverStore, LibraryErrorStatus> {
+        self.covers.as_ref().ok_or_else(|| {
+            warn!("cover requested without a cover store");
+            LibraryErrorStatus::CoverStoreError
+        })
+    }
+
+    pub async fn set_cover(
+        &mut self,
+        id: u64,
+        bytes: &[u8],
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        self.get_book_by_id(id, database).await?;
+        self.cover_store()?.save(id, bytes)
+    }
+
+    pub async fn get_cover(
+        &mut self,
+        id: u64,
+        size: CoverSize,
+        database: &DatabaseConnection,
+    ) -> Result<Cover, LibraryErrorStatus> {
+        self.get_book_by_id(id, database).await?;
+        self.cover_store()?.load(id, size)
+    }
+
+    pub(super) fn has_cover(&self, id: u64) -> bool {
+        self.covers
+            .as_ref()
+            .is_some_and(|covers| covers.contains(id))
+    }
+
+    // Called once the book row is gone, so a failure here only leaves orphaned files behind.
+    pub(super) fn remove_cover(&self, id: u64) {
+        if let Some(covers) = &self.covers {
+            if covers.remove(id).is_err() {
+                warn!("cover files of dropped book {id} were left behind");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cover_store() {
+    use image::{GenericImageView, ImageBuffer, Rgb};
+
+    let directory = std::env::temp_dir().join(format!("able-covers-{}", std::process::id()));
+    let store = CoverStore::open(&directory).expect("failed to open cover store");
+
+    let mut png = vec![];
+    ImageBuffer::from_pixel(400, 900, Rgb([200u8, 30, 30]))
+        .write_to(&mut io::Cursor::new(&mut png), ImageFormat::Png)
+        .expect("failed to encode png");
+    store.save(7, &png).expect("failed to save cover");
+
+    let original = store.load(7, CoverSize::Original).unwrap();
+    assert_eq!(original.content_type, "image/png");
+    assert_eq!(original.bytes, png);
+    let thumbnail = store.load(7, CoverSize::Thumbnail).unwrap();
+    assert_eq!(thumbnail.content_type, "image/jpeg");
+    let (width, height) = image::load_from_memory(&thumbnail.bytes)
+        .expect("thumbnail should decode")
+        .dimensions();
+    assert!(width <= THUMBNAIL_WIDTH && height == THUMBNAIL_HEIGHT);
+
+    assert!(matches!(
+        store.save(7, b"GIF89a not really"),
+        Err(LibraryErrorStatus::CoverInvalid)
+    ));
+    assert!(matches!(
+        store.save(7, &vec![0; MAX_COVER_BYTES + 1]),
+        Err(LibraryErrorStatus::CoverTooLarge)
+    ));
+
+    store.remove(7).expect("failed to remove cover");
+    assert!(matches!(
+        store.load(7, CoverSize::Thumbnail),
+        Err(LibraryErrorStatus::CoverNotFound)
+    ));
+    fs::remove_dir_all(directory).ok();
+}
@@ -0,0 +1,505 @@
+use std::fmt::Display;
+
+use super::{QueryField, QueryNode, QueryValue};
+
+// SRU diagnostics raised while parsing, from info:srw/diagnostic/1/.
+const QUERY_SYNTAX_ERROR: u32 = 10;
+const UNSUPPORTED_INDEX: u32 = 16;
+const UNSUPPORTED_RELATION: u32 = 19;
+const UNSUPPORTED_RELATION_MODIFIER: u32 = 20;
+const UNSUPPORTED_BOOLEAN_OPERATOR: u32 = 37;
+const UNSUPPORTED_BOOLEAN_MODIFIER: u32 = 46;
+const SORT_NOT_SUPPORTED: u32 = 80;
+
+/// How deeply groups and mixed boolean operators may nest, as in the catalog query language.
+const MAX_CQL_DEPTH: usize = 32;
+
+/// A CQL query that cannot be run, with the number of the SRU diagnostic describing why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CqlError {
+    pub diagnostic: u32,
+    pub message: String,
+}
+
+impl CqlError {
+    fn new(diagnostic: u32, message: impl Into<String>) -> Self {
+        Self {
+            diagnostic,
+            message: message.into(),
+        }
+    }
+
+    fn syntax(position: usize, message: impl Display) -> Self {
+        Self::new(
+            QUERY_SYNTAX_ERROR,
+            format!("{message} at position {position}"),
+        )
+    }
+}
+
+impl Display for CqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Slash,
+    /// `=`, `==`, `<>`, `<`, `>`, `<=` or `>=`.
+    Comparison(String),
+    /// A quoted string, with its backslash escapes still in place.
+    Quoted(String),
+    Word(String),
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | '/' | '<' | '>' | '=')
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, CqlError> {
+    let chars = query.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        match chars[index] {
+            c if c.is_whitespace() => index += 1,
+            '(' => {
+                tokens.push((Token::LeftParen, start));
+                index += 1;
+            }
+            ')' => {
+                tokens.push((Token::RightParen, start));
+                index += 1;
+            }
+            '/' => {
+                tokens.push((Token::Slash, start));
+                index += 1;
+            }
+            '"' => {
+                index += 1;
+                while index < chars.len() && chars[index] != '"' {
+                    // Skip whatever is escaped, including a quote.
+                    index += if chars[index] == '\\' { 2 } else { 1 };
+                }
+                if index >= chars.len() {
+                    return Err(CqlError::syntax(start, "unterminated string"));
+                }
+                let quoted = chars[start + 1..index].iter().collect();
+                tokens.push((Token::Quoted(quoted), start));
+                index += 1;
+            }
+            '<' | '>' | '=' => {
+                let pair = chars[start..chars.len().min(start + 2)]
+                    .iter()
+                    .collect::<String>();
+                let symbol = match pair.as_str() {
+                    "==" | "<>" | "<=" | ">=" => pair,
+                    _ => chars[start].to_string(),
+                };
+                index += symbol.len();
+                tokens.push((Token::Comparison(symbol), start));
+            }
+            _ => {
+                while index < chars.len() && is_word_char(chars[index]) {
+                    index += if chars[index] == '\\' { 2 } else { 1 };
+                }
+                index = index.min(chars.len());
+                let word = chars[start..index].iter().collect();
+                tokens.push((Token::Word(word), start));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Resolves backslash escapes. Unescaped `*` and `?` stay wildcards, which the catalog query
+/// language spells the same way; an escaped one makes the term a literal phrase.
+fn search_value(raw: &str) -> QueryValue {
+    let mut text = String::new();
+    let mut wildcard = false;
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            '*' | '?' => {
+                wildcard = true;
+                text.push(c);
+            }
+            c => text.push(c),
+        }
+    }
+    if wildcard {
+        QueryValue::Word(text)
+    } else {
+        QueryValue::Phrase(text)
+    }
+}
+
+/// Maps a CQL index onto a catalog field; `None` is the server's choice of title, author and ISBN.
+fn index_field(index: &str) -> Result<Option<QueryField>, CqlError> {
+    let index = index.to_ascii_lowercase();
+    let name = index
+        .rsplit_once('.')
+        .map_or(index.as_str(), |(_, name)| name);
+    match name {
+        "serverchoice" | "anywhere" | "keywords" => Ok(None),
+        "title" => Ok(Some(QueryField::Title)),
+        "creator" | "author" | "name" => Ok(Some(QueryField::Author)),
+        "isbn" => Ok(Some(QueryField::Isbn)),
+        "date" | "year" => Ok(Some(QueryField::Year)),
+        "subject" => Ok(Some(QueryField::Subject)),
+        _ => Err(CqlError::new(
+            UNSUPPORTED_INDEX,
+            format!("unsupported index `{index}`"),
+        )),
+    }
+}
+
+fn year_bound(value: &str, offset: i64, position: usize) -> Result<String, CqlError> {
+    let year = value
+        .parse::<i64>()
+        .ok()
+        .and_then(|year| year.checked_add(offset))
+        .ok_or_else(|| CqlError::syntax(position, format!("`{value}` is not a year")))?;
+    Ok(year.max(0).to_string())
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+    /// Levels of the tree above the clause being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.index + offset).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn peek_word(&self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Goes one level deeper into the tree, refusing to go past `MAX_CQL_DEPTH`.
+    fn descend(&mut self, position: usize) -> Result<(), CqlError> {
+        if self.depth == MAX_CQL_DEPTH {
+            return Err(CqlError::syntax(position, "query nests too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Boolean operators all bind equally tightly and associate to the left.
+    fn parse_scoped(&mut self) -> Result<QueryNode, CqlError> {
+        let depth = self.depth;
+        let mut node = self.parse_clause()?;
+        while let Some(operator) = self.peek_word() {
+            if !matches!(operator.as_str(), "and" | "or" | "not" | "prox") {
+                break;
+            }
+            let position = self.position();
+            self.next();
+            if operator == "prox" {
+                return Err(CqlError::new(
+                    UNSUPPORTED_BOOLEAN_OPERATOR,
+                    "unsupported boolean operator `prox`",
+                ));
+            }
+            if self.peek() == Some(&Token::Slash) {
+                return Err(CqlError::new(
+                    UNSUPPORTED_BOOLEAN_MODIFIER,
+                    format!("modifiers of `{operator}` are not supported"),
+                ));
+            }
+            let right = self.parse_clause()?;
+            node = match (operator.as_str(), node) {
+                ("and", QueryNode::And(mut nodes)) => {
+                    nodes.push(right);
+                    QueryNode::And(nodes)
+                }
+                ("or", QueryNode::Or(mut nodes)) => {
+                    nodes.push(right);
+                    QueryNode::Or(nodes)
+                }
+                ("not", QueryNode::And(mut nodes)) => {
+                    nodes.push(QueryNode::Not(Box::new(right)));
+                    QueryNode::And(nodes)
+                }
+                // Any other change of operator wraps everything so far one level deeper.
+                (operator, left) => {
+                    self.descend(position)?;
+                    match operator {
+                        "or" => QueryNode::Or(vec![left, right]),
+                        "not" => QueryNode::And(vec![left, QueryNode::Not(Box::new(right))]),
+                        _ => QueryNode::And(vec![left, right]),
+                    }
+                }
+            };
+        }
+        self.depth = depth;
+        Ok(node)
+    }
+
+    fn is_relation(token: Option<&Token>) -> bool {
+        match token {
+            Some(Token::Comparison(_)) => true,
+            Some(Token::Word(word)) => matches!(
+                word.to_ascii_lowercase().as_str(),
+                "exact" | "any" | "all" | "adj" | "within" | "encloses"
+            ),
+            _ => false,
+        }
+    }
+
+    fn parse_clause(&mut self) -> Result<QueryNode, CqlError> {
+        let position = self.position();
+        let (term, quoted) = match self.next() {
+            Some((Token::LeftParen, _)) => {
+                self.descend(position)?;
+                let node = self.parse_scoped()?;
+                self.depth -= 1;
+                return match self.next() {
+                    Some((Token::RightParen, _)) => Ok(node),
+                    _ => Err(CqlError::syntax(
+                        self.end,
+                        format!("expected `)` to close the group opened at position {position}"),
+                    )),
+                };
+            }
+            Some((Token::Word(word), _)) => (word, false),
+            Some((Token::Quoted(quoted), _)) => (quoted, true),
+            Some((token, _)) => {
+                return Err(CqlError::syntax(position, format!("unexpected {token:?}")))
+            }
+            None => return Err(CqlError::syntax(position, "unexpected end of query")),
+        };
+
+        // A relation followed by a term makes the first word an index; a lone word is a search
+        // term in its own right, even one spelled like a relation.
+        let indexed = !quoted
+            && Self::is_relation(self.peek())
+            && matches!(
+                self.peek_at(1),
+                Some(Token::Word(_) | Token::Quoted(_) | Token::Slash)
+            );
+        if !indexed {
+            if Self::is_relation(self.peek()) {
+                return Err(CqlError::syntax(self.position(), "expected a search term"));
+            }
+            return Ok(QueryNode::Term {
+                field: None,
+                value: search_value(&term),
+                position,
+            });
+        }
+
+        let relation = match self.next() {
+            Some((Token::Comparison(symbol), _)) => symbol,
+            Some((Token::Word(word), _)) => word.to_ascii_lowercase(),
+            _ => unreachable!("checked by is_relation"),
+        };
+        if self.peek() == Some(&Token::Slash) {
+            return Err(CqlError::new(
+                UNSUPPORTED_RELATION_MODIFIER,
+                format!("modifiers of `{relation}` are not supported"),
+            ));
+        }
+        let value_position = self.position();
+        let value = match self.next() {
+            Some((Token::Word(value) | Token::Quoted(value), _)) => value,
+            _ => return Err(CqlError::syntax(value_position, "expected a search term")),
+        };
+        Self::search_clause(&term, &relation, &value, value_position)
+    }
+
+    fn search_clause(
+        index: &str,
+        relation: &str,
+        value: &str,
+        position: usize,
+    ) -> Result<QueryNode, CqlError> {
+        if index.eq_ignore_ascii_case("cql.allRecords") {
+            return Ok(QueryNode::And(vec![]));
+        }
+        let field = index_field(index)?;
+        let term = |value: QueryValue| QueryNode::Term {
+            field,
+            value,
+            position,
+        };
+        let words = || {
+            value
+                .split_whitespace()
+                .map(|word| term(search_value(word)))
+        };
+
+        let year = field == Some(QueryField::Year);
+        match relation {
+            "=" | "==" | "exact" | "adj" => Ok(term(search_value(value))),
+            "any" => Ok(QueryNode::Or(words().collect())),
+            "all" => Ok(QueryNode::And(words().collect())),
+            "<>" => Ok(QueryNode::Not(Box::new(term(search_value(value))))),
+            // Ranges of the catalog language are inclusive, so strict bounds move by a year.
+            "<" | "<=" | ">" | ">=" if year => {
+                let bound = match relation {
+                    "<" => QueryValue::Range(None, Some(year_bound(value, -1, position)?)),
+                    "<=" => QueryValue::Range(None, Some(year_bound(value, 0, position)?)),
+                    ">" => QueryValue::Range(Some(year_bound(value, 1, position)?), None),
+                    _ => QueryValue::Range(Some(year_bound(value, 0, position)?), None),
+                };
+                Ok(term(bound))
+            }
+            "<=" | ">=" => Ok(term(match relation {
+                "<=" => QueryValue::Range(None, Some(value.to_string())),
+                _ => QueryValue::Range(Some(value.to_string()), None),
+            })),
+            _ => Err(CqlError::new(
+                UNSUPPORTED_RELATION,
+                format!("unsupported relation `{relation}` for index `{index}`"),
+            )),
+        }
+    }
+}
+
+/// Parses a CQL query into the same tree as the catalog query language, so both compile to SQL
+/// alike. Supports the `title`, `creator`, `isbn`, `date` and `subject` indexes of the `dc` and
+/// `bath` context sets, and `and`, `or` and `not`.
+pub fn parse_cql(query: &str) -> Result<QueryNode, CqlError> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        index: 0,
+        end: query.chars().count(),
+        depth: 0,
+    };
+    let node = parser.parse_scoped()?;
+    if parser.peek_word().as_deref() == Some("sortby") {
+        return Err(CqlError::new(SORT_NOT_SUPPORTED, "sortBy is not supported"));
+    }
+    if let Some((token, position)) = parser.next() {
+        return Err(CqlError::syntax(position, format!("unexpected {token:?}")));
+    }
+    Ok(node)
+}
+
+#[test]
+fn test_parse_cql() {
+    let term = |field, value, position| QueryNode::Term {
+        field,
+        value,
+        position,
+    };
+    let phrase = |text: &str| QueryValue::Phrase(text.to_string());
+
+    assert_eq!(parse_cql("dune"), Ok(term(None, phrase("dune"), 0)));
+    assert_eq!(
+        parse_cql(r#"dc.title = "the \"lost\" world""#),
+        Ok(term(
+            Some(QueryField::Title),
+            phrase("the \"lost\" world"),
+            11
+        ))
+    );
+    assert_eq!(
+        parse_cql("title=dune and (creator any \"herbert pratchett\" or bath.isbn=978044*)"),
+        Ok(QueryNode::And(vec![
+            term(Some(QueryField::Title), phrase("dune"), 6),
+            QueryNode::Or(vec![
+                term(Some(QueryField::Author), phrase("herbert"), 28),
+                term(Some(QueryField::Author), phrase("pratchett"), 28),
+                term(
+                    Some(QueryField::Isbn),
+                    QueryValue::Word("978044*".to_string()),
+                    61
+                ),
+            ]),
+        ]))
+    );
+    assert_eq!(
+        parse_cql("dc.date > 1979 NOT subject == poetry"),
+        Ok(QueryNode::And(vec![
+            term(
+                Some(QueryField::Year),
+                QueryValue::Range(Some("1980".to_string()), None),
+                10
+            ),
+            QueryNode::Not(Box::new(term(
+                Some(QueryField::Subject),
+                phrase("poetry"),
+                30
+            ))),
+        ]))
+    );
+    assert_eq!(parse_cql("cql.allRecords = 1"), Ok(QueryNode::And(vec![])));
+}
+
+#[test]
+fn test_parse_cql_errors() {
+    let diagnostic = |query: &str| parse_cql(query).unwrap_err().diagnostic;
+
+    assert_eq!(diagnostic(""), QUERY_SYNTAX_ERROR);
+    assert_eq!(diagnostic("title = \"dune"), QUERY_SYNTAX_ERROR);
+    assert_eq!(diagnostic("(title = dune"), QUERY_SYNTAX_ERROR);
+    assert_eq!(diagnostic("title ="), QUERY_SYNTAX_ERROR);
+    assert_eq!(diagnostic("date < soon"), QUERY_SYNTAX_ERROR);
+    assert_eq!(
+        diagnostic(&format!("date > {}", i64::MAX)),
+        QUERY_SYNTAX_ERROR
+    );
+    assert_eq!(
+        diagnostic(&format!("date < {}", i64::MIN)),
+        QUERY_SYNTAX_ERROR
+    );
+    assert!(parse_cql(&format!("date >= {}", i64::MAX)).is_ok());
+    assert_eq!(diagnostic("dc.publisher = tor"), UNSUPPORTED_INDEX);
+    assert_eq!(diagnostic("title within dune"), UNSUPPORTED_RELATION);
+    assert_eq!(
+        diagnostic("title =/stem dune"),
+        UNSUPPORTED_RELATION_MODIFIER
+    );
+    assert_eq!(
+        diagnostic("dune prox arrakis"),
+        UNSUPPORTED_BOOLEAN_OPERATOR
+    );
+    assert_eq!(
+        diagnostic("dune and/rel.algorithm=lr arrakis"),
+        UNSUPPORTED_BOOLEAN_MODIFIER
+    );
+    assert_eq!(diagnostic("dune sortBy title"), SORT_NOT_SUPPORTED);
+
+    let nested = |depth| format!("{}dune{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(parse_cql(&nested(MAX_CQL_DEPTH)).is_ok());
+    let error = parse_cql(&nested(MAX_CQL_DEPTH + 1)).unwrap_err();
+    assert_eq!(error.diagnostic, QUERY_SYNTAX_ERROR);
+    assert_eq!(error.message, "query nests too deeply at position 32");
+    assert_eq!(diagnostic(&"(".repeat(100_000)), QUERY_SYNTAX_ERROR);
+    // Alternating operators nest on the left just as groups do.
+    assert_eq!(
+        diagnostic(&format!("dune{}", " or dune and dune".repeat(100))),
+        QUERY_SYNTAX_ERROR
+    );
+    assert!(parse_cql(&format!("dune{}", " not dune".repeat(100))).is_ok());
+}
@@ -0,0 +1,98 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::request::sort::Sort;
+
+use super::LibraryErrorStatus;
+
+/// An opaque position in a sorted listing: the sort key and id of a book, and which side of it the
+/// requested page lies on. Clients receive it as URL-safe base64 JSON and must not build their own.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Cursor {
+    pub key: Value,
+    pub id: u64,
+    /// `true` when the page ends just before this book rather than starting just after it.
+    pub before: bool,
+    /// The order the cursor was issued for; resuming it under any other order is refused.
+    #[serde(default)]
+    pub sort: Option<Sort>,
+}
+
+impl Cursor {
+    pub fn after(key: Value, id: u64, sort: Option<Sort>) -> Self {
+        Self {
+            key,
+            id,
+            before: false,
+            sort,
+        }
+    }
+
+    pub fn before(key: Value, id: u64, sort: Option<Sort>) -> Self {
+        Self {
+            key,
+            id,
+            before: true,
+            sort,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, LibraryErrorStatus> {
+        let json = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| LibraryErrorStatus::CursorInvalid)?;
+        serde_json::from_slice(&json).map_err(|_| LibraryErrorStatus::CursorInvalid)
+    }
+}
+
+/// How many rows precede `page` (counted from 1) in a listing of `total` rows. Pages past the end,
+/// and pages so far out that the offset overflows, are refused.
+pub(super) fn page_offset(page: u64, per_page: u64, total: u64) -> Result<u64, LibraryErrorStatus> {
+    page.checked_sub(1)
+        .and_then(|skipped| skipped.checked_mul(per_page))
+        .filter(|offset| *offset <= total)
+        .ok_or(LibraryErrorStatus::PaginationInvalid)
+}
+
+#[test]
+fn test_page_offset() {
+    let invalid = |page| {
+        matches!(
+            page_offset(page, 50, 120),
+            Err(LibraryErrorStatus::PaginationInvalid)
+        )
+    };
+    assert_eq!(page_offset(1, 50, 0).unwrap(), 0);
+    assert_eq!(page_offset(3, 50, 120).unwrap(), 100);
+    assert!(invalid(0));
+    assert!(invalid(4));
+    assert!(invalid(u64::MAX));
+}
+
+#[test]
+fn test_cursor_round_trip() {
+    let cursor = Cursor::before(
+        Value::from("hitchhiker's guide"),
+        42,
+        Some("-title".parse().unwrap()),
+    );
+    let encoded = cursor.encode();
+    assert!(encoded
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+    assert!(matches!(
+        Cursor::decode("not a cursor"),
+        Err(LibraryErrorStatus::CursorInvalid)
+    ));
+    assert!(matches!(
+        Cursor::decode(&URL_SAFE_NO_PAD.encode("[1,2]")),
+        Err(LibraryErrorStatus::CursorInvalid)
+    ));
+}
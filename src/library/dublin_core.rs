@@ -0,0 +1,43 @@
+use quick_xml::escape::escape;
+
+use crate::orm::{book::Book, book_contributor::ContributorRole};
+
+use super::contributor::split_name;
+
+pub(super) const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// The simple Dublin Core elements describing `book`, each prefixed `dc:`. The caller wraps them
+/// in whatever container its protocol expects and binds the prefix to `DC_NAMESPACE`.
+pub(super) fn dc_elements(book: &Book, base_url: &str) -> String {
+    let mut elements = vec![("title", book.title.clone())];
+    for credit in &book.contributors {
+        let name = match split_name(&credit.name) {
+            (Some(given), family) => format!("{family}, {given}"),
+            (None, name) => name.to_string(),
+        };
+        let element = match credit.role {
+            ContributorRole::Author => "creator",
+            _ => "contributor",
+        };
+        elements.push((element, name));
+    }
+    elements.extend(
+        book.subjects
+            .iter()
+            .map(|subject| ("subject", subject.clone())),
+    );
+    elements.extend(
+        book.description
+            .iter()
+            .map(|description| ("description", description.clone())),
+    );
+    elements.push(("date", book.publication_year.to_string()));
+    elements.push(("type", "Text".to_string()));
+    elements.push(("identifier", format!("urn:isbn:{}", book.isbn)));
+    elements.push(("identifier", format!("{base_url}/books/{}", book.id)));
+
+    elements
+        .into_iter()
+        .map(|(element, value)| format!("<dc:{element}>{}</dc:{element}>", escape(&value)))
+        .collect()
+}
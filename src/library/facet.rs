@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use log::warn;
+use sea_orm::{
+    sea_query::{Alias, Expr, Order, Query, SelectStatement, SimpleExpr},
+    ColumnTrait, Condition, ConnectionTrait, QueryResult,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::orm::{
+    book,
+    book_contributor::{self, ContributorRole},
+    book_subject, contributor,
+    copy::{self, CopyStatus},
+};
+
+use super::{Library, LibraryErrorStatus};
+
+// Author and subject lists are long-tailed; a sidebar only needs the most common values.
+const FACET_LIMIT: u64 = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FacetBucket {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Facets {
+    pub author: Vec<FacetBucket>,
+    pub decade: Vec<FacetBucket>,
+    pub subject: Vec<FacetBucket>,
+    pub availability: Vec<FacetBucket>,
+}
+
+fn matching_books(condition: &Condition) -> SelectStatement {
+    Query::select()
+        .column((book::Entity, book::Column::Id))
+        .from(book::Entity)
+        .cond_where(condition.clone())
+        .to_owned()
+}
+
+fn available_books() -> SelectStatement {
+    Query::select()
+        .column(copy::Column::Book)
+        .from(copy::Entity)
+        .and_where(copy::Column::Status.eq(CopyStatus::Available))
+        .to_owned()
+}
+
+/// Folds per-year counts into decades, oldest first.
+fn decade_buckets(years: impl IntoIterator<Item = (u64, u64)>) -> Vec<FacetBucket> {
+    let mut decades = BTreeMap::<u64, u64>::new();
+    for (year, count) in years {
+        *decades.entry(year - year % 10).or_default() += count;
+    }
+    decades
+        .into_iter()
+        .map(|(decade, count)| FacetBucket {
+            value: decade.to_string(),
+            count,
+        })
+        .collect()
+}
+
+pub(super) fn count_of(row: &QueryResult) -> Result<u64, LibraryErrorStatus> {
+    let count = row.try_get::<i64>("", "count");
+    if let Err(error) = &count {
+        warn!("failed to read facet count: {error}");
+        return Err(LibraryErrorStatus::DatabaseError);
+    }
+    Ok(count.unwrap() as u64)
+}
+
+impl Library {
+    pub(super) async fn query_facet<C: ConnectionTrait>(
+        statement: &SelectStatement,
+        database: &C,
+    ) -> Result<Vec<QueryResult>, LibraryErrorStatus> {
+        let db_result = database
+            .query_all(database.get_database_backend().build(statement))
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to compute facet: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    async fn named_facet<C: ConnectionTrait>(
+        statement: &SelectStatement,
+        database: &C,
+    ) -> Result<Vec<FacetBucket>, LibraryErrorStatus> {
+        let mut buckets = vec![];
+        for row in Self::query_facet(statement, database).await? {
+            let value = row.try_get::<String>("", "value");
+            if let Err(error) = &value {
+                warn!("failed to read facet value: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            buckets.push(FacetBucket {
+                value: value.unwrap(),
+                count: count_of(&row)?,
+            });
+        }
+        Ok(buckets)
+    }
+
+    /// Counts every book matching `condition`, not just the requested page.
+    pub(super) async fn facets<C: ConnectionTrait>(
+        condition: &Condition,
+        database: &C,
+    ) -> Result<Facets, LibraryErrorStatus> {
+        let value = Alias::new("value");
+        let count = Alias::new("count");
+
+        let author = Query::select()
+            .expr_as(
+                Expr::col((contributor::Entity, contributor::Column::Name)),
+                value.clone(),
+            )
+            .expr_as(
+                Expr::col((book_contributor::Entity, book_contributor::Column::Book))
+                    .count_distinct(),
+                count.clone(),
+            )
+            .from(book_contributor::Entity)
+            .inner_join(
+                contributor::Entity,
+                Expr::col((contributor::Entity, contributor::Column::Id)).equals((
+                    book_contributor::Entity,
+                    book_contributor::Column::Contributor,
+                )),
+            )
+            .and_where(book_contributor::Column::Role.eq(ContributorRole::Author))
+            .and_where(book_contributor::Column::Book.in_subquery(matching_books(condition)))
+            .group_by_col((contributor::Entity, contributor::Column::Name))
+            .order_by(count.clone(), Order::Desc)
+            .order_by(value.clone(), Order::Asc)
+            .limit(FACET_LIMIT)
+            .to_owned();
+
+        let subject = Query::select()
+            .expr_as(
+                Expr::col((book_subject::Entity, book_subject::Column::Subject)),
+                value.clone(),
+            )
+            .expr_as(
+                Expr::col((book_subject::Entity, book_subject::Column::Book)).count_distinct(),
+                count.clone(),
+            )
+            .from(book_subject::Entity)
+            .and_where(book_subject::Column::Book.in_subquery(matching_books(condition)))
+            .group_by_col((book_subject::Entity, book_subject::Column::Subject))
+            .order_by(count.clone(), Order::Desc)
+            .order_by(value.clone(), Order::Asc)
+            .limit(FACET_LIMIT)
+            .to_owned();
+
+        let years = Query::select()
+            .expr_as(
+                Expr::col((book::Entity, book::Column::PublicationYear)),
+                value.clone(),
+            )
+            .expr_as(
+                Expr::col((book::Entity, book::Column::Id)).count(),
+                count.clone(),
+            )
+            .from(book::Entity)
+            .cond_where(condition.clone())
+            .group_by_col((book::Entity, book::Column::PublicationYear))
+            .to_owned();
+        let mut year_counts = vec![];
+        for row in Self::query_facet(&years, database).await? {
+            let year = row.try_get::<u64>("", "value");
+            if let Err(error) = &year {
+                warn!("failed to read facet value: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            year_counts.push((year.unwrap(), count_of(&row)?));
+        }
+        let decade = decade_buckets(year_counts);
+
+        let total = Query::select()
+            .expr_as(
+                Expr::col((book::Entity, book::Column::Id)).count(),
+                count.clone(),
+            )
+            .from(book::Entity)
+            .cond_where(condition.clone())
+            .to_owned();
+        let available = Query::select()
+            .expr_as(
+                Expr::col((book::Entity, book::Column::Id)).count(),
+                count.clone(),
+            )
+            .from(book::Entity)
+            .cond_where(condition.clone())
+            .and_where(book::Column::Id.in_subquery(available_books()))
+            .to_owned();
+        let mut counts = vec![];
+        for statement in [&total, &available] {
+            let rows = Self::query_facet(statement, database).await?;
+            counts.push(match rows.first() {
+                Some(row) => count_of(row)?,
+                None => 0,
+            });
+        }
+        let availability = [
+            ("available", counts[1]),
+            ("unavailable", counts[0].saturating_sub(counts[1])),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(value, count)| FacetBucket {
+            value: value.to_string(),
+            count,
+        })
+        .collect();
+
+        Ok(Facets {
+            author: Self::named_facet(&author, database).await?,
+            decade,
+            subject: Self::named_facet(&subject, database).await?,
+            availability,
+        })
+    }
+}
+
+/// The subquery used by `BookSearch::available`; shared so facet counts and filters agree.
+pub(super) fn available_condition(available: bool) -> SimpleExpr {
+    if available {
+        book::Column::Id.in_subquery(available_books())
+    } else {
+        book::Column::Id.not_in_subquery(available_books())
+    }
+}
+
+#[test]
+fn test_decade_buckets() {
+    assert_eq!(
+        decade_buckets([(1979, 2), (1984, 1), (1970, 3), (2001, 1)]),
+        vec![
+            FacetBucket {
+                value: "1970".to_string(),
+                count: 5
+            },
+            FacetBucket {
+                value: "1980".to_string(),
+                count: 1
+            },
+            FacetBucket {
+                value: "2000".to_string(),
+                count: 1
+            },
+        ]
+    );
+    assert!(decade_buckets([]).is_empty());
+}
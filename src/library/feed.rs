@@ -0,0 +1,266 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::warn;
+use quick_xml::escape::escape;
+use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+use crate::{
+    model::request::search::BookSearch,
+    orm::{
+        book::{self, Book},
+        book_contributor::ContributorRole,
+        book_tombstone,
+    },
+};
+
+use super::{search_condition, Library, LibraryErrorStatus};
+
+/// Books per feed; older arrivals drop off the end.
+pub const FEED_SIZE: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Atom => "application/atom+xml; charset=utf-8",
+            Self::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Names the catalog in feeds and anchors the links to its books.
+#[derive(Debug, Clone)]
+pub struct FeedChannel {
+    pub name: String,
+    /// The public URL of the server, without a trailing slash.
+    pub base_url: String,
+}
+
+/// The newest books matching a search, as served in a feed.
+#[derive(Debug, Clone)]
+pub struct NewArrivals {
+    pub books: Vec<Book>,
+    /// When the feed last changed: the latest edit to one of its books, or the latest deletion
+    /// of any book, whichever came last.
+    pub last_modified: DateTime<Utc>,
+}
+
+/// "By Douglas Adams, 1980", from the authors credited on `book`.
+fn byline(book: &Book) -> String {
+    let authors = authors(book).collect::<Vec<_>>();
+    if authors.is_empty() {
+        return book.publication_year.to_string();
+    }
+    format!("By {}, {}", authors.join(", "), book.publication_year)
+}
+
+fn authors(book: &Book) -> impl Iterator<Item = &str> {
+    book.contributors
+        .iter()
+        .filter(|credit| credit.role == ContributorRole::Author)
+        .map(|credit| credit.name.as_str())
+}
+
+impl NewArrivals {
+    /// Serializes the feed; `self_url` is the address it was requested at, search included.
+    pub fn render(&self, format: FeedFormat, channel: &FeedChannel, self_url: &str) -> String {
+        match format {
+            FeedFormat::Atom => self.to_atom(channel, self_url),
+            FeedFormat::Rss => self.to_rss(channel, self_url),
+        }
+    }
+
+    fn to_atom(&self, channel: &FeedChannel, self_url: &str) -> String {
+        let timestamp = |time: &DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n  \
+             <id>{0}</id>\n  \
+             <title>{1}: New arrivals</title>\n  \
+             <updated>{2}</updated>\n  \
+             <author>\n    <name>{1}</name>\n    <uri>{3}</uri>\n  </author>\n  \
+             <link rel=\"self\" href=\"{0}\" type=\"application/atom+xml\"/>\n  \
+             <link rel=\"alternate\" href=\"{3}\"/>\n",
+            escape(self_url),
+            escape(&channel.name),
+            timestamp(&self.last_modified),
+            escape(&channel.base_url),
+        );
+        for book in &self.books {
+            let url = format!("{}/books/{}", channel.base_url, book.id);
+            xml.push_str(&format!(
+                "  <entry>\n    \
+                 <id>{0}</id>\n    \
+                 <title>{1}</title>\n    \
+                 <published>{2}</published>\n    \
+                 <updated>{3}</updated>\n    \
+                 <link rel=\"alternate\" href=\"{0}\"/>\n    \
+                 <summary>{4}</summary>\n",
+                escape(&url),
+                escape(&book.title),
+                timestamp(&book.created_at),
+                timestamp(&book.updated_at),
+                escape(byline(book)),
+            ));
+            for author in authors(book) {
+                xml.push_str(&format!(
+                    "    <author>\n      <name>{}</name>\n    </author>\n",
+                    escape(author)
+                ));
+            }
+            for subject in &book.subjects {
+                xml.push_str(&format!("    <category term=\"{}\"/>\n", escape(subject)));
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn to_rss(&self, channel: &FeedChannel, self_url: &str) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+             <channel>\n  \
+             <title>{0}: New arrivals</title>\n  \
+             <link>{1}</link>\n  \
+             <description>Books recently added to the {0} catalog</description>\n  \
+             <lastBuildDate>{2}</lastBuildDate>\n  \
+             <atom:link rel=\"self\" href=\"{3}\" type=\"application/rss+xml\"/>\n",
+            escape(&channel.name),
+            escape(&channel.base_url),
+            self.last_modified.to_rfc2822(),
+            escape(self_url),
+        );
+        for book in &self.books {
+            let url = format!("{}/books/{}", channel.base_url, book.id);
+            xml.push_str(&format!(
+                "  <item>\n    \
+                 <title>{1}</title>\n    \
+                 <link>{0}</link>\n    \
+                 <guid isPermaLink=\"true\">{0}</guid>\n    \
+                 <pubDate>{2}</pubDate>\n    \
+                 <description>{3}</description>\n",
+                escape(&url),
+                escape(&book.title),
+                book.created_at.to_rfc2822(),
+                escape(byline(book)),
+            ));
+            // RSS reserves `author` for e-mail addresses, so names go in `dc:creator`.
+            for author in authors(book) {
+                xml.push_str(&format!(
+                    "    <dc:creator>{}</dc:creator>\n",
+                    escape(author)
+                ));
+            }
+            for subject in &book.subjects {
+                xml.push_str(&format!("    <category>{}</category>\n", escape(subject)));
+            }
+            xml.push_str("  </item>\n");
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+}
+
+impl Library {
+    /// The `FEED_SIZE` most recently added books matching `search`, newest first.
+    pub async fn new_arrivals(
+        &self,
+        search: &BookSearch,
+        database: &DatabaseConnection,
+    ) -> Result<NewArrivals, LibraryErrorStatus> {
+        let db_result = book::Entity::find()
+            .filter(search_condition(search)?)
+            .order_by_desc(book::Column::CreatedAt)
+            .order_by_desc(book::Column::Id)
+            .limit(FEED_SIZE)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch new arrivals: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+
+        // A deleted book may have been in the feed, so any deletion counts as a change.
+        let db_result = book_tombstone::Entity::find()
+            .order_by_desc(book_tombstone::Column::DeletedAt)
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch latest tombstone: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let last_deleted = db_result.unwrap().map(|tombstone| tombstone.deleted_at);
+
+        let last_modified = books
+            .iter()
+            .map(|book| book.updated_at)
+            .chain(last_deleted)
+            .max()
+            .unwrap_or_default();
+        Ok(NewArrivals {
+            books,
+            last_modified,
+        })
+    }
+}
+
+#[test]
+fn test_new_arrivals_feeds() {
+    use crate::orm::book_contributor::Credit;
+
+    let time = |time| DateTime::parse_from_rfc3339(time).unwrap().into();
+    let book = Book {
+        id: 3,
+        title: "Pride & Prejudice".to_string(),
+        author: "Jane Austen".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![Credit {
+            name: "Jane Austen".to_string(),
+            role: ContributorRole::Author,
+        }],
+        publication_year: 1813,
+        subjects: vec!["Courtship".to_string()],
+        isbn: "9780141439518".to_string(),
+        description: None,
+        created_at: time("2024-03-01T10:20:30Z"),
+        updated_at: time("2024-03-02T08:00:00Z"),
+    };
+    let arrivals = NewArrivals {
+        books: vec![book],
+        last_modified: time("2024-03-02T08:00:00Z"),
+    };
+    let channel = FeedChannel {
+        name: "ABLE".to_string(),
+        base_url: "https://library.example.org".to_string(),
+    };
+    let self_url = "https://library.example.org/feeds/new.atom?subject=Courtship&decade=1810";
+
+    let atom = arrivals.render(FeedFormat::Atom, &channel, self_url);
+    assert!(atom.contains(
+        "<link rel=\"self\" href=\"https://library.example.org/feeds/new.atom?subject=Courtship\
+         &amp;decade=1810\" type=\"application/atom+xml\"/>"
+    ));
+    assert!(atom.contains("<updated>2024-03-02T08:00:00Z</updated>"));
+    assert!(atom.contains("<id>https://library.example.org/books/3</id>"));
+    assert!(atom.contains("<title>Pride &amp; Prejudice</title>"));
+    assert!(atom.contains("<published>2024-03-01T10:20:30Z</published>"));
+    assert!(atom.contains("<summary>By Jane Austen, 1813</summary>"));
+    assert!(atom.contains("<category term=\"Courtship\"/>"));
+
+    let rss = arrivals.render(FeedFormat::Rss, &channel, self_url);
+    assert!(rss.contains("<lastBuildDate>Sat, 2 Mar 2024 08:00:00 +0000</lastBuildDate>"));
+    assert!(rss.contains("<guid isPermaLink=\"true\">https://library.example.org/books/3</guid>"));
+    assert!(rss.contains("<pubDate>Fri, 1 Mar 2024 10:20:30 +0000</pubDate>"));
+    assert!(rss.contains("<dc:creator>Jane Austen</dc:creator>"));
+    assert!(rss.contains("<category>Courtship</category>"));
+}
@@ -0,0 +1,238 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use log::warn;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+};
+use strsim::normalized_damerau_levenshtein;
+
+use crate::{
+    model::request::{pagination::Pagination, search::BookSearch},
+    orm::book::{self, Book},
+};
+
+use super::{cursor::page_offset, search_condition, BookPage, Library, LibraryErrorStatus};
+
+const FUZZY_CANDIDATES: usize = 200;
+const MAX_SUGGESTIONS: usize = 5;
+const SUGGESTION_THRESHOLD: f64 = 0.6;
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Scores how closely `text` matches `query` from 0 to 1 by pairing every query word with its
+/// closest word in `text`, so word order and extra words in `text` do not count against it.
+pub fn similarity(query: &str, text: &str) -> f64 {
+    let query = words(query);
+    let text = words(text);
+    if query.is_empty() || text.is_empty() {
+        return 0.0;
+    }
+    query
+        .iter()
+        .map(|query_word| {
+            text.iter()
+                .map(|word| normalized_damerau_levenshtein(query_word, word))
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>()
+        / query.len() as f64
+}
+
+/// The closest credited name to `query`, falling back to the display author.
+fn best_author<'a>(query: &str, book: &'a Book) -> (&'a str, f64) {
+    book.contributors
+        .iter()
+        .map(|credit| credit.name.as_str())
+        .chain([book.author.as_str()])
+        .map(|name| (name, similarity(query, name)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .unwrap_or_default()
+}
+
+fn score(search: &BookSearch, book: &Book) -> f64 {
+    let mut scores = vec![];
+    if let Some(title) = &search.title {
+        scores.push(similarity(title, &book.title));
+    }
+    if let Some(author) = &search.author {
+        scores.push(best_author(author, book).1);
+    }
+    scores.iter().sum::<f64>() / scores.len().max(1) as f64
+}
+
+/// Picks distinct titles and names close enough to what was typed to offer as "did you mean".
+fn suggestions(search: &BookSearch, ranked: &[Book]) -> Vec<String> {
+    let mut suggestions = vec![];
+    for book in ranked {
+        if let Some(title) = &search.title {
+            if similarity(title, &book.title) >= SUGGESTION_THRESHOLD {
+                suggestions.push(book.title.clone());
+            }
+        }
+        if let Some(author) = &search.author {
+            let (name, score) = best_author(author, book);
+            if score >= SUGGESTION_THRESHOLD {
+                suggestions.push(name.to_string());
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    suggestions.retain(|suggestion| seen.insert(suggestion.clone()));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+impl Library {
+    pub(super) async fn get_books_fuzzy(
+        &self,
+        database: &DatabaseConnection,
+        pagination: Pagination,
+        search: BookSearch,
+    ) -> Result<BookPage, LibraryErrorStatus> {
+        let Some(search_index) = &self.search_index else {
+            warn!("fuzzy search requested without a search index");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        };
+
+        let candidates = search_index.fuzzy_candidates(
+            search.title.as_deref(),
+            search.author.as_deref(),
+            FUZZY_CANDIDATES,
+        );
+        if let Err(error) = &candidates {
+            warn!("failed to search index: {error}");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        }
+
+        // Title and author are matched loosely through the index; the remaining filters still apply.
+        let strict = BookSearch {
+            title: None,
+            author: None,
+            ..search.clone()
+        };
+        let condition = Condition::all()
+            .add(book::Column::Id.is_in(candidates.unwrap()))
+            .add(search_condition(&strict)?);
+        let db_result = book::Entity::find()
+            .filter(condition.clone())
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+
+        let mut ranked = books
+            .into_iter()
+            .map(|book| (score(&search, &book), book))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(Ordering::Equal)
+                .then(a.1.id.cmp(&b.1.id))
+        });
+        let ranked = ranked.into_iter().map(|(_, book)| book).collect::<Vec<_>>();
+
+        let exact_hits = book::Entity::find()
+            .filter(search_condition(&search)?)
+            .count(database)
+            .await;
+        if let Err(error) = &exact_hits {
+            warn!("failed to count books: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let suggestions = if exact_hits.unwrap() == 0 {
+            suggestions(&search, &ranked)
+        } else {
+            vec![]
+        };
+
+        // Ranking happens in memory, so fuzzy results are paged by offset and carry no cursors.
+        let page = pagination.page();
+        let per_page = pagination.per_page();
+        if per_page == 0 {
+            return Err(LibraryErrorStatus::PaginationInvalid);
+        }
+        let total = ranked.len() as u64;
+        let start = page_offset(page as u64, per_page as u64, total)? as usize;
+
+        let facets = if search.facets == Some(true) {
+            Some(Self::facets(&condition, database).await?)
+        } else {
+            None
+        };
+        Ok(BookPage {
+            books: ranked.into_iter().skip(start).take(per_page).collect(),
+            total,
+            next_cursor: None,
+            prev_cursor: None,
+            suggestions,
+            facets,
+        })
+    }
+}
+
+#[test]
+fn test_similarity() {
+    assert_eq!(similarity("Douglas Adams", "Douglas Adams"), 1.0);
+    assert_eq!(similarity("adams", "Douglas Adams"), 1.0);
+    assert!(similarity("Douglass Adms", "Douglas Adams") > 0.8);
+    assert!(
+        similarity("Douglass Adms", "Douglas Adams")
+            > similarity("Douglass Adms", "Douglas Hofstadter")
+    );
+    assert_eq!(similarity("", "Douglas Adams"), 0.0);
+}
+
+#[test]
+fn test_suggestions() {
+    use crate::orm::book_contributor::{ContributorRole, Credit};
+
+    let book = |id, title: &str, author: &str| Book {
+        id,
+        title: title.to_string(),
+        author: author.to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![Credit {
+            name: author.to_string(),
+            role: ContributorRole::Author,
+        }],
+        publication_year: 1979,
+        subjects: vec![],
+        isbn: String::new(),
+        description: None,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+    let search = BookSearch {
+        title: None,
+        author: Some("Douglass Adms".to_string()),
+        role: None,
+        isbn: None,
+        fuzzy: Some(true),
+        subject: None,
+        decade: None,
+        available: None,
+        facets: None,
+        query: None,
+    };
+    let ranked = [
+        book(1, "The Hitchhiker's Guide to the Galaxy", "Douglas Adams"),
+        book(
+            2,
+            "The Restaurant at the End of the Universe",
+            "Douglas Adams",
+        ),
+        book(3, "Gödel, Escher, Bach", "Douglas Hofstadter"),
+    ];
+    assert_eq!(suggestions(&search, &ranked), vec!["Douglas Adams"]);
+}
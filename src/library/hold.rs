@@ -0,0 +1,473 @@
+use chrono::{TimeDelta, Utc};
+use log::{info, trace, warn};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+
+use crate::orm::{
+    copy::{self, BookCopy, CopyStatus},
+    hold::{self, Hold, HoldStatus},
+    user,
+};
+
+use super::{Library, LibraryErrorStatus};
+
+const HOLD_PICKUP_DAYS: i64 = 7;
+
+/// Where a new hold joins the queue of a title whose last waiting hold is at `last`.
+fn next_position(last: Option<u32>) -> u32 {
+    last.unwrap_or(0) + 1
+}
+
+/// The waiting hold first in line: lowest position, then earliest placed.
+fn next_in_line(queue: &[Hold]) -> Option<&Hold> {
+    queue
+        .iter()
+        .filter(|hold| hold.status == HoldStatus::Waiting)
+        .min_by_key(|hold| (hold.position, hold.id))
+}
+
+/// The holds of `queue` that move up once the hold at `vacated` leaves it, with their new
+/// positions.
+fn close_gap(queue: &[Hold], vacated: u32) -> Vec<(u64, u32)> {
+    queue
+        .iter()
+        .filter(|hold| hold.status == HoldStatus::Waiting && hold.position > vacated)
+        .map(|hold| (hold.id, hold.position - 1))
+        .collect()
+}
+
+/// Whether a copy on the hold shelf, reserved by `ready`, may go to `borrower`.
+fn held_for(ready: Option<&Hold>, borrower: u64) -> bool {
+    ready.is_some_and(|hold| hold.patron == borrower)
+}
+
+fn is_open(status: HoldStatus) -> bool {
+    matches!(status, HoldStatus::Waiting | HoldStatus::Ready)
+}
+
+impl Library {
+    pub async fn get_holds(
+        &mut self,
+        book_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<Hold>, LibraryErrorStatus> {
+        self.get_book_by_id(book_id, database).await?;
+
+        let db_result = hold::Entity::find()
+            .filter(hold::Column::Book.eq(book_id))
+            .filter(hold::Column::Status.is_in([HoldStatus::Ready, HoldStatus::Waiting]))
+            .order_by_asc(hold::Column::Position)
+            .order_by_asc(hold::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch holds: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn hold_queue_length(
+        &self,
+        book_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<u64, LibraryErrorStatus> {
+        let db_result = hold::Entity::find()
+            .filter(hold::Column::Book.eq(book_id))
+            .filter(hold::Column::Status.eq(HoldStatus::Waiting))
+            .count(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to count holds: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn place_hold(
+        &mut self,
+        book_id: u64,
+        patron: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Hold, LibraryErrorStatus> {
+        self.get_book_by_id(book_id, database).await?;
+
+        let patron_result = user::Entity::find_by_id(patron).one(database).await;
+        if let Err(error) = &patron_result {
+            warn!("failed to fetch patron: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if patron_result.unwrap().is_none() {
+            return Err(LibraryErrorStatus::UserNotFound);
+        }
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin hold transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        let existing = hold::Entity::find()
+            .filter(hold::Column::Book.eq(book_id))
+            .filter(hold::Column::Patron.eq(patron))
+            .filter(hold::Column::Status.is_in([HoldStatus::Ready, HoldStatus::Waiting]))
+            .count(&txn)
+            .await;
+        if let Err(error) = &existing {
+            warn!("failed to query existing holds: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if existing.unwrap() != 0 {
+            warn!("refusing to place duplicate hold: {patron} -> {book_id}");
+            return Err(LibraryErrorStatus::HoldExists);
+        }
+
+        let last_position = hold::Entity::find()
+            .select_only()
+            .column_as(hold::Column::Position.max(), "position")
+            .filter(hold::Column::Book.eq(book_id))
+            .filter(hold::Column::Status.eq(HoldStatus::Waiting))
+            .into_tuple::<Option<u32>>()
+            .one(&txn)
+            .await;
+        if let Err(error) = &last_position {
+            warn!("failed to query hold queue: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let position = next_position(last_position.unwrap().flatten());
+
+        trace!("inserting hold to db");
+        let hold = hold::ActiveModel {
+            id: ActiveValue::NotSet,
+            book: ActiveValue::Set(book_id),
+            patron: ActiveValue::Set(patron),
+            position: ActiveValue::Set(position),
+            status: ActiveValue::Set(HoldStatus::Waiting),
+            copy: ActiveValue::Set(None),
+            placed_at: ActiveValue::Set(Utc::now()),
+            pickup_expires_at: ActiveValue::Set(None),
+        }
+        .insert(&txn)
+        .await;
+        if let Err(error) = &hold {
+            warn!("failed to insert hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut hold = hold.unwrap();
+
+        // A copy may already be sitting on the shelf, in which case the queue is empty and it goes straight to this hold.
+        let available = copy::Entity::find()
+            .filter(copy::Column::Book.eq(book_id))
+            .filter(copy::Column::Status.eq(CopyStatus::Available))
+            .one(&txn)
+            .await;
+        if let Err(error) = &available {
+            warn!("failed to query available copies: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if let Some(copy) = available.unwrap() {
+            if let Some(assigned) = Self::assign_copy_to_next_hold(copy, &txn).await? {
+                hold = assigned;
+            }
+        }
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(hold)
+    }
+
+    pub async fn get_hold(
+        &self,
+        book_id: u64,
+        hold_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Hold, LibraryErrorStatus> {
+        Self::find_hold(book_id, hold_id, database).await
+    }
+
+    pub async fn cancel_hold(
+        &self,
+        book_id: u64,
+        hold_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Hold, LibraryErrorStatus> {
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin hold transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        let hold = Self::find_hold(book_id, hold_id, &txn).await?;
+        let hold = Self::close_hold(hold, HoldStatus::Cancelled, &txn).await?;
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit hold cancellation: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(hold)
+    }
+
+    pub async fn expire_holds(
+        &self,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = hold::Entity::find()
+            .filter(hold::Column::Status.eq(HoldStatus::Ready))
+            .filter(hold::Column::PickupExpiresAt.lt(Utc::now()))
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch expired holds: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        for hold in db_result.unwrap() {
+            info!("expiring hold {} for patron {}", hold.id, hold.patron);
+            let txn = database.begin().await;
+            if let Err(error) = &txn {
+                warn!("failed to begin hold transaction: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            let txn = txn.unwrap();
+
+            Self::close_hold(hold, HoldStatus::Expired, &txn).await?;
+
+            if let Err(error) = txn.commit().await {
+                warn!("failed to commit hold expiry: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+        }
+        Ok(())
+    }
+
+    /// Hands `copy` to the first waiting hold on its title and puts it on the hold shelf.
+    /// Returns the now-ready hold, or `None` if nobody is waiting.
+    pub(super) async fn assign_copy_to_next_hold<C: ConnectionTrait>(
+        copy: BookCopy,
+        database: &C,
+    ) -> Result<Option<Hold>, LibraryErrorStatus> {
+        let queue = Self::waiting_queue(copy.book, database).await?;
+        let Some(next) = next_in_line(&queue).cloned() else {
+            return Ok(None);
+        };
+        let position = next.position;
+
+        trace!("assigning copy {} to hold {}", copy.id, next.id);
+        let mut active = next.into_active_model();
+        active.status = ActiveValue::Set(HoldStatus::Ready);
+        active.position = ActiveValue::Set(0);
+        active.copy = ActiveValue::Set(Some(copy.id));
+        active.pickup_expires_at =
+            ActiveValue::Set(Some(Utc::now() + TimeDelta::days(HOLD_PICKUP_DAYS)));
+        let hold = active.update(database).await;
+        if let Err(error) = &hold {
+            warn!("failed to update hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let hold = hold.unwrap();
+
+        Self::shift_queue(hold.book, position, database).await?;
+        Self::set_copy_status(copy, CopyStatus::OnHoldShelf, database).await?;
+        Ok(Some(hold))
+    }
+
+    /// Marks the ready hold waiting on `copy` as fulfilled by a checkout to `borrower`.
+    pub(super) async fn fulfill_hold<C: ConnectionTrait>(
+        copy: &BookCopy,
+        borrower: u64,
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        let ready = hold::Entity::find()
+            .filter(hold::Column::Copy.eq(copy.id))
+            .filter(hold::Column::Status.eq(HoldStatus::Ready))
+            .one(database)
+            .await;
+        if let Err(error) = &ready {
+            warn!("failed to fetch ready hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let ready = ready.unwrap();
+        if !held_for(ready.as_ref(), borrower) {
+            warn!(
+                "refusing to check out copy held for another patron: {}",
+                copy.id
+            );
+            return Err(LibraryErrorStatus::CopyOnHold);
+        }
+
+        let mut active = ready.unwrap().into_active_model();
+        active.status = ActiveValue::Set(HoldStatus::Fulfilled);
+        if let Err(error) = active.update(database).await {
+            warn!("failed to fulfill hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(())
+    }
+
+    async fn find_hold<C: ConnectionTrait>(
+        book_id: u64,
+        hold_id: u64,
+        database: &C,
+    ) -> Result<Hold, LibraryErrorStatus> {
+        let db_result = hold::Entity::find_by_id(hold_id)
+            .filter(hold::Column::Book.eq(book_id))
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let hold = db_result.unwrap();
+        if hold.is_none() {
+            return Err(LibraryErrorStatus::HoldNotFound);
+        }
+        Ok(hold.unwrap())
+    }
+
+    async fn close_hold<C: ConnectionTrait>(
+        hold: Hold,
+        status: HoldStatus,
+        database: &C,
+    ) -> Result<Hold, LibraryErrorStatus> {
+        let previous_status = hold.status;
+        let position = hold.position;
+        let copy_id = hold.copy;
+        if !is_open(previous_status) {
+            return Err(LibraryErrorStatus::HoldClosed);
+        }
+
+        let mut active = hold.into_active_model();
+        active.status = ActiveValue::Set(status);
+        active.position = ActiveValue::Set(0);
+        let hold = active.update(database).await;
+        if let Err(error) = &hold {
+            warn!("failed to close hold: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let hold = hold.unwrap();
+
+        if previous_status == HoldStatus::Waiting {
+            Self::shift_queue(hold.book, position, database).await?;
+            return Ok(hold);
+        }
+
+        // The hold had a copy waiting on the shelf; pass it along to the next patron in line.
+        if let Some(copy_id) = copy_id {
+            let copy = Self::find_copy(copy_id, database).await?;
+            if copy.status == CopyStatus::OnHoldShelf {
+                let copy = Self::set_copy_status(copy, CopyStatus::Available, database).await?;
+                Self::assign_copy_to_next_hold(copy, database).await?;
+            }
+        }
+        Ok(hold)
+    }
+
+    async fn shift_queue<C: ConnectionTrait>(
+        book_id: u64,
+        vacated_position: u32,
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        let queue = Self::waiting_queue(book_id, database).await?;
+        for (hold_id, position) in close_gap(&queue, vacated_position) {
+            let db_result = hold::Entity::update_many()
+                .col_expr(hold::Column::Position, Expr::value(position))
+                .filter(hold::Column::Id.eq(hold_id))
+                .exec(database)
+                .await;
+            if let Err(error) = &db_result {
+                warn!("failed to shift hold queue: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+        }
+        Ok(())
+    }
+
+    async fn waiting_queue<C: ConnectionTrait>(
+        book_id: u64,
+        database: &C,
+    ) -> Result<Vec<Hold>, LibraryErrorStatus> {
+        let db_result = hold::Entity::find()
+            .filter(hold::Column::Book.eq(book_id))
+            .filter(hold::Column::Status.eq(HoldStatus::Waiting))
+            .order_by_asc(hold::Column::Position)
+            .order_by_asc(hold::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch hold queue: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+}
+
+#[cfg(test)]
+fn test_hold(id: u64, patron: u64, position: u32, status: HoldStatus) -> Hold {
+    Hold {
+        id,
+        book: 1,
+        patron,
+        position,
+        status,
+        copy: None,
+        placed_at: Utc::now(),
+        pickup_expires_at: None,
+    }
+}
+
+#[test]
+fn test_hold_queue_positions() {
+    assert_eq!(next_position(None), 1);
+    assert_eq!(next_position(Some(3)), 4);
+
+    let queue = vec![
+        test_hold(10, 100, 1, HoldStatus::Waiting),
+        test_hold(11, 101, 2, HoldStatus::Waiting),
+        test_hold(12, 102, 3, HoldStatus::Waiting),
+        test_hold(9, 99, 0, HoldStatus::Ready),
+    ];
+    let next = next_in_line(&queue).unwrap();
+    assert_eq!(next.id, 10);
+
+    // Fulfilling the head of the queue moves everyone behind it up one place.
+    assert_eq!(close_gap(&queue[1..], next.position), [(11, 1), (12, 2)]);
+    // Cancelling from the middle only moves those behind it.
+    assert_eq!(close_gap(&queue, 2), [(12, 2)]);
+    // Cancelling the last hold, or one that was already out of the queue, moves nobody.
+    assert!(close_gap(&queue, 3).is_empty());
+
+    // Ties on position, which a racing insert could cause, go to the earlier hold.
+    let tied = vec![
+        test_hold(21, 201, 1, HoldStatus::Waiting),
+        test_hold(20, 200, 1, HoldStatus::Waiting),
+    ];
+    assert_eq!(next_in_line(&tied).unwrap().id, 20);
+    assert!(next_in_line(&[test_hold(9, 99, 0, HoldStatus::Ready)]).is_none());
+}
+
+#[test]
+fn test_hold_pickup() {
+    let ready = test_hold(9, 99, 0, HoldStatus::Ready);
+    assert!(held_for(Some(&ready), 99));
+    assert!(!held_for(Some(&ready), 100));
+    assert!(!held_for(None, 99));
+
+    assert!(is_open(HoldStatus::Waiting));
+    assert!(is_open(HoldStatus::Ready));
+    for status in [
+        HoldStatus::Fulfilled,
+        HoldStatus::Cancelled,
+        HoldStatus::Expired,
+    ] {
+        assert!(!is_open(status));
+    }
+}
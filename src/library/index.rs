@@ -0,0 +1,404 @@
+use std::{
+    fmt::Debug,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tantivy::{
+    collector::{Count, TopDocs},
+    directory::MmapDirectory,
+    query::{BooleanQuery, FuzzyTermQuery, Occur, Query, QueryParser},
+    schema::{
+        Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, FAST, INDEXED,
+        STORED, STRING,
+    },
+    tokenizer::{
+        AsciiFoldingFilter, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer,
+        TextAnalyzer, TokenStream,
+    },
+    Index, IndexReader, IndexWriter, ReloadPolicy, SnippetGenerator, TantivyDocument, Term,
+};
+
+use crate::orm::book::Book;
+
+use super::normalize_isbn;
+
+const CATALOG_TOKENIZER: &str = "catalog";
+const WRITER_MEMORY_BYTES: usize = 50_000_000;
+const SNIPPET_MAX_CHARS: usize = 150;
+// Short terms only tolerate a single typo, otherwise nearly every short word would match.
+const FUZZY_SHORT_TERM_LEN: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchHit {
+    pub id: u64,
+    pub score: f32,
+    pub title: String,
+    pub author: String,
+    pub isbn: String,
+    /// HTML fragments with matched terms wrapped in `<b>`.
+    pub highlights: SearchHighlights,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SearchHighlights {
+    pub title: String,
+    pub author: String,
+}
+
+#[derive(Clone, Copy)]
+struct Fields {
+    id: Field,
+    title: Field,
+    author: Field,
+    isbn: Field,
+}
+
+/// Full-text index over title, author and ISBN. Titles and authors are lower-cased, folded to
+/// ASCII and stemmed so "Galaxies" finds "galaxy" and "Godel" finds "Gödel".
+#[derive(Clone)]
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Arc<Mutex<IndexWriter>>,
+    fields: Fields,
+}
+
+impl Debug for SearchIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchIndex").finish_non_exhaustive()
+    }
+}
+
+fn schema() -> (Schema, Fields) {
+    let text = TextOptions::default().set_stored().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(CATALOG_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        id: builder.add_u64_field("id", INDEXED | STORED | FAST),
+        title: builder.add_text_field("title", text.clone()),
+        author: builder.add_text_field("author", text),
+        isbn: builder.add_text_field("isbn", STRING | STORED),
+    };
+    (builder.build(), fields)
+}
+
+impl SearchIndex {
+    /// Opens the index stored in `directory`, creating it when missing.
+    pub fn open(directory: &Path) -> tantivy::Result<Self> {
+        if let Err(error) = fs::create_dir_all(directory) {
+            return Err(tantivy::TantivyError::IoError(Arc::new(error)));
+        }
+        let (schema, fields) = schema();
+        let index = Index::open_or_create(MmapDirectory::open(directory)?, schema)?;
+        Self::from_index(index, fields)
+    }
+
+    pub fn open_in_ram() -> tantivy::Result<Self> {
+        let (schema, fields) = schema();
+        Self::from_index(Index::create_in_ram(schema), fields)
+    }
+
+    fn from_index(index: Index, fields: Fields) -> tantivy::Result<Self> {
+        index.tokenizers().register(
+            CATALOG_TOKENIZER,
+            TextAnalyzer::builder(SimpleTokenizer::default())
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(AsciiFoldingFilter)
+                .filter(Stemmer::new(Language::English))
+                .build(),
+        );
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = index.writer(WRITER_MEMORY_BYTES)?;
+        Ok(Self {
+            index,
+            reader,
+            writer: Arc::new(Mutex::new(writer)),
+            fields,
+        })
+    }
+
+    fn document(&self, book: &Book) -> TantivyDocument {
+        let mut document = TantivyDocument::default();
+        document.add_u64(self.fields.id, book.id);
+        document.add_text(self.fields.title, &book.title);
+        document.add_text(self.fields.author, &book.author);
+        document.add_text(self.fields.isbn, &book.isbn);
+        document
+    }
+
+    fn commit(&self, writer: &mut IndexWriter) -> tantivy::Result<()> {
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    /// Adds `book`, replacing any previous document for the same id.
+    pub fn upsert(&self, book: &Book) -> tantivy::Result<()> {
+        self.upsert_all(std::slice::from_ref(book))
+    }
+
+    /// Adds every book in `books` like `upsert`, but in a single commit, for bulk imports.
+    pub fn upsert_all(&self, books: &[Book]) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        for book in books {
+            writer.delete_term(Term::from_field_u64(self.fields.id, book.id));
+            writer.add_document(self.document(book))?;
+        }
+        self.commit(&mut writer)
+    }
+
+    pub fn remove(&self, id: u64) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        writer.delete_term(Term::from_field_u64(self.fields.id, id));
+        self.commit(&mut writer)
+    }
+
+    /// Drops every document ahead of a rebuild. Searchers see no change until `finish_rebuild`
+    /// commits the books added in between, and `abort_rebuild` puts everything back.
+    pub fn start_rebuild(&self) -> tantivy::Result<()> {
+        let writer = self.writer.lock().expect("search index writer poisoned");
+        writer.delete_all_documents()?;
+        Ok(())
+    }
+
+    /// Adds one batch of books to a rebuild, so the catalog never has to fit in memory at once.
+    pub fn add_to_rebuild(&self, books: &[Book]) -> tantivy::Result<()> {
+        let writer = self.writer.lock().expect("search index writer poisoned");
+        for book in books {
+            writer.add_document(self.document(book))?;
+        }
+        Ok(())
+    }
+
+    /// Makes a rebuild of `count` books visible in a single commit.
+    pub fn finish_rebuild(&self, count: usize) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        self.commit(&mut writer)?;
+        info!("Rebuilt search index with {count} books.");
+        Ok(())
+    }
+
+    pub fn abort_rebuild(&self) {
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        if let Err(error) = writer.rollback() {
+            warn!("failed to roll back search index rebuild: {error}");
+        }
+    }
+
+    /// Runs `query` and returns one page of hits, best match first, along with the total hit count.
+    pub fn search(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> tantivy::Result<(Vec<SearchHit>, usize)> {
+        let mut parser = QueryParser::for_index(
+            &self.index,
+            vec![self.fields.title, self.fields.author, self.fields.isbn],
+        );
+        parser.set_field_boost(self.fields.title, 2.0);
+
+        // ISBNs are indexed in canonical form, so hyphenated and ISBN-10 input is rewritten first.
+        let query = normalize_isbn(query).unwrap_or_else(|| query.to_string());
+        let (query, errors) = parser.parse_query_lenient(&query);
+        for error in errors {
+            warn!("ignoring part of search query: {error}");
+        }
+
+        let searcher = self.reader.searcher();
+        let (top_docs, total) = searcher.search(
+            &query,
+            &(TopDocs::with_limit(limit).and_offset(offset), Count),
+        )?;
+
+        let mut title_snippets = SnippetGenerator::create(&searcher, &*query, self.fields.title)?;
+        title_snippets.set_max_num_chars(SNIPPET_MAX_CHARS);
+        let mut author_snippets = SnippetGenerator::create(&searcher, &*query, self.fields.author)?;
+        author_snippets.set_max_num_chars(SNIPPET_MAX_CHARS);
+
+        let mut hits = Vec::with_capacity(top_docs.len());
+        for (score, address) in top_docs {
+            let document = searcher.doc::<TantivyDocument>(address)?;
+            let text = |field| {
+                document
+                    .get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            hits.push(SearchHit {
+                id: document
+                    .get_first(self.fields.id)
+                    .and_then(|value| value.as_u64())
+                    .unwrap_or_default(),
+                score,
+                title: text(self.fields.title),
+                author: text(self.fields.author),
+                isbn: text(self.fields.isbn),
+                highlights: SearchHighlights {
+                    title: title_snippets.snippet_from_doc(&document).to_html(),
+                    author: author_snippets.snippet_from_doc(&document).to_html(),
+                },
+            });
+        }
+        Ok((hits, total))
+    }
+
+    /// Returns the ids of books whose title or author contain terms within a small edit distance
+    /// of the terms in `title` and `author`. The order is only a loose relevance order.
+    pub fn fuzzy_candidates(
+        &self,
+        title: Option<&str>,
+        author: Option<&str>,
+        limit: usize,
+    ) -> tantivy::Result<Vec<u64>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![];
+        for (field, text) in [(self.fields.title, title), (self.fields.author, author)] {
+            let Some(text) = text else {
+                continue;
+            };
+            let mut tokenizer = self.index.tokenizer_for_field(field)?;
+            let mut tokens = tokenizer.token_stream(text);
+            while tokens.advance() {
+                let term = &tokens.token().text;
+                let distance = if term.chars().count() <= FUZZY_SHORT_TERM_LEN {
+                    1
+                } else {
+                    2
+                };
+                clauses.push((
+                    Occur::Should,
+                    Box::new(FuzzyTermQuery::new(
+                        Term::from_field_text(field, term),
+                        distance,
+                        true,
+                    )),
+                ));
+            }
+        }
+        if clauses.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let searcher = self.reader.searcher();
+        let top_docs = searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))?;
+        let mut ids = Vec::with_capacity(top_docs.len());
+        for (_, address) in top_docs {
+            let document = searcher.doc::<TantivyDocument>(address)?;
+            if let Some(id) = document
+                .get_first(self.fields.id)
+                .and_then(|value| value.as_u64())
+            {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+fn book(id: u64, title: &str, author: &str, isbn: &str) -> Book {
+    Book {
+        id,
+        title: title.to_string(),
+        author: author.to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![],
+        publication_year: 1979,
+        subjects: vec![],
+        isbn: isbn.to_string(),
+        description: None,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }
+}
+
+#[test]
+fn test_search_index() {
+    let index = SearchIndex::open_in_ram().expect("failed to create index");
+    index.start_rebuild().expect("failed to start rebuild");
+    index
+        .add_to_rebuild(&[
+            book(
+                1,
+                "The Hitchhiker's Guide to the Galaxy",
+                "Douglas Adams",
+                "9780575074842",
+            ),
+            book(
+                2,
+                "Gödel, Escher, Bach",
+                "Douglas Hofstadter",
+                "9780465026562",
+            ),
+            book(3, "Galaxies", "Timothy Ferris", "9780871562089"),
+        ])
+        .expect("failed to build index");
+    index.finish_rebuild(3).expect("failed to commit index");
+
+    let ids = |query| {
+        index
+            .search(query, 0, 10)
+            .expect("search failed")
+            .0
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids("adams"), vec![1]);
+    assert_eq!(ids("godel"), vec![2]);
+    assert_eq!(ids("0-575-07484-1"), vec![1]);
+    let mut galaxy = ids("galaxy");
+    galaxy.sort();
+    assert_eq!(galaxy, vec![1, 3]);
+
+    let (hits, total) = index.search("douglas", 0, 1).expect("search failed");
+    assert_eq!(total, 2);
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0].highlights.author.contains("<b>Douglas</b>"));
+
+    let fuzzy = index
+        .fuzzy_candidates(Some("hitchiker"), Some("Douglass Adms"), 10)
+        .expect("fuzzy search failed");
+    assert_eq!(fuzzy.first(), Some(&1));
+    assert!(!fuzzy.contains(&3));
+
+    index.remove(1).expect("failed to remove book");
+    assert!(ids("adams").is_empty());
+    index
+        .upsert(&book(3, "Galaxies", "Tim Ferris", "9780871562089"))
+        .expect("failed to update book");
+    assert_eq!(ids("tim"), vec![3]);
+    assert_eq!(ids("galaxies"), vec![3]);
+
+    index
+        .upsert_all(&[
+            book(3, "Galaxies", "Timothy Ferris", "9780871562089"),
+            book(4, "Mostly Harmless", "Douglas Adams", "9780330323116"),
+        ])
+        .expect("failed to update books");
+    assert!(ids("tim").is_empty());
+    assert_eq!(ids("timothy"), vec![3]);
+    assert_eq!(ids("harmless"), vec![4]);
+
+    // An abandoned rebuild leaves the committed index as it was.
+    index.start_rebuild().expect("failed to start rebuild");
+    index
+        .add_to_rebuild(&[book(5, "Dune", "Frank Herbert", "9780441172719")])
+        .expect("failed to add books");
+    index.abort_rebuild();
+    assert!(ids("dune").is_empty());
+    assert_eq!(ids("harmless"), vec![4]);
+}
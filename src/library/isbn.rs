@@ -0,0 +1,80 @@
+/// Removes the separators people commonly type into an ISBN.
+pub fn strip_isbn(isbn: &str) -> String {
+    isbn.chars().filter(|c| !matches!(c, '-' | ' ')).collect()
+}
+
+/// Validates an ISBN-10 or ISBN-13 and returns it as a canonical ISBN-13 without separators.
+pub fn normalize_isbn(isbn: &str) -> Option<String> {
+    let isbn = strip_isbn(isbn).to_ascii_uppercase();
+    match isbn.len() {
+        10 => {
+            if !isbn10_valid(&isbn) {
+                return None;
+            }
+            let body = format!("978{}", &isbn[..9]);
+            let check = isbn13_check_digit(&body);
+            Some(format!("{body}{check}"))
+        }
+        13 => {
+            if !isbn.bytes().all(|c| c.is_ascii_digit())
+                || !(isbn.starts_with("978") || isbn.starts_with("979"))
+                || isbn13_check_digit(&isbn[..12]) != isbn.as_bytes()[12] - b'0'
+            {
+                return None;
+            }
+            Some(isbn)
+        }
+        _ => None,
+    }
+}
+
+fn isbn10_valid(isbn: &str) -> bool {
+    let mut sum = 0;
+    for (index, c) in isbn.bytes().enumerate() {
+        let value = match c {
+            b'0'..=b'9' => (c - b'0') as u32,
+            // X stands for 10 and is only allowed as the check digit.
+            b'X' if index == 9 => 10,
+            _ => return false,
+        };
+        sum += (10 - index as u32) * value;
+    }
+    sum % 11 == 0
+}
+
+// Takes the first twelve digits of an ISBN-13.
+fn isbn13_check_digit(body: &str) -> u8 {
+    let sum: u32 = body
+        .bytes()
+        .enumerate()
+        .map(|(index, c)| (c - b'0') as u32 * if index % 2 == 0 { 1 } else { 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+#[test]
+fn test_normalize_isbn() {
+    assert_eq!(
+        normalize_isbn("978-0-575-07484-2"),
+        Some("9780575074842".to_string())
+    );
+    assert_eq!(
+        normalize_isbn("0 575 07484 1"),
+        Some("9780575074842".to_string())
+    );
+    assert_eq!(
+        normalize_isbn("080442957x"),
+        Some("9780804429573".to_string())
+    );
+    assert_eq!(
+        normalize_isbn("979-10-90636-07-1"),
+        Some("9791090636071".to_string())
+    );
+
+    assert_eq!(normalize_isbn("0-575-07484-X"), None);
+    assert_eq!(normalize_isbn("9780575074843"), None);
+    assert_eq!(normalize_isbn("9770575074845"), None);
+    assert_eq!(normalize_isbn("05750X4841"), None);
+    assert_eq!(normalize_isbn("not an isbn"), None);
+    assert_eq!(normalize_isbn(""), None);
+}
@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use chrono::{NaiveDate, TimeDelta, Utc};
+use log::{info, trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::request::ledger_entry::PostLedgerEntryRequest,
+    orm::{
+        ledger::{self, LedgerEntry, LedgerEntryKind},
+        loan::{self, Loan},
+    },
+};
+
+use super::{resolve_policy, Library, LibraryErrorStatus};
+
+// Loans returned late within this window still get their final days of fines posted.
+const FINE_CATCH_UP_DAYS: i64 = 7;
+/// The largest single entry, in the smallest currency unit, so totals stay far from overflowing.
+pub const MAX_LEDGER_AMOUNT: u64 = 100_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LedgerTotals {
+    pub charges: u64,
+    pub payments: u64,
+    pub waivers: u64,
+    pub balance: i64,
+}
+
+impl LedgerTotals {
+    pub fn from_entries(entries: &[LedgerEntry]) -> Result<Self, LibraryErrorStatus> {
+        let mut totals = Self::default();
+        for entry in entries {
+            let total = match entry.kind {
+                LedgerEntryKind::Charge => &mut totals.charges,
+                LedgerEntryKind::Payment => &mut totals.payments,
+                LedgerEntryKind::Waiver => &mut totals.waivers,
+            };
+            *total = total
+                .checked_add(entry.amount)
+                .ok_or(LibraryErrorStatus::LedgerOverflow)?;
+        }
+        let signed = |total: u64| i64::try_from(total).ok();
+        totals.balance = signed(totals.charges)
+            .zip(signed(totals.payments))
+            .zip(signed(totals.waivers))
+            .and_then(|((charges, payments), waivers)| {
+                charges.checked_sub(payments)?.checked_sub(waivers)
+            })
+            .ok_or(LibraryErrorStatus::LedgerOverflow)?;
+        Ok(totals)
+    }
+}
+
+impl Library {
+    pub async fn get_ledger(
+        &self,
+        user: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<LedgerEntry>, LibraryErrorStatus> {
+        let db_result = ledger::Entity::find()
+            .filter(ledger::Column::User.eq(user))
+            .order_by_asc(ledger::Column::PostedAt)
+            .order_by_asc(ledger::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch ledger: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn post_ledger_entry(
+        &self,
+        user: u64,
+        request: PostLedgerEntryRequest,
+        posted_by: u64,
+        database: &DatabaseConnection,
+    ) -> Result<LedgerEntry, LibraryErrorStatus> {
+        if request.amount == 0 || request.amount > MAX_LEDGER_AMOUNT {
+            return Err(LibraryErrorStatus::AmountInvalid);
+        }
+
+        trace!("inserting ledger entry to db");
+        let db_result = ledger::ActiveModel {
+            id: ActiveValue::NotSet,
+            user: ActiveValue::Set(user),
+            kind: ActiveValue::Set(request.kind),
+            amount: ActiveValue::Set(request.amount),
+            reason: ActiveValue::Set(request.reason),
+            posted_by: ActiveValue::Set(Some(posted_by)),
+            loan: ActiveValue::Set(request.loan),
+            accrued_on: ActiveValue::Set(None),
+            posted_at: ActiveValue::Set(Utc::now()),
+        }
+        .insert(database)
+        .await;
+        if let Err(error) = &db_result {
+            warn!("failed to post ledger entry: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    /// Charges every overdue day of every late loan that has not been charged yet, at the rate of the
+    /// loan's resolved policy or `default_fine_per_day` when no policy matches.
+    /// Safe to run repeatedly; each (loan, day) pair is only ever posted once.
+    pub async fn post_overdue_fines(
+        &self,
+        default_fine_per_day: u64,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let rules = self.get_policies(database).await?;
+        let now = Utc::now();
+        let db_result = loan::Entity::find()
+            .filter(loan::Column::DueAt.lt(now))
+            .filter(
+                Condition::any()
+                    .add(loan::Column::ReturnedAt.is_null())
+                    .add(loan::Column::ReturnedAt.gt(now - TimeDelta::days(FINE_CATCH_UP_DAYS))),
+            )
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch overdue loans: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        for loan in db_result.unwrap() {
+            let copy = Self::find_copy(loan.copy, database).await?;
+            let key = Self::policy_key_for(loan.borrower, &copy, database).await?;
+            let fine_per_day =
+                resolve_policy(&rules, &key).map_or(default_fine_per_day, |rule| rule.fine_per_day);
+            self.post_fines_for_loan(&loan, fine_per_day, database)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn post_fines_for_loan(
+        &self,
+        loan: &Loan,
+        fine_per_day: u64,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let last_day = loan.returned_at.unwrap_or(Utc::now()).date_naive();
+        let overdue_days = overdue_days(loan.due_at.date_naive(), last_day);
+        if overdue_days.is_empty() || fine_per_day == 0 {
+            return Ok(());
+        }
+
+        let accrued = ledger::Entity::find()
+            .select_only()
+            .column(ledger::Column::AccruedOn)
+            .filter(ledger::Column::Loan.eq(loan.id))
+            .filter(ledger::Column::AccruedOn.is_not_null())
+            .into_tuple::<Option<NaiveDate>>()
+            .all(database)
+            .await;
+        if let Err(error) = &accrued {
+            warn!("failed to fetch accrued fines: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let accrued = accrued
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<HashSet<_>>();
+
+        let fines = overdue_days
+            .into_iter()
+            .filter(|day| !accrued.contains(day))
+            .map(|day| ledger::ActiveModel {
+                id: ActiveValue::NotSet,
+                user: ActiveValue::Set(loan.borrower),
+                kind: ActiveValue::Set(LedgerEntryKind::Charge),
+                amount: ActiveValue::Set(fine_per_day),
+                reason: ActiveValue::Set(format!("overdue fine for loan {} on {day}", loan.id)),
+                posted_by: ActiveValue::Set(None),
+                loan: ActiveValue::Set(Some(loan.id)),
+                accrued_on: ActiveValue::Set(Some(day)),
+                posted_at: ActiveValue::Set(Utc::now()),
+            })
+            .collect::<Vec<_>>();
+        if fines.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "posting {} overdue fine(s) for loan {}",
+            fines.len(),
+            loan.id
+        );
+        if let Err(error) = ledger::Entity::insert_many(fines).exec(database).await {
+            warn!("failed to post overdue fines: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(())
+    }
+}
+
+fn overdue_days(due: NaiveDate, last_day: NaiveDate) -> Vec<NaiveDate> {
+    due.iter_days()
+        .skip(1)
+        .take_while(|day| *day <= last_day)
+        .collect()
+}
+
+#[test]
+fn test_overdue_days() {
+    let due = NaiveDate::from_ymd_opt(2025, 1, 30).unwrap();
+    assert!(overdue_days(due, due).is_empty());
+    assert_eq!(
+        overdue_days(due, NaiveDate::from_ymd_opt(2025, 2, 2).unwrap()),
+        vec![
+            NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 2, 2).unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_ledger_totals() {
+    let entry = |kind, amount| LedgerEntry {
+        id: 0,
+        user: 1,
+        kind,
+        amount,
+        reason: String::new(),
+        posted_by: None,
+        loan: None,
+        accrued_on: None,
+        posted_at: Utc::now(),
+    };
+
+    let totals = LedgerTotals::from_entries(&[
+        entry(LedgerEntryKind::Charge, 250),
+        entry(LedgerEntryKind::Charge, 100),
+        entry(LedgerEntryKind::Payment, 300),
+        entry(LedgerEntryKind::Waiver, 75),
+    ])
+    .unwrap();
+    assert_eq!(totals.charges, 350);
+    assert_eq!(totals.payments, 300);
+    assert_eq!(totals.waivers, 75);
+    assert_eq!(totals.balance, -25);
+
+    // Entries posted before amounts were bounded cannot wrap the totals.
+    let overflow = |entries: &[LedgerEntry]| {
+        matches!(
+            LedgerTotals::from_entries(entries),
+            Err(LibraryErrorStatus::LedgerOverflow)
+        )
+    };
+    assert!(overflow(&[
+        entry(LedgerEntryKind::Charge, u64::MAX),
+        entry(LedgerEntryKind::Charge, 1),
+    ]));
+    assert!(overflow(&[entry(LedgerEntryKind::Charge, u64::MAX)]));
+    assert!(overflow(&[
+        entry(LedgerEntryKind::Payment, i64::MAX as u64),
+        entry(LedgerEntryKind::Waiver, 2),
+    ]));
+}
@@ -0,0 +1,528 @@
+use futures::{stream, Stream};
+use quick_xml::escape::escape;
+use sea_orm::DatabaseConnection;
+use serde_json::{json, Value};
+
+use crate::orm::{book::Book, book_contributor::ContributorRole};
+
+use super::{catalog_csv::EXPORT_BATCH_SIZE, Library, LibraryErrorStatus};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+const BF: &str = "http://id.loc.gov/ontologies/bibframe/";
+const RELATORS: &str = "http://id.loc.gov/vocabulary/relators/";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+/// Prefixes used for Turtle and for the element names of RDF/XML.
+const PREFIXES: [(&str, &str); 5] = [
+    ("rdf", RDF),
+    ("rdfs", RDFS),
+    ("bf", BF),
+    ("relators", RELATORS),
+    ("xsd", XSD),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkedDataFormat {
+    /// schema.org `Book` as JSON-LD.
+    JsonLd,
+    /// BIBFRAME 2.0 as Turtle.
+    Turtle,
+    /// BIBFRAME 2.0 as RDF/XML.
+    RdfXml,
+    /// BIBFRAME 2.0 as N-Triples, one statement per line.
+    NTriples,
+}
+
+impl LinkedDataFormat {
+    const ALL: [Self; 4] = [Self::JsonLd, Self::Turtle, Self::RdfXml, Self::NTriples];
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::JsonLd => "application/ld+json",
+            Self::Turtle => "text/turtle",
+            Self::RdfXml => "application/rdf+xml",
+            Self::NTriples => "application/n-triples",
+        }
+    }
+
+    /// Picks a format from an `Accept` header, honoring quality values. `None` means the client
+    /// prefers plain JSON, or anything at all, and should get the regular API response.
+    pub fn negotiate(accept: &str) -> Option<Self> {
+        let mut ranges = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let media_type = parts.next()?.to_ascii_lowercase();
+                let quality = parts
+                    .filter_map(|parameter| parameter.strip_prefix("q="))
+                    .find_map(|quality| quality.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((media_type, quality))
+            })
+            .collect::<Vec<_>>();
+        // A stable sort keeps the client's order among equally preferred types.
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        for (media_type, _) in ranges {
+            if let Some(format) = Self::ALL
+                .into_iter()
+                .find(|format| format.media_type() == media_type)
+            {
+                return Some(format);
+            }
+            if matches!(
+                media_type.as_str(),
+                "application/json" | "application/*" | "*/*"
+            ) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Renders `book`, whose page is `{base_url}/books/{id}`.
+    pub fn render(self, book: &Book, base_url: &str) -> String {
+        match self {
+            Self::JsonLd => {
+                serde_json::to_string_pretty(&schema_org(book, base_url)).unwrap_or_default()
+            }
+            Self::Turtle => to_turtle(&bibframe(book, base_url)),
+            Self::RdfXml => to_rdf_xml(&bibframe(book, base_url)),
+            Self::NTriples => to_ntriples(&bibframe(book, base_url)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Iri(String),
+    Blank(String),
+    Literal {
+        value: String,
+        /// An XSD datatype such as `dateTime`; plain strings have none.
+        datatype: Option<&'static str>,
+    },
+}
+
+type Triple = (Term, &'static str, Term);
+
+fn iri(iri: impl Into<String>) -> Term {
+    Term::Iri(iri.into())
+}
+
+fn literal(value: impl Into<String>) -> Term {
+    Term::Literal {
+        value: value.into(),
+        datatype: None,
+    }
+}
+
+fn relator(role: ContributorRole) -> &'static str {
+    match role {
+        ContributorRole::Author => "aut",
+        ContributorRole::Editor => "edt",
+        ContributorRole::Translator => "trl",
+        ContributorRole::Illustrator => "ill",
+    }
+}
+
+fn schema_role(role: ContributorRole) -> &'static str {
+    match role {
+        ContributorRole::Author => "author",
+        ContributorRole::Editor => "editor",
+        ContributorRole::Translator => "translator",
+        ContributorRole::Illustrator => "illustrator",
+    }
+}
+
+/// The schema.org description of `book`, as search engines read it from JSON-LD.
+fn schema_org(book: &Book, base_url: &str) -> Value {
+    let url = format!("{base_url}/books/{}", book.id);
+    let mut item = json!({
+        "@context": "https://schema.org",
+        "@type": "Book",
+        "@id": url,
+        "url": url,
+        "name": book.title,
+        "isbn": book.isbn,
+        "datePublished": book.publication_year.to_string(),
+        "dateModified": book.updated_at.to_rfc3339(),
+    });
+    if let Some(description) = &book.description {
+        item["description"] = description.as_str().into();
+    }
+    for credit in &book.contributors {
+        let key = schema_role(credit.role);
+        let person = json!({ "@type": "Person", "name": credit.name });
+        match item.get_mut(key).and_then(Value::as_array_mut) {
+            Some(people) => people.push(person),
+            None => item[key] = json!([person]),
+        }
+    }
+    if !book.subjects.is_empty() {
+        item["about"] = book
+            .subjects
+            .iter()
+            .map(|subject| json!({ "@type": "Thing", "name": subject }))
+            .collect::<Vec<_>>()
+            .into();
+    }
+    item
+}
+
+/// Describes `book` as a BIBFRAME work and its printed instance. Blank node labels carry the
+/// book id so the statements of many books can share one document.
+fn bibframe(book: &Book, base_url: &str) -> Vec<Triple> {
+    let url = format!("{base_url}/books/{}", book.id);
+    let work = iri(format!("{url}#work"));
+    let instance = iri(format!("{url}#instance"));
+    let mut nodes = 0;
+    let mut blank = || {
+        nodes += 1;
+        Term::Blank(format!("book{}n{nodes}", book.id))
+    };
+    let mut triples = vec![];
+    let mut add = |subject: &Term, predicate: &'static str, object: Term| {
+        triples.push((subject.clone(), predicate, object));
+    };
+
+    add(&work, "rdf:type", iri(format!("{BF}Work")));
+    add(&work, "rdf:type", iri(format!("{BF}Text")));
+    let title = blank();
+    add(&work, "bf:title", title.clone());
+    add(&title, "rdf:type", iri(format!("{BF}Title")));
+    add(&title, "bf:mainTitle", literal(&book.title));
+    for credit in &book.contributors {
+        let contribution = blank();
+        let agent = blank();
+        add(&work, "bf:contribution", contribution.clone());
+        add(&contribution, "rdf:type", iri(format!("{BF}Contribution")));
+        add(&contribution, "bf:agent", agent.clone());
+        add(
+            &contribution,
+            "bf:role",
+            iri(format!("{RELATORS}{}", relator(credit.role))),
+        );
+        add(&agent, "rdf:type", iri(format!("{BF}Agent")));
+        add(&agent, "rdfs:label", literal(&credit.name));
+    }
+    for subject in &book.subjects {
+        let topic = blank();
+        add(&work, "bf:subject", topic.clone());
+        add(&topic, "rdf:type", iri(format!("{BF}Topic")));
+        add(&topic, "rdfs:label", literal(subject));
+    }
+    add(&work, "bf:hasInstance", instance.clone());
+
+    add(&instance, "rdf:type", iri(format!("{BF}Instance")));
+    add(&instance, "rdf:type", iri(format!("{BF}Print")));
+    add(&instance, "bf:instanceOf", work.clone());
+    add(&instance, "bf:title", title);
+    let isbn = blank();
+    add(&instance, "bf:identifiedBy", isbn.clone());
+    add(&isbn, "rdf:type", iri(format!("{BF}Isbn")));
+    add(&isbn, "rdf:value", literal(&book.isbn));
+    let publication = blank();
+    add(&instance, "bf:provisionActivity", publication.clone());
+    add(&publication, "rdf:type", iri(format!("{BF}Publication")));
+    add(
+        &publication,
+        "bf:date",
+        Term::Literal {
+            value: book.publication_year.to_string(),
+            datatype: Some("gYear"),
+        },
+    );
+    let admin = blank();
+    add(&instance, "bf:adminMetadata", admin.clone());
+    add(&admin, "rdf:type", iri(format!("{BF}AdminMetadata")));
+    add(
+        &admin,
+        "bf:changeDate",
+        Term::Literal {
+            value: book.updated_at.to_rfc3339(),
+            datatype: Some("dateTime"),
+        },
+    );
+    triples
+}
+
+/// Expands a `prefix:local` predicate to its full IRI.
+fn expand(qname: &str) -> String {
+    let (prefix, local) = qname.split_once(':').unwrap_or_default();
+    let namespace = PREFIXES
+        .iter()
+        .find(|(known, _)| *known == prefix)
+        .map_or("", |(_, namespace)| namespace);
+    format!("{namespace}{local}")
+}
+
+/// Shortens `iri` to `prefix:local` where the local part needs no escaping.
+fn compact(iri: &str) -> Option<String> {
+    PREFIXES.iter().find_map(|(prefix, namespace)| {
+        let local = iri.strip_prefix(namespace)?;
+        let simple = !local.is_empty() && local.chars().all(|c| c.is_ascii_alphanumeric());
+        simple.then(|| format!("{prefix}:{local}"))
+    })
+}
+
+/// Escapes a literal for N-Triples and Turtle, which share their string syntax.
+fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn ntriples_term(term: &Term) -> String {
+    match term {
+        Term::Iri(iri) => format!("<{iri}>"),
+        Term::Blank(label) => format!("_:{label}"),
+        Term::Literal {
+            value,
+            datatype: None,
+        } => format!("\"{}\"", escape_string(value)),
+        Term::Literal {
+            value,
+            datatype: Some(datatype),
+        } => format!("\"{}\"^^<{XSD}{datatype}>", escape_string(value)),
+    }
+}
+
+fn to_ntriples(triples: &[Triple]) -> String {
+    triples
+        .iter()
+        .map(|(subject, predicate, object)| {
+            format!(
+                "{} <{}> {} .\n",
+                ntriples_term(subject),
+                expand(predicate),
+                ntriples_term(object)
+            )
+        })
+        .collect()
+}
+
+fn turtle_term(term: &Term) -> String {
+    match term {
+        Term::Iri(iri) => compact(iri).unwrap_or_else(|| format!("<{iri}>")),
+        Term::Literal {
+            value,
+            datatype: Some(datatype),
+        } => format!("\"{}\"^^xsd:{datatype}", escape_string(value)),
+        term => ntriples_term(term),
+    }
+}
+
+/// Triples grouped by subject, with subjects in order of first appearance.
+fn by_subject(triples: &[Triple]) -> Vec<Vec<&Triple>> {
+    let mut groups = Vec::<Vec<&Triple>>::new();
+    for triple in triples {
+        match groups.iter_mut().find(|group| group[0].0 == triple.0) {
+            Some(group) => group.push(triple),
+            None => groups.push(vec![triple]),
+        }
+    }
+    groups
+}
+
+fn to_turtle(triples: &[Triple]) -> String {
+    let mut turtle = PREFIXES
+        .iter()
+        .map(|(prefix, namespace)| format!("@prefix {prefix}: <{namespace}> .\n"))
+        .collect::<String>();
+    for group in by_subject(triples) {
+        turtle.push('\n');
+        turtle.push_str(&turtle_term(&group[0].0));
+        for (index, (_, predicate, object)) in group.iter().enumerate() {
+            let predicate = match *predicate {
+                "rdf:type" => "a",
+                predicate => predicate,
+            };
+            let separator = if index + 1 == group.len() { " ." } else { " ;" };
+            turtle.push_str(&format!(
+                "\n    {predicate} {}{separator}",
+                turtle_term(object)
+            ));
+        }
+        turtle.push('\n');
+    }
+    turtle
+}
+
+fn to_rdf_xml(triples: &[Triple]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rdf:RDF");
+    for (prefix, namespace) in PREFIXES {
+        xml.push_str(&format!("\n    xmlns:{prefix}=\"{namespace}\""));
+    }
+    xml.push_str(">\n");
+    for group in by_subject(triples) {
+        let node = match &group[0].0 {
+            Term::Iri(iri) => format!("rdf:about=\"{}\"", escape(iri)),
+            Term::Blank(label) => format!("rdf:nodeID=\"{label}\""),
+            // Literals are only ever objects.
+            Term::Literal { .. } => continue,
+        };
+        xml.push_str(&format!("  <rdf:Description {node}>\n"));
+        for (_, predicate, object) in group {
+            let element = match object {
+                Term::Iri(iri) => format!("<{predicate} rdf:resource=\"{}\"/>", escape(iri)),
+                Term::Blank(label) => format!("<{predicate} rdf:nodeID=\"{label}\"/>"),
+                Term::Literal {
+                    value,
+                    datatype: None,
+                } => format!("<{predicate}>{}</{predicate}>", escape(value)),
+                Term::Literal {
+                    value,
+                    datatype: Some(datatype),
+                } => format!(
+                    "<{predicate} rdf:datatype=\"{XSD}{datatype}\">{}</{predicate}>",
+                    escape(value)
+                ),
+            };
+            xml.push_str(&format!("    {element}\n"));
+        }
+        xml.push_str("  </rdf:Description>\n");
+    }
+    xml.push_str("</rdf:RDF>\n");
+    xml
+}
+
+impl Library {
+    /// The whole catalog as BIBFRAME N-Triples, fetched in batches as the stream is polled.
+    pub fn export_ntriples(
+        database: DatabaseConnection,
+        base_url: String,
+    ) -> impl Stream<Item = Result<Vec<u8>, LibraryErrorStatus>> {
+        stream::try_unfold(Some(0), move |after| {
+            let database = database.clone();
+            let base_url = base_url.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+                let books = Self::export_batch(after, &database).await?;
+                let Some(last) = books.last() else {
+                    return Ok(None);
+                };
+                let next = (books.len() as u64 == EXPORT_BATCH_SIZE).then_some(last.id);
+                let statements = books
+                    .iter()
+                    .map(|book| to_ntriples(&bibframe(book, &base_url)))
+                    .collect::<String>();
+                Ok(Some((statements.into_bytes(), next)))
+            }
+        })
+    }
+}
+
+#[test]
+fn test_negotiate_linked_data() {
+    let negotiate = LinkedDataFormat::negotiate;
+    assert_eq!(negotiate("text/turtle"), Some(LinkedDataFormat::Turtle));
+    assert_eq!(
+        negotiate("text/html;q=0.9, application/ld+json"),
+        Some(LinkedDataFormat::JsonLd)
+    );
+    assert_eq!(
+        negotiate("application/json;q=0.5, application/rdf+xml;q=0.8"),
+        Some(LinkedDataFormat::RdfXml)
+    );
+    assert_eq!(negotiate("application/json, text/turtle"), None);
+    assert_eq!(negotiate("*/*"), None);
+    assert_eq!(negotiate("text/turtle;q=0, */*"), None);
+    assert_eq!(negotiate(""), None);
+}
+
+#[test]
+fn test_linked_data_serializations() {
+    use crate::orm::book_contributor::Credit;
+    use chrono::DateTime;
+
+    let updated_at = DateTime::parse_from_rfc3339("2024-03-01T10:20:30Z")
+        .unwrap()
+        .into();
+    let book = Book {
+        id: 5,
+        title: "The \"Hobbit\"".to_string(),
+        author: "J. R. R. Tolkien".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![
+            Credit {
+                name: "J. R. R. Tolkien".to_string(),
+                role: ContributorRole::Author,
+            },
+            Credit {
+                name: "Alan Lee".to_string(),
+                role: ContributorRole::Illustrator,
+            },
+        ],
+        publication_year: 1937,
+        subjects: vec!["Fantasy & magic".to_string()],
+        isbn: "9780261102217".to_string(),
+        description: None,
+        created_at: updated_at,
+        updated_at,
+    };
+    let base_url = "https://library.example.org";
+
+    let json_ld = LinkedDataFormat::JsonLd.render(&book, base_url);
+    let json_ld = serde_json::from_str::<Value>(&json_ld).unwrap();
+    assert_eq!(json_ld["@type"], "Book");
+    assert_eq!(json_ld["@id"], "https://library.example.org/books/5");
+    assert_eq!(json_ld["name"], "The \"Hobbit\"");
+    assert_eq!(
+        json_ld["author"],
+        json!([{ "@type": "Person", "name": "J. R. R. Tolkien" }])
+    );
+    assert_eq!(json_ld["illustrator"][0]["name"], "Alan Lee");
+    assert_eq!(json_ld["datePublished"], "1937");
+    assert_eq!(json_ld["about"][0]["name"], "Fantasy & magic");
+
+    let ntriples = LinkedDataFormat::NTriples.render(&book, base_url);
+    assert!(ntriples.contains(
+        "<https://library.example.org/books/5#work> \
+         <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> \
+         <http://id.loc.gov/ontologies/bibframe/Work> .\n"
+    ));
+    assert!(ntriples.contains(
+        "_:book5n1 <http://id.loc.gov/ontologies/bibframe/mainTitle> \"The \\\"Hobbit\\\"\" .\n"
+    ));
+    assert!(ntriples.contains(
+        "<http://id.loc.gov/ontologies/bibframe/role> <http://id.loc.gov/vocabulary/relators/ill> ."
+    ));
+    assert!(ntriples.contains("\"1937\"^^<http://www.w3.org/2001/XMLSchema#gYear> .\n"));
+    assert!(ntriples.lines().all(|line| line.ends_with(" .")));
+
+    let turtle = LinkedDataFormat::Turtle.render(&book, base_url);
+    assert!(turtle.starts_with("@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .\n"));
+    assert!(turtle.contains(
+        "\n<https://library.example.org/books/5#work>\n    a bf:Work ;\n    a bf:Text ;\n"
+    ));
+    assert!(turtle.contains("\n    bf:role relators:aut .\n"));
+    assert!(turtle.contains("\n    bf:date \"1937\"^^xsd:gYear .\n"));
+
+    let rdf_xml = LinkedDataFormat::RdfXml.render(&book, base_url);
+    assert!(rdf_xml
+        .contains("<rdf:Description rdf:about=\"https://library.example.org/books/5#instance\">"));
+    assert!(rdf_xml.contains("<bf:mainTitle>The &quot;Hobbit&quot;</bf:mainTitle>"));
+    assert!(rdf_xml.contains("<rdfs:label>Fantasy &amp; magic</rdfs:label>"));
+    assert!(rdf_xml
+        .contains("<bf:instanceOf rdf:resource=\"https://library.example.org/books/5#work\"/>"));
+    let mut reader = quick_xml::Reader::from_str(&rdf_xml);
+    loop {
+        match reader.read_event() {
+            Ok(quick_xml::events::Event::Eof) => break,
+            Ok(_) => {}
+            Err(error) => panic!("RDF/XML should be well-formed: {error}"),
+        }
+    }
+}
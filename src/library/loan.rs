@@ -0,0 +1,381 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+
+use crate::orm::{
+    copy::{self, BookCopy, CopyStatus},
+    loan::{self, Loan},
+    policy::LoanPolicy,
+};
+
+use super::{Library, LibraryErrorStatus};
+
+/// Checks that `copy` may go out on loan. `on_loan` is whether it has an unreturned loan, which
+/// wins over whatever its status says. Copies on the hold shelf pass here; `fulfill_hold` then
+/// decides whether this borrower may take them.
+fn assert_loanable(copy: &BookCopy, on_loan: bool) -> Result<(), LibraryErrorStatus> {
+    if on_loan {
+        warn!("refusing to check out copy already on loan: {}", copy.id);
+        return Err(LibraryErrorStatus::CopyOnLoan);
+    }
+    if !matches!(copy.status, CopyStatus::Available | CopyStatus::OnHoldShelf) {
+        warn!("refusing to check out unavailable copy: {}", copy.id);
+        return Err(LibraryErrorStatus::CopyUnavailable);
+    }
+    Ok(())
+}
+
+fn assert_below_loan_limit(
+    active_loans: u64,
+    policy: &LoanPolicy,
+) -> Result<(), LibraryErrorStatus> {
+    if active_loans >= policy.max_loans as u64 {
+        return Err(LibraryErrorStatus::LoanLimitReached(policy.max_loans));
+    }
+    Ok(())
+}
+
+fn due_date(policy: &LoanPolicy, from: DateTime<Utc>) -> DateTime<Utc> {
+    from + TimeDelta::days(policy.loan_days as i64)
+}
+
+/// The new due date and renewal count of `loan` renewed at `now`, unless it has used up its
+/// renewals.
+fn renewal(
+    loan: &Loan,
+    policy: &LoanPolicy,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, u32), LibraryErrorStatus> {
+    if loan.renewals >= policy.max_renewals {
+        return Err(LibraryErrorStatus::RenewalLimitReached(policy.max_renewals));
+    }
+    Ok((due_date(policy, now), loan.renewals + 1))
+}
+
+impl Library {
+    pub async fn checkout(
+        &self,
+        copy_id: u64,
+        borrower: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Loan, LibraryErrorStatus> {
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin checkout transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        let copy = Self::find_copy(copy_id, &txn).await?;
+        let key = Self::policy_key_for(borrower, &copy, &txn).await?;
+        let policy = self
+            .resolve_policy(&key, database)
+            .await?
+            .unwrap_or_else(LoanPolicy::fallback);
+
+        let on_loan = Self::active_loan_for_copy(copy_id, &txn).await?.is_some();
+        assert_loanable(&copy, on_loan)?;
+        if copy.status == CopyStatus::OnHoldShelf {
+            Self::fulfill_hold(&copy, borrower, &txn).await?;
+        }
+
+        let active_loans = loan::Entity::find()
+            .filter(loan::Column::Borrower.eq(borrower))
+            .filter(loan::Column::ReturnedAt.is_null())
+            .count(&txn)
+            .await;
+        if let Err(error) = &active_loans {
+            warn!("failed to count loans: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        assert_below_loan_limit(active_loans.unwrap(), &policy)?;
+
+        let now = Utc::now();
+        trace!("inserting loan to db");
+        let loan = loan::ActiveModel {
+            id: ActiveValue::NotSet,
+            copy: ActiveValue::Set(copy_id),
+            borrower: ActiveValue::Set(borrower),
+            checked_out_at: ActiveValue::Set(now),
+            due_at: ActiveValue::Set(due_date(&policy, now)),
+            returned_at: ActiveValue::Set(None),
+            renewals: ActiveValue::Set(0),
+        }
+        .insert(&txn)
+        .await;
+        if let Err(error) = &loan {
+            warn!("failed to insert loan: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        Self::set_copy_status(copy, CopyStatus::OnLoan, &txn).await?;
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit checkout: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(loan.unwrap())
+    }
+
+    pub async fn return_loan(
+        &self,
+        loan_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Loan, LibraryErrorStatus> {
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin return transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        let loan = Self::find_loan(loan_id, &txn).await?;
+        if loan.returned_at.is_some() {
+            return Err(LibraryErrorStatus::LoanReturned);
+        }
+
+        let mut active = loan.into_active_model();
+        active.returned_at = ActiveValue::Set(Some(Utc::now()));
+        let loan = active.update(&txn).await;
+        if let Err(error) = &loan {
+            warn!("failed to update loan: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let loan = loan.unwrap();
+
+        let copy = Self::find_copy(loan.copy, &txn).await?;
+        if copy.status == CopyStatus::OnLoan {
+            let copy = Self::set_copy_status(copy, CopyStatus::Available, &txn).await?;
+            Self::assign_copy_to_next_hold(copy, &txn).await?;
+        }
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit return: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(loan)
+    }
+
+    pub async fn renew_loan(
+        &self,
+        loan_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Loan, LibraryErrorStatus> {
+        let loan = Self::find_loan(loan_id, database).await?;
+        if loan.returned_at.is_some() {
+            return Err(LibraryErrorStatus::LoanReturned);
+        }
+
+        let copy = Self::find_copy(loan.copy, database).await?;
+        let key = Self::policy_key_for(loan.borrower, &copy, database).await?;
+        let policy = self
+            .resolve_policy(&key, database)
+            .await?
+            .unwrap_or_else(LoanPolicy::fallback);
+        let (due_at, renewals) = renewal(&loan, &policy, Utc::now())?;
+        let mut active = loan.into_active_model();
+        active.due_at = ActiveValue::Set(due_at);
+        active.renewals = ActiveValue::Set(renewals);
+        let loan = active.update(database).await;
+        if let Err(error) = &loan {
+            warn!("failed to renew loan: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(loan.unwrap())
+    }
+
+    pub async fn get_loan(
+        &self,
+        loan_id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<Loan, LibraryErrorStatus> {
+        Self::find_loan(loan_id, database).await
+    }
+
+    pub async fn get_loans(
+        &self,
+        borrower: Option<u64>,
+        active_only: bool,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<Loan>, LibraryErrorStatus> {
+        let mut query = loan::Entity::find().order_by_asc(loan::Column::Id);
+        if let Some(borrower) = borrower {
+            query = query.filter(loan::Column::Borrower.eq(borrower));
+        }
+        if active_only {
+            query = query.filter(loan::Column::ReturnedAt.is_null());
+        }
+
+        let db_result = query.all(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch loans: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    async fn find_loan<C: ConnectionTrait>(
+        loan_id: u64,
+        database: &C,
+    ) -> Result<Loan, LibraryErrorStatus> {
+        let db_result = loan::Entity::find_by_id(loan_id).one(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch loan: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let loan = db_result.unwrap();
+        if loan.is_none() {
+            return Err(LibraryErrorStatus::LoanNotFound);
+        }
+        Ok(loan.unwrap())
+    }
+
+    pub(super) async fn find_copy<C: ConnectionTrait>(
+        copy_id: u64,
+        database: &C,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        let db_result = copy::Entity::find_by_id(copy_id).one(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch copy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let copy = db_result.unwrap();
+        if copy.is_none() {
+            return Err(LibraryErrorStatus::CopyNotFound);
+        }
+        Ok(copy.unwrap())
+    }
+
+    async fn active_loan_for_copy<C: ConnectionTrait>(
+        copy_id: u64,
+        database: &C,
+    ) -> Result<Option<Loan>, LibraryErrorStatus> {
+        let db_result = loan::Entity::find()
+            .filter(loan::Column::Copy.eq(copy_id))
+            .filter(loan::Column::ReturnedAt.is_null())
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch active loan: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub(super) async fn set_copy_status<C: ConnectionTrait>(
+        copy: BookCopy,
+        status: CopyStatus,
+        database: &C,
+    ) -> Result<BookCopy, LibraryErrorStatus> {
+        let mut active = copy.into_active_model();
+        active.status = ActiveValue::Set(status);
+        active.updated_at = ActiveValue::Set(Utc::now());
+        let db_result = active.update(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to update copy status: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+}
+
+#[cfg(test)]
+fn test_policy() -> LoanPolicy {
+    LoanPolicy {
+        loan_days: 14,
+        max_renewals: 2,
+        max_loans: 3,
+        ..LoanPolicy::fallback()
+    }
+}
+
+#[test]
+fn test_assert_loanable() {
+    use chrono::NaiveDate;
+
+    use crate::orm::copy::CopyCondition;
+
+    let now = Utc::now();
+    let copy = |status| BookCopy {
+        id: 1,
+        book: 1,
+        barcode: "A0001".to_string(),
+        shelf_location: String::new(),
+        condition: CopyCondition::Good,
+        acquired_on: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+        status,
+        item_type: "book".to_string(),
+        branch: "main".to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+
+    assert!(assert_loanable(&copy(CopyStatus::Available), false).is_ok());
+    assert!(assert_loanable(&copy(CopyStatus::OnHoldShelf), false).is_ok());
+    // An open loan blocks a second checkout even if the status was never updated.
+    assert!(matches!(
+        assert_loanable(&copy(CopyStatus::Available), true),
+        Err(LibraryErrorStatus::CopyOnLoan)
+    ));
+    assert!(matches!(
+        assert_loanable(&copy(CopyStatus::OnLoan), true),
+        Err(LibraryErrorStatus::CopyOnLoan)
+    ));
+    for status in [
+        CopyStatus::OnLoan,
+        CopyStatus::InRepair,
+        CopyStatus::Lost,
+        CopyStatus::Withdrawn,
+    ] {
+        assert!(matches!(
+            assert_loanable(&copy(status), false),
+            Err(LibraryErrorStatus::CopyUnavailable)
+        ));
+    }
+
+    let policy = test_policy();
+    assert!(assert_below_loan_limit(2, &policy).is_ok());
+    assert!(matches!(
+        assert_below_loan_limit(3, &policy),
+        Err(LibraryErrorStatus::LoanLimitReached(3))
+    ));
+}
+
+#[test]
+fn test_renewal() {
+    let policy = test_policy();
+    let checked_out = DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z")
+        .unwrap()
+        .into();
+    let mut loan = Loan {
+        id: 1,
+        copy: 1,
+        borrower: 1,
+        checked_out_at: checked_out,
+        due_at: due_date(&policy, checked_out),
+        returned_at: None,
+        renewals: 0,
+    };
+    assert_eq!(loan.due_at.to_rfc3339(), "2024-03-15T10:00:00+00:00");
+
+    let now = DateTime::parse_from_rfc3339("2024-03-10T09:00:00Z")
+        .unwrap()
+        .into();
+    let (due_at, renewals) = renewal(&loan, &policy, now).unwrap();
+    // Renewing restarts the loan period from today, not from the old due date.
+    assert_eq!(due_at.to_rfc3339(), "2024-03-24T09:00:00+00:00");
+    assert_eq!(renewals, 1);
+
+    loan.renewals = 1;
+    assert_eq!(renewal(&loan, &policy, now).unwrap().1, 2);
+    loan.renewals = 2;
+    assert!(matches!(
+        renewal(&loan, &policy, now),
+        Err(LibraryErrorStatus::RenewalLimitReached(2))
+    ));
+}
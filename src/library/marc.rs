@@ -0,0 +1,906 @@
+use chrono::Utc;
+use log::warn;
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use sea_orm::DatabaseConnection;
+
+use crate::orm::{
+    book::Book,
+    book_contributor::{ContributorRole, Credit},
+};
+
+use super::{
+    catalog_csv::{flag_duplicates, publication_year_error},
+    contributor::split_name,
+    normalize_isbn,
+    sort::LEADING_ARTICLES,
+    ImportReport, ImportRow, Library, LibraryErrorStatus,
+};
+
+// ISO 2709 structure characters.
+const SUBFIELD_DELIMITER: u8 = 0x1f;
+const FIELD_TERMINATOR: u8 = 0x1e;
+const RECORD_TERMINATOR: u8 = 0x1d;
+const LEADER_LEN: usize = 24;
+const DIRECTORY_ENTRY_LEN: usize = 12;
+/// The directory gives field lengths in four digits and the leader the record length in five.
+const MAX_FIELD_LEN: usize = 9999;
+const MAX_RECORD_LEN: usize = 99999;
+/// A 520 $a this long still fits a field beside its indicators, subfield code and terminator.
+const MAX_SUMMARY_LEN: usize = MAX_FIELD_LEN - 5;
+/// New, language material, monograph, UTF-8, full level, ISBD punctuation.
+const DEFAULT_LEADER: &str = "00000nam a2200000 i 4500";
+const MARCXML_NAMESPACE: &str = "http://www.loc.gov/MARC21/slim";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarcField {
+    /// Tags `001` to `009`: a bare value without indicators or subfields.
+    Control { tag: String, value: String },
+    Data {
+        tag: String,
+        indicators: [char; 2],
+        subfields: Vec<(char, String)>,
+    },
+}
+
+impl MarcField {
+    fn data(tag: &str, indicators: [char; 2], subfields: Vec<(char, String)>) -> Self {
+        Self::Data {
+            tag: tag.to_string(),
+            indicators,
+            subfields,
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        match self {
+            Self::Control { tag, .. } | Self::Data { tag, .. } => tag,
+        }
+    }
+
+    fn indicators(&self) -> [char; 2] {
+        match self {
+            Self::Control { .. } => [' ', ' '],
+            Self::Data { indicators, .. } => *indicators,
+        }
+    }
+
+    fn subfields(&self, code: char) -> impl Iterator<Item = &str> {
+        let subfields = match self {
+            Self::Control { .. } => &[][..],
+            Self::Data { subfields, .. } => subfields.as_slice(),
+        };
+        subfields
+            .iter()
+            .filter(move |(subfield, _)| *subfield == code)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn subfield(&self, code: char) -> Option<&str> {
+        self.subfields(code).next()
+    }
+}
+
+/// A bibliographic record in MARC 21, the exchange format of library systems. Only the fields
+/// that map onto a `Book` are interpreted; everything else is carried along untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarcRecord {
+    pub leader: String,
+    pub fields: Vec<MarcField>,
+}
+
+/// Strips the ISBD punctuation catalogers append to subfields, such as the " /" ending 245 $a.
+fn clean(value: &str) -> String {
+    value
+        .trim()
+        .trim_end_matches([' ', '/', ':', ';', '=', ','])
+        .trim()
+        .to_string()
+}
+
+/// "Douglas Adams" becomes "Adams, Douglas" with first indicator 1 (surname first), the form
+/// personal name headings are recorded in.
+fn inverted_name(name: &str) -> (char, String) {
+    match split_name(name) {
+        (Some(given), family) => ('1', format!("{family}, {given}")),
+        (None, name) => ('0', name.to_string()),
+    }
+}
+
+fn direct_name(indicator: char, name: &str) -> String {
+    let name = clean(name);
+    match name.split_once(", ") {
+        Some((surname, given)) if indicator == '1' => format!("{given} {surname}"),
+        _ => name,
+    }
+}
+
+/// Reads the relator term ($e) or code ($4) of a name heading; unqualified names are authors.
+fn relator(field: &MarcField) -> ContributorRole {
+    field
+        .subfields('e')
+        .chain(field.subfields('4'))
+        .find_map(|relator| {
+            match relator
+                .trim()
+                .trim_end_matches(['.', ','])
+                .to_lowercase()
+                .as_str()
+            {
+                "edt" | "ed" | "editor" => Some(ContributorRole::Editor),
+                "trl" | "tr" | "translator" => Some(ContributorRole::Translator),
+                "ill" | "illustrator" => Some(ContributorRole::Illustrator),
+                "aut" | "author" => Some(ContributorRole::Author),
+                _ => None,
+            }
+        })
+        .unwrap_or(ContributorRole::Author)
+}
+
+fn relator_term(role: ContributorRole) -> &'static str {
+    match role {
+        ContributorRole::Author => "author",
+        ContributorRole::Editor => "editor",
+        ContributorRole::Translator => "translator",
+        ContributorRole::Illustrator => "illustrator",
+    }
+}
+
+/// The first four-digit run in `value`, e.g. 1993 from "c1993." or "[1993?]".
+fn year_in(value: &str) -> Option<u64> {
+    value
+        .as_bytes()
+        .windows(4)
+        .find(|window| window.iter().all(u8::is_ascii_digit))
+        .and_then(|window| std::str::from_utf8(window).ok()?.parse().ok())
+}
+
+/// How many leading characters filing ignores, recorded in the second indicator of 245.
+fn nonfiling_characters(title: &str) -> char {
+    let skip = match title.split_once(' ') {
+        Some((article, _)) if LEADING_ARTICLES.contains(&article.to_lowercase().as_str()) => {
+            article.chars().count() + 1
+        }
+        _ => 0,
+    };
+    char::from_digit(skip.min(9) as u32, 10).unwrap_or('0')
+}
+
+/// Splits a summary into pieces that each fit one 520 field, breaking between words where it can.
+fn split_summary(summary: &str) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut rest = summary;
+    while rest.len() > MAX_SUMMARY_LEN {
+        let mut cut = MAX_SUMMARY_LEN;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if let Some(space) = rest[..cut]
+            .rfind(char::is_whitespace)
+            .filter(|&space| space > 0)
+        {
+            cut = space;
+        }
+        pieces.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+    pieces.push(rest);
+    pieces
+}
+
+fn invalid(message: impl Into<String>) -> LibraryErrorStatus {
+    LibraryErrorStatus::MarcInvalid(message.into())
+}
+
+fn number(digits: &[u8]) -> Result<usize, LibraryErrorStatus> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| invalid("malformed length in leader or directory"))
+}
+
+impl MarcRecord {
+    fn control(&self, tag: &str) -> Option<&str> {
+        self.fields.iter().find_map(|field| match field {
+            MarcField::Control { tag: found, value } if found == tag => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    fn data_fields<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a MarcField> {
+        self.fields
+            .iter()
+            .filter(move |field| matches!(field, MarcField::Data { .. }) && field.tag() == tag)
+    }
+
+    pub fn from_book(book: &Book) -> Self {
+        let mut fields = vec![
+            MarcField::Control {
+                tag: "001".to_string(),
+                value: book.id.to_string(),
+            },
+            MarcField::Control {
+                tag: "005".to_string(),
+                value: book.updated_at.format("%Y%m%d%H%M%S.0").to_string(),
+            },
+            MarcField::Control {
+                tag: "008".to_string(),
+                value: format!(
+                    "{}s{:04}    xx {:17}und d",
+                    book.created_at.format("%y%m%d"),
+                    book.publication_year,
+                    ""
+                ),
+            },
+            MarcField::data("020", [' ', ' '], vec![('a', book.isbn.clone())]),
+        ];
+
+        let main_entry = book
+            .contributors
+            .iter()
+            .position(|credit| credit.role == ContributorRole::Author);
+        let name_field = |tag, credit: &Credit| {
+            let (indicator, name) = inverted_name(&credit.name);
+            MarcField::data(
+                tag,
+                [indicator, ' '],
+                vec![('a', name), ('e', relator_term(credit.role).to_string())],
+            )
+        };
+        if let Some(main_entry) = main_entry {
+            fields.push(name_field("100", &book.contributors[main_entry]));
+        }
+        fields.push(MarcField::data(
+            "245",
+            [
+                if main_entry.is_some() { '1' } else { '0' },
+                nonfiling_characters(&book.title),
+            ],
+            vec![('a', book.title.clone())],
+        ));
+        fields.push(MarcField::data(
+            "264",
+            [' ', '1'],
+            vec![('c', book.publication_year.to_string())],
+        ));
+        if let Some(description) = &book.description {
+            // 520 repeats, so a summary too long for one field continues in the next.
+            for piece in split_summary(description) {
+                fields.push(MarcField::data(
+                    "520",
+                    [' ', ' '],
+                    vec![('a', piece.to_string())],
+                ));
+            }
+        }
+        for subject in &book.subjects {
+            fields.push(MarcField::data(
+                "650",
+                [' ', '4'],
+                vec![('a', subject.clone())],
+            ));
+        }
+        for (position, credit) in book.contributors.iter().enumerate() {
+            if Some(position) != main_entry {
+                fields.push(name_field("700", credit));
+            }
+        }
+
+        Self {
+            leader: DEFAULT_LEADER.to_string(),
+            fields,
+        }
+    }
+
+    /// Maps the record onto a new book, along with every reason it cannot be imported as is.
+    pub fn to_book(&self) -> (Book, Vec<String>) {
+        let mut errors = vec![];
+
+        let title = self
+            .data_fields("245")
+            .next()
+            .map(|field| {
+                let title = clean(field.subfield('a').unwrap_or_default());
+                match field.subfield('b').map(clean) {
+                    Some(subtitle) if !subtitle.is_empty() => format!("{title} : {subtitle}"),
+                    _ => title,
+                }
+            })
+            .unwrap_or_default();
+        if title.is_empty() {
+            errors.push("record has no title (245 $a)".to_string());
+        }
+
+        // 020 $a often carries a qualifier such as "0152038655 (pbk.)".
+        let candidates = self
+            .data_fields("020")
+            .filter_map(|field| field.subfield('a'))
+            .filter_map(|isbn| isbn.split_whitespace().next())
+            .collect::<Vec<_>>();
+        let isbn = match candidates.iter().find_map(|isbn| normalize_isbn(isbn)) {
+            Some(isbn) => isbn,
+            None => {
+                errors.push(match candidates.first() {
+                    Some(isbn) => format!("isbn `{isbn}` is not a valid ISBN-10 or ISBN-13"),
+                    None => "record has no ISBN (020 $a)".to_string(),
+                });
+                candidates.first().unwrap_or(&"").to_string()
+            }
+        };
+
+        let publication = self
+            .data_fields("264")
+            .filter(|field| field.indicators()[1] == '1')
+            .chain(self.data_fields("260"))
+            .filter_map(|field| field.subfield('c'))
+            .find_map(year_in)
+            .or_else(|| year_in(self.control("008")?.get(7..11)?));
+        let publication_year = match publication {
+            Some(year) => {
+                errors.extend(publication_year_error(year));
+                year
+            }
+            None => {
+                errors.push("record has no publication year (264 $c, 260 $c or 008)".to_string());
+                0
+            }
+        };
+
+        let contributors = self
+            .data_fields("100")
+            .chain(self.data_fields("700"))
+            .filter_map(|field| {
+                let name = direct_name(field.indicators()[0], field.subfield('a')?);
+                Some(Credit {
+                    name,
+                    role: relator(field),
+                })
+            })
+            .collect();
+        let subjects = self
+            .data_fields("650")
+            .map(|field| {
+                ['a', 'x', 'y', 'z']
+                    .into_iter()
+                    .flat_map(|code| field.subfields(code))
+                    .map(|part| clean(part).trim_end_matches('.').to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -- ")
+            })
+            .collect();
+        let description = self
+            .data_fields("520")
+            .filter_map(|field| field.subfield('a'))
+            .map(str::trim)
+            .filter(|summary| !summary.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let description = Some(description).filter(|summary| !summary.is_empty());
+
+        let now = Utc::now();
+        let mut book = Book {
+            id: 0,
+            title,
+            author: String::new(),
+            title_sort: String::new(),
+            author_sort: String::new(),
+            contributors,
+            publication_year,
+            subjects,
+            isbn,
+            description,
+            created_at: now,
+            updated_at: now,
+        };
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+        (book, errors)
+    }
+
+    /// Encodes the record in ISO 2709, the binary MARC 21 exchange format, always as UTF-8. A field
+    /// longer than its four-digit directory length allows is cut short; a record longer than its
+    /// five-digit leader length allows is refused with `ExportError`.
+    pub fn to_marc21(&self) -> Result<Vec<u8>, LibraryErrorStatus> {
+        let mut directory = vec![];
+        let mut data = vec![];
+        for field in &self.fields {
+            let start = data.len();
+            match field {
+                MarcField::Control { value, .. } => data.extend_from_slice(value.as_bytes()),
+                MarcField::Data {
+                    indicators,
+                    subfields,
+                    ..
+                } => {
+                    for indicator in indicators {
+                        data.push(u8::try_from(*indicator).unwrap_or(b' '));
+                    }
+                    for (code, value) in subfields {
+                        data.push(SUBFIELD_DELIMITER);
+                        data.push(u8::try_from(*code).unwrap_or(b'a'));
+                        data.extend_from_slice(value.as_bytes());
+                    }
+                }
+            }
+            if data.len() - start >= MAX_FIELD_LEN {
+                let mut cut = start + MAX_FIELD_LEN - 1;
+                // Never split a UTF-8 sequence or leave a delimiter without its code.
+                while data[cut] & 0xc0 == 0x80 || data[cut - 1] == SUBFIELD_DELIMITER {
+                    cut -= 1;
+                }
+                warn!(
+                    "truncating MARC field {} to {MAX_FIELD_LEN} bytes",
+                    field.tag()
+                );
+                data.truncate(cut);
+            }
+            data.push(FIELD_TERMINATOR);
+            directory.extend_from_slice(
+                format!("{:0>3.3}{:04}{:05}", field.tag(), data.len() - start, start).as_bytes(),
+            );
+        }
+        directory.push(FIELD_TERMINATOR);
+
+        let base_address = LEADER_LEN + directory.len();
+        let length = base_address + data.len() + 1;
+        if length > MAX_RECORD_LEN {
+            warn!("MARC record of {length} bytes exceeds {MAX_RECORD_LEN}");
+            return Err(LibraryErrorStatus::ExportError);
+        }
+        let mut leader = self.leader.clone().into_bytes();
+        leader.resize(LEADER_LEN, b' ');
+        leader[0..5].copy_from_slice(format!("{length:05}").as_bytes());
+        leader[9] = b'a';
+        leader[10..12].copy_from_slice(b"22");
+        leader[12..17].copy_from_slice(format!("{base_address:05}").as_bytes());
+        leader[20..24].copy_from_slice(b"4500");
+
+        let mut record = leader;
+        record.extend(directory);
+        record.extend(data);
+        record.push(RECORD_TERMINATOR);
+        Ok(record)
+    }
+
+    fn parse_marc21(record: &[u8]) -> Result<Self, LibraryErrorStatus> {
+        let leader = String::from_utf8_lossy(&record[..LEADER_LEN]).into_owned();
+        let base_address = number(&record[12..17])?;
+        if base_address <= LEADER_LEN || base_address > record.len() {
+            return Err(invalid("base address outside of record"));
+        }
+        let directory = &record[LEADER_LEN..base_address - 1];
+        if !directory.len().is_multiple_of(DIRECTORY_ENTRY_LEN) {
+            return Err(invalid("directory is not a whole number of entries"));
+        }
+
+        let mut fields = vec![];
+        for entry in directory.chunks(DIRECTORY_ENTRY_LEN) {
+            let tag = String::from_utf8_lossy(&entry[0..3]).into_owned();
+            let length = number(&entry[3..7])?;
+            let start = base_address + number(&entry[7..12])?;
+            let Some(field) = record.get(start..start + length) else {
+                return Err(invalid(format!("field {tag} extends past the record")));
+            };
+            let field = field.strip_suffix(&[FIELD_TERMINATOR]).unwrap_or(field);
+
+            if tag.starts_with("00") {
+                fields.push(MarcField::Control {
+                    tag,
+                    value: String::from_utf8_lossy(field).into_owned(),
+                });
+                continue;
+            }
+            if field.len() < 2 {
+                return Err(invalid(format!("field {tag} has no indicators")));
+            }
+            let subfields = field[2..]
+                .split(|byte| *byte == SUBFIELD_DELIMITER)
+                .skip(1)
+                .filter_map(|subfield| {
+                    let value = String::from_utf8_lossy(subfield);
+                    let mut chars = value.chars();
+                    Some((chars.next()?, chars.as_str().to_string()))
+                })
+                .collect();
+            fields.push(MarcField::data(
+                &tag,
+                [char::from(field[0]), char::from(field[1])],
+                subfields,
+            ));
+        }
+        Ok(Self { leader, fields })
+    }
+
+    /// Decodes a file of concatenated ISO 2709 records. Records in the legacy MARC-8 encoding are
+    /// read as UTF-8, so their non-ASCII characters come through garbled.
+    pub fn from_marc21(mut data: &[u8]) -> Result<Vec<Self>, LibraryErrorStatus> {
+        let mut records = vec![];
+        loop {
+            // Files written on other systems sometimes put line breaks between records.
+            data = data.trim_ascii_start();
+            if data.is_empty() {
+                return Ok(records);
+            }
+            if data.len() < LEADER_LEN {
+                return Err(invalid("truncated leader"));
+            }
+            let length = number(&data[0..5])?;
+            if length <= LEADER_LEN || length > data.len() {
+                return Err(invalid("record length outside of file"));
+            }
+            records.push(Self::parse_marc21(&data[..length])?);
+            data = &data[length..];
+        }
+    }
+
+    /// Encodes `records` as a MARCXML collection.
+    pub fn to_marcxml(records: &[Self]) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<collection xmlns=\"{MARCXML_NAMESPACE}\">\n"
+        );
+        for record in records {
+            xml.push_str("  <record>\n");
+            xml.push_str(&format!(
+                "    <leader>{}</leader>\n",
+                escape(&record.leader)
+            ));
+            for field in &record.fields {
+                match field {
+                    MarcField::Control { tag, value } => xml.push_str(&format!(
+                        "    <controlfield tag=\"{}\">{}</controlfield>\n",
+                        escape(tag),
+                        escape(value)
+                    )),
+                    MarcField::Data {
+                        tag,
+                        indicators: [ind1, ind2],
+                        subfields,
+                    } => {
+                        xml.push_str(&format!(
+                            "    <datafield tag=\"{}\" ind1=\"{}\" ind2=\"{}\">\n",
+                            escape(tag),
+                            escape(ind1.to_string()),
+                            escape(ind2.to_string())
+                        ));
+                        for (code, value) in subfields {
+                            xml.push_str(&format!(
+                                "      <subfield code=\"{}\">{}</subfield>\n",
+                                escape(code.to_string()),
+                                escape(value)
+                            ));
+                        }
+                        xml.push_str("    </datafield>\n");
+                    }
+                }
+            }
+            xml.push_str("  </record>\n");
+        }
+        xml.push_str("</collection>\n");
+        xml
+    }
+
+    /// Decodes every `record` element of a MARCXML document, with or without a namespace prefix.
+    pub fn from_marcxml(xml: &str) -> Result<Vec<Self>, LibraryErrorStatus> {
+        let mut parser = MarcXmlParser::default();
+        let mut reader = Reader::from_str(xml);
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|error| invalid(format!("malformed MARCXML: {error}")))?;
+            match event {
+                Event::Start(element) => parser.start(&element)?,
+                Event::Empty(element) => {
+                    parser.start(&element)?;
+                    parser.end(element.local_name().as_ref());
+                }
+                Event::End(element) => parser.end(element.local_name().as_ref()),
+                Event::Text(text) => {
+                    let text = text
+                        .unescape()
+                        .map_err(|error| invalid(format!("malformed MARCXML: {error}")))?;
+                    parser.text.push_str(&text);
+                }
+                Event::CData(text) => parser.text.push_str(&String::from_utf8_lossy(&text)),
+                Event::Eof => return Ok(parser.records),
+                _ => {}
+            }
+        }
+    }
+
+    /// Decodes MARCXML or ISO 2709, whichever `data` holds.
+    pub fn read(data: &[u8]) -> Result<Vec<Self>, LibraryErrorStatus> {
+        let data = data.strip_prefix("\u{feff}".as_bytes()).unwrap_or(data);
+        if data.trim_ascii_start().starts_with(b"<") {
+            let xml = std::str::from_utf8(data).map_err(|_| invalid("MARCXML is not UTF-8"))?;
+            Self::from_marcxml(xml)
+        } else {
+            Self::from_marc21(data)
+        }
+    }
+}
+
+#[derive(Default)]
+struct MarcXmlParser {
+    records: Vec<MarcRecord>,
+    record: Option<MarcRecord>,
+    field: Option<MarcField>,
+    control_tag: Option<String>,
+    subfield_code: Option<char>,
+    text: String,
+}
+
+impl MarcXmlParser {
+    fn attribute(element: &BytesStart, name: &str) -> Result<String, LibraryErrorStatus> {
+        let attribute = element
+            .try_get_attribute(name)
+            .map_err(|error| invalid(format!("malformed MARCXML: {error}")))?
+            .ok_or_else(|| invalid(format!("MARCXML element is missing `{name}`")))?;
+        let value = attribute
+            .unescape_value()
+            .map_err(|error| invalid(format!("malformed MARCXML: {error}")))?;
+        Ok(value.into_owned())
+    }
+
+    fn indicator(element: &BytesStart, name: &str) -> char {
+        Self::attribute(element, name)
+            .ok()
+            .and_then(|value| value.chars().next())
+            .unwrap_or(' ')
+    }
+
+    fn start(&mut self, element: &BytesStart) -> Result<(), LibraryErrorStatus> {
+        self.text.clear();
+        match element.local_name().as_ref() {
+            b"record" => {
+                self.record = Some(MarcRecord {
+                    leader: DEFAULT_LEADER.to_string(),
+                    fields: vec![],
+                })
+            }
+            b"controlfield" => self.control_tag = Some(Self::attribute(element, "tag")?),
+            b"datafield" => {
+                self.field = Some(MarcField::data(
+                    &Self::attribute(element, "tag")?,
+                    [
+                        Self::indicator(element, "ind1"),
+                        Self::indicator(element, "ind2"),
+                    ],
+                    vec![],
+                ))
+            }
+            b"subfield" => {
+                self.subfield_code = Self::attribute(element, "code")?.chars().next();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, name: &[u8]) {
+        let text = std::mem::take(&mut self.text);
+        match (name, &mut self.record) {
+            (b"record", record) => self.records.extend(record.take()),
+            (b"leader", Some(record)) => record.leader = text,
+            (b"controlfield", Some(record)) => {
+                if let Some(tag) = self.control_tag.take() {
+                    record.fields.push(MarcField::Control { tag, value: text });
+                }
+            }
+            (b"datafield", Some(record)) => record.fields.extend(self.field.take()),
+            (b"subfield", _) => {
+                if let (Some(MarcField::Data { subfields, .. }), Some(code)) =
+                    (&mut self.field, self.subfield_code.take())
+                {
+                    subfields.push((code, text));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Library {
+    /// Imports MARC 21 records, binary or MARCXML, with the same all-or-nothing rules as CSV.
+    pub async fn import_marc(
+        &mut self,
+        data: &[u8],
+        dry_run: bool,
+        database: &DatabaseConnection,
+    ) -> Result<ImportReport, LibraryErrorStatus> {
+        let mut rows = MarcRecord::read(data)?
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let (book, errors) = record.to_book();
+                let row = ImportRow {
+                    line: index as u64 + 1,
+                    title: book.title.clone(),
+                    isbn: book.isbn.clone(),
+                    errors,
+                };
+                (row, book)
+            })
+            .collect::<Vec<_>>();
+        flag_duplicates(&mut rows, "record");
+        self.import_rows(rows, dry_run, database).await
+    }
+}
+
+#[cfg(test)]
+fn sample_book() -> Book {
+    let mut book = Book {
+        id: 42,
+        title: "The Restaurant at the End of the Universe".to_string(),
+        author: String::new(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![
+            Credit {
+                name: "Douglas Adams".to_string(),
+                role: ContributorRole::Author,
+            },
+            Credit {
+                name: "Peter Stöckl".to_string(),
+                role: ContributorRole::Illustrator,
+            },
+        ],
+        publication_year: 1980,
+        subjects: vec!["Science fiction".to_string(), "Humor & satire".to_string()],
+        isbn: "9780345391810".to_string(),
+        description: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    book.normalize_contributors();
+    book.refresh_sort_keys();
+    book
+}
+
+#[cfg(test)]
+fn assert_same_book(imported: &Book, original: &Book) {
+    assert_eq!(imported.title, original.title);
+    assert_eq!(imported.author, original.author);
+    assert_eq!(imported.contributors, original.contributors);
+    assert_eq!(imported.publication_year, original.publication_year);
+    assert_eq!(imported.subjects, original.subjects);
+    assert_eq!(imported.isbn, original.isbn);
+    assert_eq!(imported.title_sort, original.title_sort);
+}
+
+#[test]
+fn test_marc21_round_trip() {
+    let book = sample_book();
+    let record = MarcRecord::from_book(&book);
+    assert!(record.fields.contains(&MarcField::data(
+        "245",
+        ['1', '4'],
+        vec![('a', book.title.clone())]
+    )));
+
+    let mut file = record.to_marc21().unwrap();
+    file.extend(MarcRecord::from_book(&book).to_marc21().unwrap());
+    let length = number(&file[0..5]).unwrap();
+    assert_eq!(file[length - 1], RECORD_TERMINATOR);
+
+    let records = MarcRecord::read(&file).expect("records should decode");
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].fields, record.fields);
+    let (imported, errors) = records[0].to_book();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_same_book(&imported, &book);
+
+    assert!(matches!(
+        MarcRecord::read(b"00042nam"),
+        Err(LibraryErrorStatus::MarcInvalid(_))
+    ));
+    assert!(matches!(
+        MarcRecord::read(&file[..length - 10]),
+        Err(LibraryErrorStatus::MarcInvalid(_))
+    ));
+
+    // A long summary spreads over several 520 fields and comes back whole.
+    let mut long = book.clone();
+    long.description = Some("Don't panic. ".repeat(2000).trim_end().to_string());
+    let record = MarcRecord::from_book(&long);
+    assert_eq!(record.data_fields("520").count(), 3);
+    let binary = record.to_marc21().unwrap();
+    let records = MarcRecord::read(&binary).expect("record should decode");
+    assert_eq!(records[0].to_book().0.description, long.description);
+
+    // Any other overlong field is cut short on a character boundary.
+    let mut record = MarcRecord::from_book(&book);
+    record.fields.push(MarcField::data(
+        "500",
+        [' ', ' '],
+        vec![('a', "é".repeat(6000))],
+    ));
+    let binary = record.to_marc21().unwrap();
+    let records = MarcRecord::read(&binary).expect("record should decode");
+    let note = records[0].data_fields("500").next().unwrap().subfield('a');
+    assert_eq!(note, Some("é".repeat(4997).as_str()));
+
+    long.description = Some("Don't panic. ".repeat(8000));
+    assert!(matches!(
+        MarcRecord::from_book(&long).to_marc21(),
+        Err(LibraryErrorStatus::ExportError)
+    ));
+}
+
+#[test]
+fn test_marcxml_round_trip() {
+    let book = sample_book();
+    let xml = MarcRecord::to_marcxml(&[MarcRecord::from_book(&book)]);
+    assert!(xml.contains("<subfield code=\"a\">Humor &amp; satire</subfield>"));
+    assert!(xml.contains("<subfield code=\"a\">Stöckl, Peter</subfield>"));
+
+    let records = MarcRecord::read(xml.as_bytes()).expect("MARCXML should decode");
+    assert_eq!(records, vec![MarcRecord::from_book(&book)]);
+    let (imported, errors) = records[0].to_book();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_same_book(&imported, &book);
+}
+
+#[test]
+fn test_marcxml_catalog_record() {
+    // A Library of Congress record, with its ISBD punctuation and namespace prefix intact.
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<marc:collection xmlns:marc="http://www.loc.gov/MARC21/slim">
+  <marc:record>
+    <marc:leader>01142cam  2200301 a 4500</marc:leader>
+    <marc:controlfield tag="001">   92005291 </marc:controlfield>
+    <marc:controlfield tag="008">920219s1993    caua   j      000 0 eng  </marc:controlfield>
+    <marc:datafield tag="020" ind1=" " ind2=" ">
+      <marc:subfield code="a">0152038655 :</marc:subfield>
+      <marc:subfield code="c">$15.95</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="100" ind1="1" ind2=" ">
+      <marc:subfield code="a">Sandburg, Carl,</marc:subfield>
+      <marc:subfield code="d">1878-1967.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="245" ind1="1" ind2="0">
+      <marc:subfield code="a">Arithmetic /</marc:subfield>
+      <marc:subfield code="c">Carl Sandburg ; illustrated as an anamorphic adventure by Ted Rand.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="260" ind1=" " ind2=" ">
+      <marc:subfield code="a">San Diego :</marc:subfield>
+      <marc:subfield code="b">Harcourt Brace Jovanovich,</marc:subfield>
+      <marc:subfield code="c">c1993.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="650" ind1=" " ind2="0">
+      <marc:subfield code="a">Arithmetic</marc:subfield>
+      <marc:subfield code="x">Juvenile poetry.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="700" ind1="1" ind2=" ">
+      <marc:subfield code="a">Rand, Ted,</marc:subfield>
+      <marc:subfield code="e">ill.</marc:subfield>
+    </marc:datafield>
+  </marc:record>
+</marc:collection>"#;
+    let records = MarcRecord::read(xml.as_bytes()).expect("MARCXML should decode");
+    let (book, errors) = records[0].to_book();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(book.title, "Arithmetic");
+    assert_eq!(book.isbn, normalize_isbn("0152038655").unwrap());
+    assert_eq!(book.publication_year, 1993);
+    assert_eq!(book.author, "Carl Sandburg");
+    assert_eq!(
+        book.contributors[1],
+        Credit {
+            name: "Ted Rand".to_string(),
+            role: ContributorRole::Illustrator
+        }
+    );
+    assert_eq!(book.subjects, vec!["Arithmetic -- Juvenile poetry"]);
+
+    // The same record survives a trip through ISO 2709 unchanged.
+    let binary = records[0].to_marc21().unwrap();
+    let decoded = MarcRecord::read(&binary).expect("record should decode");
+    assert_eq!(decoded[0].fields, records[0].fields);
+    assert_eq!(decoded[0].leader[5..9], records[0].leader[5..9]);
+    // Everything is written as UTF-8, whatever the source record declared.
+    assert_eq!(&decoded[0].leader[9..10], "a");
+}
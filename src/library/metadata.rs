@@ -0,0 +1,376 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
+use log::{trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::orm::{
+    book::Book,
+    book_contributor::{ContributorRole, Credit},
+    metadata_cache,
+};
+
+use super::{normalize_isbn, Library, LibraryErrorStatus};
+
+/// Cached answers older than this are fetched again, so corrections upstream reach us.
+const METADATA_CACHE_DAYS: i64 = 30;
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a provider knows about a book. Anything it does not know is `None` or empty.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct BookMetadata {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub contributors: Vec<Credit>,
+    pub publication_year: Option<u64>,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl BookMetadata {
+    /// Fills in the fields of `book` that are still blank, leaving whatever staff typed alone.
+    pub fn fill(&self, book: &mut Book) {
+        if book.title.trim().is_empty() {
+            if let Some(title) = &self.title {
+                book.title = title.clone();
+            }
+        }
+        if book.contributors.is_empty() && book.author.trim().is_empty() {
+            book.contributors = self.contributors.clone();
+        }
+        if book.publication_year == 0 {
+            if let Some(year) = self.publication_year {
+                book.publication_year = year;
+            }
+        }
+        if book.subjects.is_empty() {
+            book.subjects = self.subjects.clone();
+        }
+        if book.description.is_none() {
+            book.description = self.description.clone();
+        }
+    }
+}
+
+/// A source of bibliographic metadata keyed by ISBN, such as Open Library.
+#[async_trait]
+pub trait MetadataProvider: Debug + Send + Sync {
+    /// Identifies the provider in the cache, so answers from one are never served as another's.
+    fn name(&self) -> &str;
+
+    /// What the provider knows about the ISBN-13 `isbn`, or `None` when it does not know it.
+    async fn lookup(&self, isbn: &str) -> Result<Option<BookMetadata>, LibraryErrorStatus>;
+}
+
+/// Looks books up through the Open Library Books API.
+#[derive(Debug, Clone)]
+pub struct OpenLibrary {
+    client: reqwest::Client,
+    /// Without a trailing slash, e.g. `https://openlibrary.org`.
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenLibraryEdition {
+    title: Option<String>,
+    subtitle: Option<String>,
+    #[serde(default)]
+    authors: Vec<OpenLibraryName>,
+    publish_date: Option<String>,
+    #[serde(default)]
+    subjects: Vec<OpenLibraryName>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenLibraryName {
+    name: String,
+}
+
+/// The first four-digit run in a free-form date such as "October 12, 1979".
+fn year_of(date: &str) -> Option<u64> {
+    date.split(|c: char| !c.is_ascii_digit())
+        .find(|run| run.len() == 4)
+        .and_then(|run| run.parse().ok())
+}
+
+impl From<OpenLibraryEdition> for BookMetadata {
+    fn from(edition: OpenLibraryEdition) -> Self {
+        let title = edition.title.map(|title| match edition.subtitle {
+            Some(subtitle) => format!("{title}: {subtitle}"),
+            None => title,
+        });
+        Self {
+            title,
+            contributors: edition
+                .authors
+                .into_iter()
+                .map(|author| Credit {
+                    name: author.name,
+                    role: ContributorRole::Author,
+                })
+                .collect(),
+            publication_year: edition.publish_date.as_deref().and_then(year_of),
+            subjects: edition
+                .subjects
+                .into_iter()
+                .map(|subject| subject.name)
+                .collect(),
+            description: None,
+        }
+    }
+}
+
+impl OpenLibrary {
+    /// `user_agent` should carry a contact address, as Open Library asks of API clients.
+    pub fn new(base_url: &str, user_agent: &str) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .timeout(LOOKUP_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for OpenLibrary {
+    fn name(&self) -> &str {
+        "open_library"
+    }
+
+    async fn lookup(&self, isbn: &str) -> Result<Option<BookMetadata>, LibraryErrorStatus> {
+        let key = format!("ISBN:{isbn}");
+        let response = self
+            .client
+            .get(format!("{}/api/books", self.base_url))
+            .query(&[
+                ("bibkeys", key.as_str()),
+                ("format", "json"),
+                ("jscmd", "data"),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(error) = &response {
+            warn!("failed to look up {isbn} on Open Library: {error}");
+            return Err(LibraryErrorStatus::MetadataUnavailable);
+        }
+        let editions = response
+            .unwrap()
+            .json::<HashMap<String, OpenLibraryEdition>>()
+            .await;
+        if let Err(error) = &editions {
+            warn!("failed to parse Open Library answer for {isbn}: {error}");
+            return Err(LibraryErrorStatus::MetadataUnavailable);
+        }
+        // Unknown ISBNs are simply absent from the answer.
+        Ok(editions.unwrap().remove(&key).map(BookMetadata::from))
+    }
+}
+
+impl Library {
+    pub fn with_metadata_provider(self, metadata: Arc<dyn MetadataProvider>) -> Self {
+        Self {
+            metadata: Some(metadata),
+            ..self
+        }
+    }
+
+    /// The configured provider, for callers to look books up without holding on to the library.
+    pub fn metadata_provider(&self) -> Option<Arc<dyn MetadataProvider>> {
+        self.metadata.clone()
+    }
+
+    /// What `provider` knows about `isbn`, answered from the cache when it is fresh.
+    pub async fn lookup_metadata(
+        provider: Option<&dyn MetadataProvider>,
+        isbn: &str,
+        database: &DatabaseConnection,
+    ) -> Result<BookMetadata, LibraryErrorStatus> {
+        let isbn = normalize_isbn(isbn).ok_or(LibraryErrorStatus::IsbnInvalid)?;
+        let Some(provider) = provider else {
+            return Err(LibraryErrorStatus::MetadataUnavailable);
+        };
+
+        let db_result = metadata_cache::Entity::find()
+            .filter(metadata_cache::Column::Provider.eq(provider.name()))
+            .filter(metadata_cache::Column::Isbn.eq(&isbn))
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to read metadata cache: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let cached = db_result.unwrap();
+        let cutoff = Utc::now() - TimeDelta::days(METADATA_CACHE_DAYS);
+        if let Some(entry) = cached.as_ref().filter(|entry| entry.fetched_at > cutoff) {
+            trace!("metadata cache hit for {isbn}");
+            let metadata = match &entry.response {
+                Some(response) => serde_json::from_str(response).ok(),
+                None => None,
+            };
+            return metadata.ok_or(LibraryErrorStatus::MetadataNotFound);
+        }
+
+        let metadata = provider.lookup(&isbn).await?;
+        let response = metadata
+            .as_ref()
+            .map(|metadata| serde_json::to_string(metadata).unwrap());
+        let entry = metadata_cache::ActiveModel {
+            id: cached.map_or(ActiveValue::NotSet, |entry| {
+                ActiveValue::Unchanged(entry.id)
+            }),
+            provider: ActiveValue::Set(provider.name().to_string()),
+            isbn: ActiveValue::Set(isbn.clone()),
+            response: ActiveValue::Set(response),
+            fetched_at: ActiveValue::Set(Utc::now()),
+        };
+        // The answer is still good to use when it cannot be cached.
+        if let Err(error) = entry.save(database).await {
+            warn!("failed to cache metadata for {isbn}: {error}");
+        }
+        metadata.ok_or(LibraryErrorStatus::MetadataNotFound)
+    }
+
+    /// A new, unsaved book pre-filled from what `provider` knows about `isbn`.
+    pub async fn book_draft(
+        provider: Option<&dyn MetadataProvider>,
+        isbn: &str,
+        database: &DatabaseConnection,
+    ) -> Result<Book, LibraryErrorStatus> {
+        let metadata = Self::lookup_metadata(provider, isbn, database).await?;
+        let now = Utc::now();
+        let mut book = Book {
+            id: 0,
+            title: String::new(),
+            author: String::new(),
+            title_sort: String::new(),
+            author_sort: String::new(),
+            contributors: vec![],
+            publication_year: 0,
+            subjects: vec![],
+            isbn: normalize_isbn(isbn).ok_or(LibraryErrorStatus::IsbnInvalid)?,
+            description: None,
+            created_at: now,
+            updated_at: now,
+        };
+        metadata.fill(&mut book);
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+        Ok(book)
+    }
+
+    /// Fills the blank fields of `book` from `provider` before it is added. Enrichment is a
+    /// convenience, so a book the provider cannot help with is left as typed.
+    pub async fn enrich_book(
+        provider: Option<&dyn MetadataProvider>,
+        book: &mut Book,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        match Self::lookup_metadata(provider, &book.isbn, database).await {
+            Ok(metadata) => metadata.fill(book),
+            Err(
+                status @ (LibraryErrorStatus::MetadataNotFound
+                | LibraryErrorStatus::MetadataUnavailable),
+            ) => warn!("adding {} without enrichment: {status}", book.isbn),
+            Err(status) => return Err(status),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+async fn mock_open_library() -> String {
+    use axum::{extract::Query, routing::get, Json, Router};
+    use serde_json::{json, Value};
+
+    let router = Router::new().route(
+        "/api/books",
+        get(|Query(query): Query<HashMap<String, String>>| async move {
+            let answer = match query["bibkeys"].as_str() {
+                "ISBN:9780345391803" => json!({
+                    "ISBN:9780345391803": {
+                        "title": "The Hitchhiker's Guide to the Galaxy",
+                        "subtitle": "A Novel",
+                        "authors": [{"url": "/authors/OL272947A", "name": "Douglas Adams"}],
+                        "publish_date": "September 27, 1995",
+                        "subjects": [{"name": "Science fiction"}, {"name": "Humor"}]
+                    }
+                }),
+                _ => json!({}),
+            };
+            Json::<Value>(answer)
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{address}/")
+}
+
+#[test]
+fn test_year_of() {
+    assert_eq!(year_of("1979"), Some(1979));
+    assert_eq!(year_of("October 12, 1979"), Some(1979));
+    assert_eq!(year_of("c1995-2001"), Some(1995));
+    assert_eq!(year_of("12345"), None);
+    assert_eq!(year_of("n.d."), None);
+}
+
+#[tokio::test]
+async fn test_open_library_lookup() {
+    let base_url = mock_open_library().await;
+    let provider = OpenLibrary::new(&base_url, "ABLE test").unwrap();
+
+    let metadata = provider.lookup("9780345391803").await.unwrap().unwrap();
+    assert_eq!(
+        metadata,
+        BookMetadata {
+            title: Some("The Hitchhiker's Guide to the Galaxy: A Novel".to_string()),
+            contributors: vec![Credit {
+                name: "Douglas Adams".to_string(),
+                role: ContributorRole::Author,
+            }],
+            publication_year: Some(1995),
+            subjects: vec!["Science fiction".to_string(), "Humor".to_string()],
+            description: None,
+        }
+    );
+    assert_eq!(provider.lookup("9780141439518").await.unwrap(), None);
+
+    let unreachable = OpenLibrary::new("http://127.0.0.1:1", "ABLE test").unwrap();
+    assert!(matches!(
+        unreachable.lookup("9780345391803").await,
+        Err(LibraryErrorStatus::MetadataUnavailable)
+    ));
+
+    let now = Utc::now();
+    let mut book = Book {
+        id: 0,
+        title: String::new(),
+        author: String::new(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![],
+        publication_year: 1979,
+        subjects: vec![],
+        isbn: "9780345391803".to_string(),
+        description: Some("Don't panic.".to_string()),
+        created_at: now,
+        updated_at: now,
+    };
+    metadata.fill(&mut book);
+    assert_eq!(book.title, "The Hitchhiker's Guide to the Galaxy: A Novel");
+    assert_eq!(book.contributors.len(), 1);
+    assert_eq!(book.publication_year, 1979);
+    assert_eq!(book.subjects, ["Science fiction", "Humor"]);
+    assert_eq!(book.description.as_deref(), Some("Don't panic."));
+}
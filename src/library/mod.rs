@@ -1,29 +1,127 @@
 use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 use chrono::Utc;
-use log::{info, trace, warn};
+use log::{trace, warn};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
-    TryIntoModel,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 use tokio::sync::Mutex;
 
 use crate::{
-    model::request::{pagination::Pagination, search::BookSearch},
-    orm::book::{self, Book},
+    model::request::{pagination::Pagination, search::BookSearch, sort::BookSorting},
+    orm::{
+        book::{self, Book},
+        book_tombstone,
+    },
 };
 
+mod catalog_csv;
+mod citation;
+mod contributor;
+mod copy;
+mod cover;
+mod cql;
+mod cursor;
+mod dublin_core;
+mod facet;
+mod feed;
+mod fuzzy;
+mod hold;
+mod index;
+mod isbn;
+mod ledger;
+mod linked_data;
+mod loan;
+mod marc;
+mod metadata;
+mod oai;
+mod onix;
+mod opds;
+mod policy;
+mod query;
+mod search;
+mod sort;
+mod sru;
+mod subject;
+
+pub use catalog_csv::{ImportReport, ImportRow, MAX_IMPORT_BYTES};
+pub use citation::{bibliography, CitationFormat, MAX_BIBLIOGRAPHY_BOOKS};
+pub use contributor::{display_author, split_author};
+pub use copy::CopyCounts;
+pub use cover::{Cover, CoverSize, CoverStore, MAX_COVER_BYTES};
+pub use cql::{parse_cql, CqlError};
+pub use cursor::Cursor;
+pub use facet::{FacetBucket, Facets};
+pub use feed::{FeedChannel, FeedFormat, NewArrivals, FEED_SIZE};
+pub use fuzzy::similarity;
+pub use index::{SearchHighlights, SearchHit, SearchIndex};
+pub use isbn::{normalize_isbn, strip_isbn};
+pub use ledger::{LedgerTotals, MAX_LEDGER_AMOUNT};
+pub use linked_data::LinkedDataFormat;
+pub use marc::{MarcField, MarcRecord};
+pub use metadata::{BookMetadata, MetadataProvider, OpenLibrary};
+pub use oai::{OaiRepository, OAI_PAGE_SIZE};
+pub use onix::{OnixOutcome, OnixProductReport, OnixReport};
+pub use opds::{OpdsCatalog, OpdsFeed, OpdsFormat, OpdsRequest, OPDS_PAGE_SIZE};
+pub use policy::{resolve_policy, PolicyKey};
+pub use query::{parse_query, QueryField, QueryNode, QuerySyntaxError, QueryValue};
+pub use search::{search_condition, BookPage};
+pub use sort::{author_sort_key, title_sort_key};
+pub use sru::SruServer;
+
+use cursor::page_offset;
+use sort::{keyset_condition, sort_column, sort_value};
+
+const REINDEX_BATCH_SIZE: u64 = 1000;
+
 #[derive(Debug, Clone, Default)]
 pub struct Library {
     books: Arc<Mutex<HashMap<String, Book>>>,
+    search_index: Option<SearchIndex>,
+    covers: Option<CoverStore>,
+    metadata: Option<Arc<dyn MetadataProvider>>,
 }
 
 #[derive(Debug)]
 pub enum LibraryErrorStatus {
     IsbnExists,
     IsbnMismatch,
+    IsbnInvalid,
     IdNotFound,
+    ContributorNotFound,
     PaginationInvalid,
+    CursorInvalid,
+    QueryInvalid(QuerySyntaxError),
+    CopyNotFound,
+    BarcodeExists,
+    UserNotFound,
+    LoanNotFound,
+    CopyOnLoan,
+    CopyUnavailable,
+    LoanReturned,
+    RenewalLimitReached(u32),
+    LoanLimitReached(u32),
+    HoldNotFound,
+    HoldExists,
+    HoldClosed,
+    CopyOnHold,
+    AmountInvalid,
+    LedgerOverflow,
+    PolicyNotFound,
+    PolicyInvalid,
+    SearchIndexError,
+    CoverNotFound,
+    CoverInvalid,
+    CoverTooLarge,
+    CoverStoreError,
+    ImportInvalid(String),
+    MarcInvalid(String),
+    OnixInvalid(String),
+    BibliographyTooLarge,
+    MetadataNotFound,
+    MetadataUnavailable,
+    ExportError,
     DatabaseError,
 }
 
@@ -32,152 +130,240 @@ impl Display for LibraryErrorStatus {
         match self {
             Self::IsbnExists => f.write_str("isbn exists"),
             Self::IsbnMismatch => f.write_str("isbn mismatch"),
+            Self::IsbnInvalid => f.write_str("isbn is not a valid ISBN-10 or ISBN-13"),
             Self::IdNotFound => f.write_str("id not found"),
+            Self::ContributorNotFound => f.write_str("contributor not found"),
             Self::PaginationInvalid => f.write_str("pagination invalid"),
+            Self::CursorInvalid => f.write_str("cursor invalid"),
+            Self::QueryInvalid(error) => write!(f, "invalid query: {error}"),
+            Self::CopyNotFound => f.write_str("copy not found"),
+            Self::BarcodeExists => f.write_str("barcode exists"),
+            Self::UserNotFound => f.write_str("user not found"),
+            Self::LoanNotFound => f.write_str("loan not found"),
+            Self::CopyOnLoan => f.write_str("copy is already on loan"),
+            Self::CopyUnavailable => f.write_str("copy is not available for loan"),
+            Self::LoanReturned => f.write_str("loan already returned"),
+            Self::RenewalLimitReached(max) => {
+                write!(f, "loan has reached the maximum of {max} renewals")
+            }
+            Self::LoanLimitReached(max) => {
+                write!(
+                    f,
+                    "borrower has reached the maximum of {max} concurrent loans"
+                )
+            }
+            Self::HoldNotFound => f.write_str("hold not found"),
+            Self::HoldExists => f.write_str("patron already holds this title"),
+            Self::HoldClosed => f.write_str("hold is no longer active"),
+            Self::CopyOnHold => f.write_str("copy is on hold for another patron"),
+            Self::AmountInvalid => {
+                write!(f, "amount must be between 1 and {MAX_LEDGER_AMOUNT}")
+            }
+            Self::LedgerOverflow => f.write_str("ledger totals out of range"),
+            Self::PolicyNotFound => f.write_str("loan policy not found"),
+            Self::PolicyInvalid => write!(
+                f,
+                "loan period must be at least one day and fines at most {MAX_LEDGER_AMOUNT} a day"
+            ),
+            Self::SearchIndexError => f.write_str("search index error"),
+            Self::CoverNotFound => f.write_str("book has no cover"),
+            Self::CoverInvalid => f.write_str("cover must be a JPEG, PNG or WebP image"),
+            Self::CoverTooLarge => write!(f, "cover exceeds {MAX_COVER_BYTES} bytes"),
+            Self::CoverStoreError => f.write_str("cover storage error"),
+            Self::ImportInvalid(reason) => write!(f, "invalid import: {reason}"),
+            Self::MarcInvalid(reason) => write!(f, "invalid MARC record: {reason}"),
+            Self::OnixInvalid(reason) => write!(f, "invalid ONIX message: {reason}"),
+            Self::BibliographyTooLarge => write!(
+                f,
+                "a bibliography may cite at most {MAX_BIBLIOGRAPHY_BOOKS} books"
+            ),
+            Self::MetadataNotFound => f.write_str("no metadata found for this isbn"),
+            Self::MetadataUnavailable => f.write_str("metadata provider unavailable"),
+            Self::ExportError => f.write_str("export error"),
             Self::DatabaseError => f.write_str("database error"),
         }
     }
 }
 
 impl Library {
-    pub async fn full_sync(
-        &mut self,
-        database: &DatabaseConnection,
-    ) -> Result<(), LibraryErrorStatus> {
-        info!("Syncronizing library.");
-
-        let db_result = book::Entity::find().all(database).await;
-        if let Err(error) = db_result {
-            warn!("failed to add book: {}", error.to_string());
-            return Err(LibraryErrorStatus::DatabaseError);
+    pub fn with_search_index(search_index: SearchIndex) -> Self {
+        Self {
+            search_index: Some(search_index),
+            ..Self::default()
         }
-        let db_books = db_result.unwrap();
-        let db_books_as_isbn = db_books.iter().map(|book| &book.isbn).collect::<Vec<_>>();
+    }
 
-        let books = self.books.lock().await;
-        let missing = books
-            .iter()
-            .filter(|(isbn, _)| !db_books_as_isbn.contains(isbn))
-            .map(|(_, book)| book.clone().into_active_model())
-            .collect::<Vec<_>>();
-        drop(books);
-
-        let mut books = db_books
-            .into_iter()
-            .map(|book| (book.isbn.clone(), book))
-            .collect::<HashMap<String, Book>>();
-        for book in &missing {
-            let book = book
-                .clone()
-                .try_into_model()
-                .expect("failed to convert a known good model to a model.");
-            books.insert(book.isbn.clone(), book as Book);
-        }
-
-        if !missing.is_empty() {
-            let db_result = book::Entity::insert_many(missing).exec(database).await;
-            if let Err(error) = db_result {
-                warn!("failed to sync library: {}", error.to_string());
-                return Err(LibraryErrorStatus::DatabaseError);
-            }
+    pub fn with_cover_store(self, covers: CoverStore) -> Self {
+        Self {
+            covers: Some(covers),
+            ..self
         }
-        Ok(())
     }
 
     pub async fn add_book(
         &mut self,
-        book: Book,
+        mut book: Book,
         database: &DatabaseConnection,
     ) -> Result<(), LibraryErrorStatus> {
-        let isbn = &book.isbn;
+        book.isbn = normalize_isbn(&book.isbn).ok_or(LibraryErrorStatus::IsbnInvalid)?;
+        let isbn = book.isbn.clone();
         let mut books = self.books.lock().await;
-        if books.contains_key(isbn) {
+        if Self::book_id_by_isbn(&isbn, database).await?.is_some() {
             warn!("refusing to add book with conflicting isbn: {isbn}");
             return Err(LibraryErrorStatus::IsbnExists);
         }
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin add book transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
 
         trace!("inserting to db");
-        let db_result = book.clone().into_active_model().insert(database).await;
-        if let Err(error) = db_result {
-            warn!("failed to add book: {}", error.to_string());
+        Self::insert_book(&mut book, &txn).await?;
+
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit book: {error}");
             return Err(LibraryErrorStatus::DatabaseError);
         }
 
+        self.index_book(&book);
         trace!("inserting to local cache");
-        books.insert(isbn.to_string(), book);
+        books.insert(isbn, book);
         Ok(())
     }
 
+    /// Inserts an already normalized `book` with its credits and subjects, filling in its id.
+    async fn insert_book<C: ConnectionTrait>(
+        book: &mut Book,
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = book.clone().into_active_model().insert(database).await;
+        if let Err(error) = db_result {
+            warn!("failed to add book: {}", error.to_string());
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        book.id = db_result.unwrap().id;
+        Self::save_contributors(book.id, &book.contributors, database).await?;
+        Self::save_subjects(book.id, &book.subjects, database).await
+    }
+
     pub async fn get_books(
-        &mut self,
+        &self,
         database: &DatabaseConnection,
         pagination: Pagination,
         search: BookSearch,
-    ) -> Result<Vec<Book>, LibraryErrorStatus> {
-        self.full_sync(database).await?;
+        sorting: BookSorting,
+    ) -> Result<BookPage, LibraryErrorStatus> {
+        if search.fuzzy == Some(true) && (search.title.is_some() || search.author.is_some()) {
+            return self.get_books_fuzzy(database, pagination, search).await;
+        }
 
-        let books = self.books.lock().await;
-        let mut books = books.values().collect::<Vec<_>>();
+        let condition = search_condition(&search)?;
+        let per_page = pagination.per_page() as u64;
+        if per_page == 0 {
+            return Err(LibraryErrorStatus::PaginationInvalid);
+        }
 
-        if let Some(query_title) = search.title {
-            books.retain(|b| b.title.contains(&query_title));
+        let db_result = book::Entity::find()
+            .filter(condition.clone())
+            .count(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to count books: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
         }
-        if let Some(query_author) = search.author {
-            books.retain(|b| b.author.contains(&query_author));
+        let total = db_result.unwrap();
+
+        let sort = sorting.sort;
+        let cursor = pagination.cursor().map(Cursor::decode).transpose()?;
+        if cursor.as_ref().is_some_and(|cursor| cursor.sort != sort) {
+            return Err(LibraryErrorStatus::CursorInvalid);
         }
-        if let Some(query_isbn) = search.isbn {
-            books.retain(|b| b.isbn.contains(&query_isbn));
+        let backwards = cursor.as_ref().is_some_and(|cursor| cursor.before);
+        // Pages before a cursor are read in reverse and flipped back afterwards.
+        let descending = sort.is_some_and(|sort| sort.descending) != backwards;
+        let order = if descending { Order::Desc } else { Order::Asc };
+
+        // One extra row is fetched to learn whether another page follows in that direction.
+        let mut query = book::Entity::find().filter(condition.clone());
+        if let Some(sort) = sort {
+            query = query.order_by(sort_column(sort.field), order.clone());
         }
+        query = query.order_by(book::Column::Id, order);
+        let (query, offset) = match &cursor {
+            Some(cursor) => (query.filter(keyset_condition(sort, cursor, descending)?), 0),
+            None => {
+                let offset = page_offset(pagination.page() as u64, per_page, total)?;
+                (query, offset)
+            }
+        };
 
-        books.sort_by_key(|b| b.id);
-
-        let page = pagination.page();
-        let per_page = pagination.per_page();
-        if page == 0 {
-            return Ok(books
-                .into_iter()
-                .rev()
-                .take(per_page)
-                .rev()
-                .cloned()
-                .collect());
+        let db_result = query.offset(offset).limit(per_page + 1).all(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
         }
+        let mut books = db_result.unwrap();
+        let more = books.len() as u64 > per_page;
+        books.truncate(per_page as usize);
 
-        let start = (page - 1) * per_page;
-        if start > books.len() {
-            return Err(LibraryErrorStatus::PaginationInvalid);
+        if backwards {
+            books.reverse();
         }
-
-        Ok(books
-            .into_iter()
-            .skip(start)
-            .take(per_page)
-            .cloned()
-            .collect())
+        let (more_before, more_after) = match &cursor {
+            Some(cursor) if cursor.before => (more, true),
+            Some(_) => (true, more),
+            None => (offset > 0, more),
+        };
+        let prev_cursor = books
+            .first()
+            .filter(|_| more_before)
+            .map(|book| Cursor::before(sort_value(sort, book), book.id, sort).encode());
+        let next_cursor = books
+            .last()
+            .filter(|_| more_after)
+            .map(|book| Cursor::after(sort_value(sort, book), book.id, sort).encode());
+
+        Self::load_book_details(&mut books, database).await?;
+
+        let facets = if search.facets == Some(true) {
+            Some(Self::facets(&condition, database).await?)
+        } else {
+            None
+        };
+        Ok(BookPage {
+            books,
+            total,
+            next_cursor,
+            prev_cursor,
+            suggestions: vec![],
+            facets,
+        })
     }
 
-    pub async fn get_book_by_isbn(
-        &self,
+    /// The id of the book stored under the canonical ISBN-13 `isbn`. The cache only holds books
+    /// touched since startup, so uniqueness is always checked against the table.
+    async fn book_id_by_isbn(
         isbn: &str,
         database: &DatabaseConnection,
-    ) -> Result<Book, LibraryErrorStatus> {
-        let books = self.books.lock().await;
-        let entry = books.get(isbn);
-        if let Some(book) = entry {
-            return Ok(book.clone());
-        }
-
-        trace!("fetching db entry");
+    ) -> Result<Option<u64>, LibraryErrorStatus> {
         let db_result = book::Entity::find()
+            .select_only()
+            .column(book::Column::Id)
             .filter(book::Column::Isbn.eq(isbn))
+            .into_tuple::<u64>()
             .one(database)
             .await;
-        if let Err(error) = db_result {
-            warn!("failed to fetch from db: {}", error.to_string());
+        if let Err(error) = &db_result {
+            warn!("failed to look up book by isbn: {error}");
             return Err(LibraryErrorStatus::DatabaseError);
         }
-
-        Ok(db_result.unwrap().unwrap())
+        Ok(db_result.unwrap())
     }
 
     pub async fn get_book_by_id(
@@ -203,7 +389,9 @@ impl Library {
         }
 
         // found it in db but not local cache. add it.
-        let book = book.unwrap();
+        let mut book = [book.unwrap()];
+        Self::load_book_details(&mut book, database).await?;
+        let [book] = book;
         self.books
             .lock()
             .await
@@ -217,19 +405,48 @@ impl Library {
         mut book: Book,
         database: &DatabaseConnection,
     ) -> Result<(), LibraryErrorStatus> {
+        book.isbn = normalize_isbn(&book.isbn).ok_or(LibraryErrorStatus::IsbnInvalid)?;
         let old_book = self.get_book_by_id(book.id, database).await?;
+        let owner = Self::book_id_by_isbn(&book.isbn, database).await?;
+        if owner.is_some_and(|owner| owner != book.id) {
+            warn!("refusing to update book to conflicting isbn: {}", book.isbn);
+            return Err(LibraryErrorStatus::IsbnExists);
+        }
 
         // They can pass whatever timestamp they want; we overwrite it with what the actual time of the transaction.
         book.created_at = old_book.created_at;
         book.updated_at = Utc::now();
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin update book transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
 
         trace!("updating db entry");
-        let db_result = book.clone().into_active_model().insert(database).await;
+        let db_result = book
+            .clone()
+            .into_active_model()
+            .reset_all()
+            .update(&txn)
+            .await;
         if let Err(error) = db_result {
             warn!("failed to update db: {}", error.to_string());
             return Err(LibraryErrorStatus::DatabaseError);
         }
+        Self::save_contributors(book.id, &book.contributors, &txn).await?;
+        Self::save_subjects(book.id, &book.subjects, &txn).await?;
 
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit book update: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        self.index_book(&book);
         trace!("updating local cache");
         let mut books = self.books.lock().await;
         if book.isbn != old_book.isbn {
@@ -244,7 +461,29 @@ impl Library {
         id: u64,
         database: &DatabaseConnection,
     ) -> Result<(), LibraryErrorStatus> {
-        let db_result = book::Entity::delete_by_id(id).exec(database).await;
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin drop transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+
+        // The cache is keyed by ISBN, which is gone from the table once the row is deleted.
+        let db_result = book::Entity::find_by_id(id)
+            .select_only()
+            .column(book::Column::Isbn)
+            .into_tuple::<String>()
+            .one(&txn)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch book to drop: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let Some(isbn) = db_result.unwrap() else {
+            return Err(LibraryErrorStatus::IdNotFound);
+        };
+
+        let db_result = book::Entity::delete_by_id(id).exec(&txn).await;
         if let Err(error) = db_result {
             warn!("failed to drop book: {}", error.to_string());
             return Err(LibraryErrorStatus::DatabaseError);
@@ -254,15 +493,115 @@ impl Library {
             return Err(LibraryErrorStatus::IdNotFound);
         }
 
-        let book = self.get_book_by_id(id, database).await;
-        if book.is_err() {
-            warn!("failed to drop book id not found in cache: {}", id);
-            return Err(LibraryErrorStatus::IdNotFound);
+        let tombstone = book_tombstone::ActiveModel {
+            id: ActiveValue::NotSet,
+            book: ActiveValue::Set(id),
+            deleted_at: ActiveValue::Set(Utc::now()),
+        };
+        if let Err(error) = tombstone.insert(&txn).await {
+            warn!("failed to record tombstone of book {id}: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit dropped book: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
         }
 
-        let isbn = book.unwrap().isbn.clone();
+        if let Some(search_index) = &self.search_index {
+            if let Err(error) = search_index.remove(id) {
+                warn!("failed to remove book {id} from search index: {error}");
+            }
+        }
+        self.remove_cover(id);
+
         trace!("dropping book from cache: {isbn}");
         self.books.lock().await.remove(&isbn);
         Ok(())
     }
+
+    // The database stays the source of truth; a stale index entry is repaired by `able reindex`.
+    fn index_book(&self, book: &Book) {
+        if let Some(search_index) = &self.search_index {
+            if let Err(error) = search_index.upsert(book) {
+                warn!("failed to index book {}: {error}", book.id);
+            }
+        }
+    }
+
+    fn index_books(&self, books: &[Book]) {
+        if let Some(search_index) = &self.search_index {
+            if let Err(error) = search_index.upsert_all(books) {
+                warn!("failed to index {} books: {error}", books.len());
+            }
+        }
+    }
+
+    pub async fn search_books(
+        &self,
+        query: &str,
+        pagination: Pagination,
+    ) -> Result<(Vec<SearchHit>, usize), LibraryErrorStatus> {
+        let page = pagination.page();
+        if page == 0 {
+            return Err(LibraryErrorStatus::PaginationInvalid);
+        }
+        let Some(search_index) = &self.search_index else {
+            warn!("search requested without a search index");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        };
+
+        let per_page = pagination.per_page();
+        let Some(offset) = (page - 1).checked_mul(per_page) else {
+            return Err(LibraryErrorStatus::PaginationInvalid);
+        };
+        let result = search_index.search(query, offset, per_page);
+        if let Err(error) = &result {
+            warn!("failed to search index: {error}");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        }
+        Ok(result.unwrap())
+    }
+
+    /// Reloads every book from the database into the search index.
+    pub async fn rebuild_search_index(
+        &self,
+        database: &DatabaseConnection,
+    ) -> Result<usize, LibraryErrorStatus> {
+        let Some(search_index) = &self.search_index else {
+            return Err(LibraryErrorStatus::SearchIndexError);
+        };
+
+        if let Err(error) = search_index.start_rebuild() {
+            warn!("failed to start search index rebuild: {error}");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        }
+
+        let mut count = 0;
+        let mut pages = book::Entity::find()
+            .order_by_asc(book::Column::Id)
+            .paginate(database, REINDEX_BATCH_SIZE);
+        loop {
+            let db_result = pages.fetch_and_next().await;
+            if let Err(error) = &db_result {
+                warn!("failed to fetch books for reindex: {error}");
+                search_index.abort_rebuild();
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            let Some(batch) = db_result.unwrap() else {
+                break;
+            };
+            if let Err(error) = search_index.add_to_rebuild(&batch) {
+                warn!("failed to rebuild search index: {error}");
+                search_index.abort_rebuild();
+                return Err(LibraryErrorStatus::SearchIndexError);
+            }
+            count += batch.len();
+        }
+
+        if let Err(error) = search_index.finish_rebuild(count) {
+            warn!("failed to rebuild search index: {error}");
+            return Err(LibraryErrorStatus::SearchIndexError);
+        }
+        Ok(count)
+    }
 }
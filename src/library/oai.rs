@@ -0,0 +1,834 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use log::warn;
+use quick_xml::escape::escape;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::orm::{
+    book::{self, Book},
+    book_tombstone::{self, BookTombstone},
+};
+
+use super::{
+    dublin_core::{dc_elements, DC_NAMESPACE},
+    Library, LibraryErrorStatus,
+};
+
+/// Records or headers per `ListRecords` or `ListIdentifiers` response; the rest follow through
+/// resumption tokens.
+pub const OAI_PAGE_SIZE: u64 = 100;
+const OAI_DC_PREFIX: &str = "oai_dc";
+const DATESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Describes this repository in `Identify` responses and the identifiers of its records.
+#[derive(Debug, Clone)]
+pub struct OaiRepository {
+    pub name: String,
+    /// The public URL of the server, without a trailing slash.
+    pub base_url: String,
+    pub admin_email: String,
+}
+
+impl OaiRepository {
+    /// The host of `base_url`, which namespaces identifiers such as `oai:library.example.org:42`.
+    fn namespace(&self) -> &str {
+        let authority = self
+            .base_url
+            .split_once("://")
+            .map_or(self.base_url.as_str(), |(_, rest)| rest);
+        let host = authority.split('/').next().unwrap_or_default();
+        host.rsplit_once(':').map_or(host, |(host, _)| host)
+    }
+
+    fn identifier(&self, id: u64) -> String {
+        format!("oai:{}:{id}", self.namespace())
+    }
+
+    fn parse_identifier(&self, identifier: &str) -> Option<u64> {
+        identifier
+            .strip_prefix("oai:")?
+            .strip_prefix(self.namespace())?
+            .strip_prefix(':')?
+            .parse()
+            .ok()
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/oai", self.base_url)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OaiVerb {
+    Identify,
+    ListMetadataFormats,
+    ListSets,
+    GetRecord,
+    ListIdentifiers,
+    ListRecords,
+}
+
+impl OaiVerb {
+    fn parse(verb: &str) -> Option<Self> {
+        match verb {
+            "Identify" => Some(Self::Identify),
+            "ListMetadataFormats" => Some(Self::ListMetadataFormats),
+            "ListSets" => Some(Self::ListSets),
+            "GetRecord" => Some(Self::GetRecord),
+            "ListIdentifiers" => Some(Self::ListIdentifiers),
+            "ListRecords" => Some(Self::ListRecords),
+            _ => None,
+        }
+    }
+
+    fn arguments(self) -> &'static [&'static str] {
+        match self {
+            Self::Identify => &[],
+            Self::ListMetadataFormats => &["identifier"],
+            Self::ListSets => &["resumptionToken"],
+            Self::GetRecord => &["identifier", "metadataPrefix"],
+            Self::ListIdentifiers | Self::ListRecords => {
+                &["metadataPrefix", "from", "until", "set", "resumptionToken"]
+            }
+        }
+    }
+}
+
+/// An OAI-PMH error condition. These are part of the protocol and reported inside a regular
+/// response, unlike `LibraryErrorStatus`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OaiError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl OaiError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Errors about the request itself are answered without echoing its arguments.
+    fn rejects_request(&self) -> bool {
+        self.code == "badVerb" || self.code == "badArgument"
+    }
+}
+
+/// A datestamp range, `from` inclusive and `before` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct OaiWindow {
+    from: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+}
+
+/// The position a `ListIdentifiers` or `ListRecords` response stopped at. Handed out as URL-safe
+/// base64 JSON, like `Cursor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ResumptionToken {
+    window: OaiWindow,
+    datestamp: DateTime<Utc>,
+    id: u64,
+    /// How many entries earlier responses returned, reported as the `cursor` attribute.
+    served: u64,
+}
+
+impl ResumptionToken {
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("token is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    fn decode(token: &str) -> Result<Self, OaiError> {
+        URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| OaiError::new("badResumptionToken", "invalid resumption token"))
+    }
+}
+
+/// Accepts both granularities of the protocol. Day granularity is reported as `true`.
+fn parse_datestamp(value: &str) -> Option<(DateTime<Utc>, bool)> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some((date.and_hms_opt(0, 0, 0)?.and_utc(), true));
+    }
+    let datetime = NaiveDateTime::parse_from_str(value, DATESTAMP_FORMAT).ok()?;
+    Some((datetime.and_utc(), false))
+}
+
+fn datestamp(time: &DateTime<Utc>) -> String {
+    time.format(DATESTAMP_FORMAT).to_string()
+}
+
+/// The validated arguments of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OaiArguments {
+    verb: OaiVerb,
+    identifier: Option<String>,
+    metadata_prefix: Option<String>,
+    window: OaiWindow,
+    set: Option<String>,
+    resumption_token: Option<String>,
+}
+
+impl OaiArguments {
+    fn parse(pairs: &[(String, String)]) -> Result<Self, OaiError> {
+        let verbs = pairs
+            .iter()
+            .filter(|(key, _)| key == "verb")
+            .collect::<Vec<_>>();
+        let verb = match verbs.as_slice() {
+            [] => return Err(OaiError::new("badVerb", "missing verb")),
+            [(_, verb)] => OaiVerb::parse(verb)
+                .ok_or_else(|| OaiError::new("badVerb", format!("illegal verb `{verb}`")))?,
+            _ => return Err(OaiError::new("badVerb", "verb given more than once")),
+        };
+
+        let mut arguments = Self {
+            verb,
+            identifier: None,
+            metadata_prefix: None,
+            window: OaiWindow::default(),
+            set: None,
+            resumption_token: None,
+        };
+        let mut from = None;
+        let mut until = None;
+        for (key, value) in pairs.iter().filter(|(key, _)| key != "verb") {
+            if !verb.arguments().contains(&key.as_str()) {
+                return Err(OaiError::new(
+                    "badArgument",
+                    format!("illegal argument `{key}`"),
+                ));
+            }
+            let slot = match key.as_str() {
+                "identifier" => &mut arguments.identifier,
+                "metadataPrefix" => &mut arguments.metadata_prefix,
+                "set" => &mut arguments.set,
+                "resumptionToken" => &mut arguments.resumption_token,
+                "from" => &mut from,
+                _ => &mut until,
+            };
+            if slot.replace(value.clone()).is_some() {
+                return Err(OaiError::new(
+                    "badArgument",
+                    format!("argument `{key}` given more than once"),
+                ));
+            }
+        }
+
+        if arguments.resumption_token.is_some() {
+            if pairs.len() > 2 {
+                return Err(OaiError::new(
+                    "badArgument",
+                    "resumptionToken is an exclusive argument",
+                ));
+            }
+            return Ok(arguments);
+        }
+        let required: &[(&str, bool)] = match verb {
+            OaiVerb::GetRecord => &[
+                ("identifier", arguments.identifier.is_some()),
+                ("metadataPrefix", arguments.metadata_prefix.is_some()),
+            ],
+            OaiVerb::ListIdentifiers | OaiVerb::ListRecords => {
+                &[("metadataPrefix", arguments.metadata_prefix.is_some())]
+            }
+            _ => &[],
+        };
+        if let Some((missing, _)) = required.iter().find(|(_, present)| !present) {
+            return Err(OaiError::new(
+                "badArgument",
+                format!("missing argument `{missing}`"),
+            ));
+        }
+
+        let parse = |value: &Option<String>| match value {
+            Some(value) => parse_datestamp(value).map(Some).ok_or_else(|| {
+                OaiError::new("badArgument", format!("invalid datestamp `{value}`"))
+            }),
+            None => Ok(None),
+        };
+        let (from, until) = (parse(&from)?, parse(&until)?);
+        if let (Some((from, from_day)), Some((until, until_day))) = (from, until) {
+            if from_day != until_day {
+                return Err(OaiError::new(
+                    "badArgument",
+                    "from and until differ in granularity",
+                ));
+            }
+            if from > until {
+                return Err(OaiError::new("badArgument", "from is later than until"));
+            }
+        }
+        // `until` is inclusive, down to the last second of a day when only a date is given.
+        arguments.window = OaiWindow {
+            from: from.map(|(from, _)| from),
+            before: until.map(|(until, day)| {
+                until
+                    + if day {
+                        TimeDelta::days(1)
+                    } else {
+                        TimeDelta::seconds(1)
+                    }
+            }),
+        };
+        Ok(arguments)
+    }
+}
+
+/// A book, or the tombstone of one, as harvesters see it.
+#[derive(Debug, Clone)]
+enum OaiEntry {
+    Book(Book),
+    Deleted(BookTombstone),
+}
+
+impl OaiEntry {
+    fn key(&self) -> (DateTime<Utc>, u64) {
+        match self {
+            Self::Book(book) => (book.updated_at, book.id),
+            Self::Deleted(tombstone) => (tombstone.deleted_at, tombstone.book),
+        }
+    }
+}
+
+fn header(repository: &OaiRepository, entry: &OaiEntry) -> String {
+    let (time, id) = entry.key();
+    let status = match entry {
+        OaiEntry::Book(_) => "",
+        OaiEntry::Deleted(_) => " status=\"deleted\"",
+    };
+    format!(
+        "<header{status}><identifier>{}</identifier><datestamp>{}</datestamp></header>",
+        escape(repository.identifier(id)),
+        datestamp(&time)
+    )
+}
+
+/// Simple Dublin Core, the one metadata format every OAI-PMH repository must offer.
+fn oai_dc(repository: &OaiRepository, book: &Book) -> String {
+    format!(
+        "<oai_dc:dc xmlns:oai_dc=\"http://www.openarchives.org/OAI/2.0/oai_dc/\" \
+         xmlns:dc=\"{DC_NAMESPACE}\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.openarchives.org/OAI/2.0/oai_dc/ \
+         http://www.openarchives.org/OAI/2.0/oai_dc.xsd\">{}</oai_dc:dc>",
+        dc_elements(book, &repository.base_url)
+    )
+}
+
+fn record(repository: &OaiRepository, entry: &OaiEntry) -> String {
+    match entry {
+        OaiEntry::Book(book) => format!(
+            "<record>{}<metadata>{}</metadata></record>",
+            header(repository, entry),
+            oai_dc(repository, book)
+        ),
+        OaiEntry::Deleted(_) => format!("<record>{}</record>", header(repository, entry)),
+    }
+}
+
+fn envelope(
+    repository: &OaiRepository,
+    pairs: &[(String, String)],
+    now: DateTime<Utc>,
+    body: Result<String, OaiError>,
+) -> String {
+    let echo = match &body {
+        Err(error) if error.rejects_request() => String::new(),
+        _ => pairs
+            .iter()
+            .map(|(key, value)| format!(" {}=\"{}\"", escape(key), escape(value)))
+            .collect(),
+    };
+    let body = body.unwrap_or_else(|error| {
+        format!(
+            "<error code=\"{}\">{}</error>",
+            error.code,
+            escape(&error.message)
+        )
+    });
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <OAI-PMH xmlns=\"http://www.openarchives.org/OAI/2.0/\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.openarchives.org/OAI/2.0/ \
+         http://www.openarchives.org/OAI/2.0/OAI-PMH.xsd\">\
+         <responseDate>{}</responseDate><request{echo}>{}</request>{body}</OAI-PMH>\n",
+        datestamp(&now),
+        escape(repository.endpoint())
+    )
+}
+
+fn book_condition(window: OaiWindow, after: Option<(DateTime<Utc>, u64)>) -> Condition {
+    let mut condition = Condition::all();
+    if let Some(from) = window.from {
+        condition = condition.add(book::Column::UpdatedAt.gte(from));
+    }
+    if let Some(before) = window.before {
+        condition = condition.add(book::Column::UpdatedAt.lt(before));
+    }
+    if let Some((time, id)) = after {
+        condition = condition.add(
+            Condition::any().add(book::Column::UpdatedAt.gt(time)).add(
+                Condition::all()
+                    .add(book::Column::UpdatedAt.eq(time))
+                    .add(book::Column::Id.gt(id)),
+            ),
+        );
+    }
+    condition
+}
+
+fn tombstone_condition(window: OaiWindow, after: Option<(DateTime<Utc>, u64)>) -> Condition {
+    let mut condition = Condition::all();
+    if let Some(from) = window.from {
+        condition = condition.add(book_tombstone::Column::DeletedAt.gte(from));
+    }
+    if let Some(before) = window.before {
+        condition = condition.add(book_tombstone::Column::DeletedAt.lt(before));
+    }
+    if let Some((time, id)) = after {
+        condition = condition.add(
+            Condition::any()
+                .add(book_tombstone::Column::DeletedAt.gt(time))
+                .add(
+                    Condition::all()
+                        .add(book_tombstone::Column::DeletedAt.eq(time))
+                        .add(book_tombstone::Column::Book.gt(id)),
+                ),
+        );
+    }
+    condition
+}
+
+impl Library {
+    /// Up to `limit` books and tombstones in `window` past `after`, ordered by datestamp, then id.
+    /// Book ids are never reused, so a book and a tombstone never share an id.
+    async fn oai_entries(
+        window: OaiWindow,
+        after: Option<(DateTime<Utc>, u64)>,
+        limit: u64,
+        details: bool,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<OaiEntry>, LibraryErrorStatus> {
+        let db_result = book::Entity::find()
+            .filter(book_condition(window, after))
+            .order_by_asc(book::Column::UpdatedAt)
+            .order_by_asc(book::Column::Id)
+            .limit(limit)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books for harvesting: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        if details {
+            Self::load_book_details(&mut books, database).await?;
+        }
+
+        let db_result = book_tombstone::Entity::find()
+            .filter(tombstone_condition(window, after))
+            .order_by_asc(book_tombstone::Column::DeletedAt)
+            .order_by_asc(book_tombstone::Column::Book)
+            .limit(limit)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch tombstones for harvesting: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let mut entries = books
+            .into_iter()
+            .map(OaiEntry::Book)
+            .chain(db_result.unwrap().into_iter().map(OaiEntry::Deleted))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(OaiEntry::key);
+        entries.truncate(limit as usize);
+        Ok(entries)
+    }
+
+    async fn oai_count(
+        window: OaiWindow,
+        database: &DatabaseConnection,
+    ) -> Result<u64, LibraryErrorStatus> {
+        let books = book::Entity::find()
+            .filter(book_condition(window, None))
+            .count(database)
+            .await;
+        let tombstones = book_tombstone::Entity::find()
+            .filter(tombstone_condition(window, None))
+            .count(database)
+            .await;
+        match (books, tombstones) {
+            (Ok(books), Ok(tombstones)) => Ok(books + tombstones),
+            (Err(error), _) | (_, Err(error)) => {
+                warn!("failed to count entries for harvesting: {error}");
+                Err(LibraryErrorStatus::DatabaseError)
+            }
+        }
+    }
+
+    async fn oai_entry(
+        &mut self,
+        repository: &OaiRepository,
+        identifier: &str,
+        database: &DatabaseConnection,
+    ) -> Result<Result<OaiEntry, OaiError>, LibraryErrorStatus> {
+        let unknown = || {
+            OaiError::new(
+                "idDoesNotExist",
+                format!("unknown identifier `{identifier}`"),
+            )
+        };
+        let Some(id) = repository.parse_identifier(identifier) else {
+            return Ok(Err(unknown()));
+        };
+        match self.get_book_by_id(id, database).await {
+            Ok(book) => return Ok(Ok(OaiEntry::Book(book))),
+            Err(LibraryErrorStatus::IdNotFound) => {}
+            Err(error) => return Err(error),
+        }
+
+        let db_result = book_tombstone::Entity::find()
+            .filter(book_tombstone::Column::Book.eq(id))
+            .one(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch tombstone of book {id}: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result
+            .unwrap()
+            .map(OaiEntry::Deleted)
+            .ok_or_else(unknown))
+    }
+
+    async fn oai_earliest_datestamp(
+        database: &DatabaseConnection,
+    ) -> Result<Option<DateTime<Utc>>, LibraryErrorStatus> {
+        let window = OaiWindow::default();
+        let first = Self::oai_entries(window, None, 1, false, database).await?;
+        Ok(first.first().map(|entry| entry.key().0))
+    }
+
+    async fn oai_list(
+        arguments: &OaiArguments,
+        repository: &OaiRepository,
+        database: &DatabaseConnection,
+    ) -> Result<Result<String, OaiError>, LibraryErrorStatus> {
+        let resumed = match &arguments.resumption_token {
+            Some(token) => match ResumptionToken::decode(token) {
+                Ok(token) => Some(token),
+                Err(error) => return Ok(Err(error)),
+            },
+            None => None,
+        };
+        let (window, after, served) = match &resumed {
+            Some(token) => (
+                token.window,
+                Some((token.datestamp, token.id)),
+                token.served,
+            ),
+            None => (arguments.window, None, 0),
+        };
+
+        let records = arguments.verb == OaiVerb::ListRecords;
+        let mut entries =
+            Self::oai_entries(window, after, OAI_PAGE_SIZE + 1, records, database).await?;
+        if entries.is_empty() && resumed.is_none() {
+            return Ok(Err(OaiError::new(
+                "noRecordsMatch",
+                "no records match the request",
+            )));
+        }
+        let more = entries.len() as u64 > OAI_PAGE_SIZE;
+        entries.truncate(OAI_PAGE_SIZE as usize);
+
+        let element = if records {
+            "ListRecords"
+        } else {
+            "ListIdentifiers"
+        };
+        let mut xml = format!("<{element}>");
+        for entry in &entries {
+            xml.push_str(&if records {
+                record(repository, entry)
+            } else {
+                header(repository, entry)
+            });
+        }
+        if more || resumed.is_some() {
+            let total = Self::oai_count(window, database).await?;
+            let next = entries.last().filter(|_| more).map(|last| {
+                let (datestamp, id) = last.key();
+                ResumptionToken {
+                    window,
+                    datestamp,
+                    id,
+                    served: served + entries.len() as u64,
+                }
+                .encode()
+            });
+            xml.push_str(&format!(
+                "<resumptionToken completeListSize=\"{total}\" cursor=\"{served}\">{}</resumptionToken>",
+                next.unwrap_or_default()
+            ));
+        }
+        xml.push_str(&format!("</{element}>"));
+        Ok(Ok(xml))
+    }
+
+    /// Answers one OAI-PMH request given its raw arguments with a complete response document.
+    /// Protocol errors are part of the document; only failures of the library itself are `Err`.
+    pub async fn oai_response(
+        &mut self,
+        pairs: &[(String, String)],
+        repository: &OaiRepository,
+        database: &DatabaseConnection,
+    ) -> Result<String, LibraryErrorStatus> {
+        let body = match OaiArguments::parse(pairs) {
+            Ok(arguments) => self.oai_body(&arguments, repository, database).await?,
+            Err(error) => Err(error),
+        };
+        Ok(envelope(repository, pairs, Utc::now(), body))
+    }
+
+    async fn oai_body(
+        &mut self,
+        arguments: &OaiArguments,
+        repository: &OaiRepository,
+        database: &DatabaseConnection,
+    ) -> Result<Result<String, OaiError>, LibraryErrorStatus> {
+        let unsupported_prefix = arguments
+            .metadata_prefix
+            .as_ref()
+            .filter(|prefix| *prefix != OAI_DC_PREFIX);
+        if let Some(prefix) = unsupported_prefix {
+            return Ok(Err(OaiError::new(
+                "cannotDisseminateFormat",
+                format!("metadata format `{prefix}` is not supported"),
+            )));
+        }
+        if arguments.set.is_some() {
+            return Ok(Err(OaiError::new(
+                "noSetHierarchy",
+                "this repository does not support sets",
+            )));
+        }
+
+        match arguments.verb {
+            OaiVerb::Identify => {
+                let earliest = Self::oai_earliest_datestamp(database)
+                    .await?
+                    .unwrap_or(DateTime::UNIX_EPOCH);
+                Ok(Ok(format!(
+                    "<Identify><repositoryName>{}</repositoryName><baseURL>{}</baseURL>\
+                     <protocolVersion>2.0</protocolVersion><adminEmail>{}</adminEmail>\
+                     <earliestDatestamp>{}</earliestDatestamp>\
+                     <deletedRecord>persistent</deletedRecord>\
+                     <granularity>YYYY-MM-DDThh:mm:ssZ</granularity>\
+                     <description><oai-identifier \
+                     xmlns=\"http://www.openarchives.org/OAI/2.0/oai-identifier\" \
+                     xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+                     xsi:schemaLocation=\"http://www.openarchives.org/OAI/2.0/oai-identifier \
+                     http://www.openarchives.org/OAI/2.0/oai-identifier.xsd\">\
+                     <scheme>oai</scheme><repositoryIdentifier>{}</repositoryIdentifier>\
+                     <delimiter>:</delimiter><sampleIdentifier>{}</sampleIdentifier>\
+                     </oai-identifier></description></Identify>",
+                    escape(&repository.name),
+                    escape(repository.endpoint()),
+                    escape(&repository.admin_email),
+                    datestamp(&earliest),
+                    escape(repository.namespace()),
+                    escape(repository.identifier(1)),
+                )))
+            }
+            OaiVerb::ListMetadataFormats => {
+                if let Some(identifier) = &arguments.identifier {
+                    if let Err(error) = self.oai_entry(repository, identifier, database).await? {
+                        return Ok(Err(error));
+                    }
+                }
+                Ok(Ok(format!(
+                    "<ListMetadataFormats><metadataFormat>\
+                     <metadataPrefix>{OAI_DC_PREFIX}</metadataPrefix>\
+                     <schema>http://www.openarchives.org/OAI/2.0/oai_dc.xsd</schema>\
+                     <metadataNamespace>http://www.openarchives.org/OAI/2.0/oai_dc/</metadataNamespace>\
+                     </metadataFormat></ListMetadataFormats>"
+                )))
+            }
+            OaiVerb::ListSets => Ok(Err(OaiError::new(
+                "noSetHierarchy",
+                "this repository does not support sets",
+            ))),
+            OaiVerb::GetRecord => {
+                let identifier = arguments.identifier.as_deref().unwrap_or_default();
+                let entry = self.oai_entry(repository, identifier, database).await?;
+                Ok(entry
+                    .map(|entry| format!("<GetRecord>{}</GetRecord>", record(repository, &entry))))
+            }
+            OaiVerb::ListIdentifiers | OaiVerb::ListRecords => {
+                Self::oai_list(arguments, repository, database).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn pairs(query: &str) -> Vec<(String, String)> {
+    serde_urlencoded::from_str(query).unwrap()
+}
+
+#[test]
+fn test_oai_arguments() {
+    let parse = |query| OaiArguments::parse(&pairs(query)).map_err(|error| error.code);
+
+    assert_eq!(parse("").unwrap_err(), "badVerb");
+    assert_eq!(parse("verb=Dance").unwrap_err(), "badVerb");
+    assert_eq!(parse("verb=Identify&verb=Identify").unwrap_err(), "badVerb");
+    assert_eq!(
+        parse("verb=Identify&from=2001-01-01").unwrap_err(),
+        "badArgument"
+    );
+    assert_eq!(parse("verb=ListRecords").unwrap_err(), "badArgument");
+    assert_eq!(
+        parse("verb=GetRecord&metadataPrefix=oai_dc&metadataPrefix=oai_dc").unwrap_err(),
+        "badArgument"
+    );
+    assert_eq!(
+        parse("verb=ListRecords&resumptionToken=x&metadataPrefix=oai_dc").unwrap_err(),
+        "badArgument"
+    );
+    assert_eq!(
+        parse("verb=ListRecords&metadataPrefix=oai_dc&from=2001-01-01&until=2002-01-01T00:00:00Z")
+            .unwrap_err(),
+        "badArgument"
+    );
+    assert_eq!(
+        parse("verb=ListRecords&metadataPrefix=oai_dc&from=2002-01-02&until=2002-01-01")
+            .unwrap_err(),
+        "badArgument"
+    );
+    assert_eq!(
+        parse("verb=ListRecords&metadataPrefix=oai_dc&from=yesterday").unwrap_err(),
+        "badArgument"
+    );
+
+    let arguments =
+        parse("verb=ListIdentifiers&metadataPrefix=oai_dc&from=2002-01-01&until=2002-01-31")
+            .unwrap();
+    assert_eq!(arguments.verb, OaiVerb::ListIdentifiers);
+    let day = |day| parse_datestamp(day).unwrap().0;
+    assert_eq!(
+        arguments.window,
+        OaiWindow {
+            from: Some(day("2002-01-01")),
+            before: Some(day("2002-02-01")),
+        }
+    );
+    let arguments =
+        parse("verb=ListRecords&metadataPrefix=oai_dc&until=2002-01-31T12:00:00Z").unwrap();
+    assert_eq!(arguments.window.before, Some(day("2002-01-31T12:00:01Z")));
+}
+
+#[test]
+fn test_oai_records() {
+    use crate::orm::book_contributor::{ContributorRole, Credit};
+
+    let repository = OaiRepository {
+        name: "ABLE".to_string(),
+        base_url: "https://library.example.org:8443".to_string(),
+        admin_email: "admin@example.org".to_string(),
+    };
+    assert_eq!(repository.identifier(42), "oai:library.example.org:42");
+    assert_eq!(
+        repository.parse_identifier("oai:library.example.org:42"),
+        Some(42)
+    );
+    assert_eq!(repository.parse_identifier("oai:elsewhere.org:42"), None);
+
+    let updated_at = parse_datestamp("2024-03-01T10:20:30Z").unwrap().0;
+    let book = Book {
+        id: 42,
+        title: "Gödel, Escher, Bach & more".to_string(),
+        author: "Douglas Hofstadter".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![
+            Credit {
+                name: "Douglas Hofstadter".to_string(),
+                role: ContributorRole::Author,
+            },
+            Credit {
+                name: "Homer".to_string(),
+                role: ContributorRole::Editor,
+            },
+        ],
+        publication_year: 1979,
+        subjects: vec!["Logic".to_string()],
+        isbn: "9780465026562".to_string(),
+        description: None,
+        created_at: updated_at,
+        updated_at,
+    };
+    let xml = record(&repository, &OaiEntry::Book(book));
+    assert!(xml.starts_with(
+        "<record><header><identifier>oai:library.example.org:42</identifier>\
+         <datestamp>2024-03-01T10:20:30Z</datestamp></header><metadata><oai_dc:dc "
+    ));
+    for element in [
+        "<dc:title>Gödel, Escher, Bach &amp; more</dc:title>",
+        "<dc:creator>Hofstadter, Douglas</dc:creator>",
+        "<dc:contributor>Homer</dc:contributor>",
+        "<dc:subject>Logic</dc:subject>",
+        "<dc:date>1979</dc:date>",
+        "<dc:identifier>urn:isbn:9780465026562</dc:identifier>",
+        "<dc:identifier>https://library.example.org:8443/books/42</dc:identifier>",
+    ] {
+        assert!(xml.contains(element), "{element} missing from {xml}");
+    }
+
+    let tombstone = OaiEntry::Deleted(BookTombstone {
+        id: 1,
+        book: 7,
+        deleted_at: updated_at,
+    });
+    assert_eq!(
+        record(&repository, &tombstone),
+        "<record><header status=\"deleted\"><identifier>oai:library.example.org:7</identifier>\
+         <datestamp>2024-03-01T10:20:30Z</datestamp></header></record>"
+    );
+
+    let token = ResumptionToken {
+        window: OaiWindow::default(),
+        datestamp: updated_at,
+        id: 7,
+        served: 100,
+    };
+    assert_eq!(ResumptionToken::decode(&token.encode()), Ok(token));
+    assert_eq!(
+        ResumptionToken::decode("garbage").unwrap_err().code,
+        "badResumptionToken"
+    );
+
+    let error = envelope(
+        &repository,
+        &pairs("verb=Dance"),
+        updated_at,
+        Err(OaiError::new("badVerb", "illegal verb `Dance`")),
+    );
+    assert!(error.contains(
+        "<request>https://library.example.org:8443/oai</request>\
+         <error code=\"badVerb\">illegal verb `Dance`</error>"
+    ));
+}
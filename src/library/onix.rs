@@ -0,0 +1,807 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use log::{trace, warn};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::orm::{
+    book::{self, Book},
+    book_contributor::{ContributorRole, Credit},
+};
+
+use super::{
+    catalog_csv::{publication_year_error, CONFLICT_BATCH_SIZE},
+    normalize_isbn, Library, LibraryErrorStatus,
+};
+
+/// ONIX 3.0 short tags of the elements read here, mapped to their reference names.
+const SHORT_TAGS: [(&str, &str); 35] = [
+    ("ONIXmessage", "ONIXMessage"),
+    ("product", "Product"),
+    ("a001", "RecordReference"),
+    ("a002", "NotificationType"),
+    ("productidentifier", "ProductIdentifier"),
+    ("b221", "ProductIDType"),
+    ("b244", "IDValue"),
+    ("descriptivedetail", "DescriptiveDetail"),
+    ("titledetail", "TitleDetail"),
+    ("b202", "TitleType"),
+    ("titleelement", "TitleElement"),
+    ("x409", "TitleElementLevel"),
+    ("b203", "TitleText"),
+    ("b030", "TitlePrefix"),
+    ("b031", "TitleWithoutPrefix"),
+    ("b029", "Subtitle"),
+    ("contributor", "Contributor"),
+    ("b034", "SequenceNumber"),
+    ("b035", "ContributorRole"),
+    ("b036", "PersonName"),
+    ("b037", "PersonNameInverted"),
+    ("b039", "NamesBeforeKey"),
+    ("b040", "KeyNames"),
+    ("b047", "CorporateName"),
+    ("subject", "Subject"),
+    ("b067", "SubjectSchemeIdentifier"),
+    ("b070", "SubjectHeadingText"),
+    ("collateraldetail", "CollateralDetail"),
+    ("textcontent", "TextContent"),
+    ("x426", "TextType"),
+    ("d104", "Text"),
+    ("publishingdetail", "PublishingDetail"),
+    ("publishingdate", "PublishingDate"),
+    ("x448", "PublishingDateRole"),
+    ("b306", "Date"),
+];
+
+// Code list values used below: ProductIDType (list 5), TitleType (15), TitleElementLevel (149),
+// ContributorRole (17), SubjectSchemeIdentifier (27), TextType (153), TextFormat (34),
+// PublishingDateRole (163) and NotificationType (1).
+const ISBN_13: &str = "15";
+const GTIN_13: &str = "03";
+const DISTINCTIVE_TITLE: &str = "01";
+const PRODUCT_LEVEL: &str = "01";
+const KEYWORDS: &str = "20";
+const DESCRIPTION: &str = "03";
+const SHORT_DESCRIPTION: &str = "02";
+const PUBLICATION_DATE: &str = "01";
+const FIRST_PUBLICATION_DATE: &str = "11";
+const DELETE: &str = "05";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnixOutcome {
+    New,
+    Updated,
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct OnixProductReport {
+    /// The `RecordReference` the sender identifies the product by.
+    pub record_reference: String,
+    pub isbn: String,
+    pub title: String,
+    pub outcome: OnixOutcome,
+    /// Why the product was skipped; empty otherwise.
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct OnixReport {
+    pub dry_run: bool,
+    pub new: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub products: Vec<OnixProductReport>,
+}
+
+fn invalid(message: impl Into<String>) -> LibraryErrorStatus {
+    LibraryErrorStatus::OnixInvalid(message.into())
+}
+
+/// An ONIX element and everything below it, named by its reference name even when the message
+/// uses short tags.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    /// All text below the element in document order, including that of embedded XHTML.
+    content: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn open(start: &BytesStart) -> Result<Self, LibraryErrorStatus> {
+        let local_name = start.local_name();
+        let name = String::from_utf8_lossy(local_name.as_ref());
+        let name = SHORT_TAGS
+            .iter()
+            .find(|(short, _)| *short == name)
+            .map_or(name.to_string(), |(_, reference)| reference.to_string());
+        let mut attributes = vec![];
+        for attribute in start.attributes() {
+            let attribute =
+                attribute.map_err(|error| invalid(format!("malformed attribute: {error}")))?;
+            let value = attribute
+                .unescape_value()
+                .map_err(|error| invalid(format!("malformed attribute: {error}")))?;
+            let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string();
+            attributes.push((key, value.into_owned()));
+        }
+        Ok(Self {
+            name,
+            attributes,
+            ..Self::default()
+        })
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The trimmed text of the first `name` child, unless it is blank.
+    fn text(&self, name: &str) -> Option<String> {
+        let text = collapse_whitespace(&self.child(name)?.content);
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first `name` child whose `code` child holds one of `codes`, trying them in order.
+    fn child_with(&self, name: &str, code: &str, codes: &[&str]) -> Option<&Element> {
+        codes.iter().find_map(|wanted| {
+            self.children
+                .iter()
+                .find(|child| child.name == name && child.text(code).as_deref() == Some(wanted))
+        })
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drops the tags from HTML that arrived escaped inside a `Text` element.
+fn strip_markup(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                stripped.push(' ');
+            }
+            c if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// What one `Product` says about a book. Fields it leaves out are `None` or empty, and keep
+/// their current value when the book already exists.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct OnixProduct {
+    record_reference: String,
+    isbn: String,
+    title: String,
+    contributors: Vec<Credit>,
+    subjects: Vec<String>,
+    publication_year: Option<u64>,
+    description: Option<String>,
+    /// Reasons the product cannot be applied at all.
+    errors: Vec<String>,
+}
+
+fn contributor_role(code: &str) -> Option<ContributorRole> {
+    match code {
+        "A01" => Some(ContributorRole::Author),
+        "A12" => Some(ContributorRole::Illustrator),
+        "B01" => Some(ContributorRole::Editor),
+        "B06" => Some(ContributorRole::Translator),
+        _ => None,
+    }
+}
+
+fn contributor_name(contributor: &Element) -> Option<String> {
+    contributor.text("PersonName").or_else(|| {
+        match (
+            contributor.text("NamesBeforeKey"),
+            contributor.text("KeyNames"),
+        ) {
+            (Some(given), Some(family)) => Some(format!("{given} {family}")),
+            (None, Some(family)) => Some(family),
+            _ => contributor
+                .text("PersonNameInverted")
+                .or_else(|| contributor.text("CorporateName")),
+        }
+    })
+}
+
+fn title(descriptive: &Element) -> Option<String> {
+    let detail = descriptive
+        .child_with("TitleDetail", "TitleType", &[DISTINCTIVE_TITLE])
+        .or_else(|| descriptive.child("TitleDetail"))?;
+    let element = detail
+        .child_with("TitleElement", "TitleElementLevel", &[PRODUCT_LEVEL])
+        .or_else(|| detail.child("TitleElement"))?;
+    let title = element.text("TitleText").or_else(|| {
+        let rest = element.text("TitleWithoutPrefix")?;
+        Some(match element.text("TitlePrefix") {
+            Some(prefix) => format!("{prefix} {rest}"),
+            None => rest,
+        })
+    })?;
+    Some(match element.text("Subtitle") {
+        Some(subtitle) => format!("{title}: {subtitle}"),
+        None => title,
+    })
+}
+
+impl OnixProduct {
+    fn from_element(product: &Element) -> Self {
+        let mut parsed = Self {
+            record_reference: product.text("RecordReference").unwrap_or_default(),
+            ..Self::default()
+        };
+        if product.text("NotificationType").as_deref() == Some(DELETE) {
+            parsed
+                .errors
+                .push("deletion notices are not applied".to_string());
+        }
+
+        let identifier = product
+            .child_with("ProductIdentifier", "ProductIDType", &[ISBN_13, GTIN_13])
+            .and_then(|identifier| identifier.text("IDValue"));
+        match identifier {
+            Some(value) => match normalize_isbn(&value).filter(|isbn| isbn.len() == 13) {
+                Some(isbn) => parsed.isbn = isbn,
+                None => {
+                    parsed
+                        .errors
+                        .push(format!("`{value}` is not a valid ISBN-13"));
+                    parsed.isbn = value;
+                }
+            },
+            None => parsed.errors.push("no ISBN-13".to_string()),
+        }
+
+        let descriptive = product.child("DescriptiveDetail");
+        match descriptive.and_then(title) {
+            Some(title) => parsed.title = title,
+            None => parsed.errors.push("no title".to_string()),
+        }
+        if let Some(descriptive) = descriptive {
+            let mut contributors = descriptive
+                .children("Contributor")
+                .filter_map(|contributor| {
+                    let role = contributor
+                        .children("ContributorRole")
+                        .find_map(|role| contributor_role(role.content.trim()))?;
+                    let sequence = contributor
+                        .text("SequenceNumber")
+                        .and_then(|sequence| sequence.parse::<u32>().ok())
+                        .unwrap_or(u32::MAX);
+                    let name = contributor_name(contributor)?;
+                    Some((sequence, Credit { name, role }))
+                })
+                .collect::<Vec<_>>();
+            // The sort is stable, so unnumbered contributors stay in document order at the end.
+            contributors.sort_by_key(|(sequence, _)| *sequence);
+            parsed.contributors = contributors.into_iter().map(|(_, credit)| credit).collect();
+
+            for subject in descriptive.children("Subject") {
+                let Some(heading) = subject.text("SubjectHeadingText") else {
+                    continue;
+                };
+                if subject.text("SubjectSchemeIdentifier").as_deref() == Some(KEYWORDS) {
+                    parsed
+                        .subjects
+                        .extend(heading.split(';').map(|keyword| keyword.trim().to_string()));
+                } else {
+                    parsed.subjects.push(heading);
+                }
+            }
+        }
+
+        let date = product.child("PublishingDetail").and_then(|publishing| {
+            publishing
+                .child_with(
+                    "PublishingDate",
+                    "PublishingDateRole",
+                    &[PUBLICATION_DATE, FIRST_PUBLICATION_DATE],
+                )
+                .or_else(|| publishing.child("PublishingDate"))?
+                .text("Date")
+        });
+        if let Some(date) = date {
+            match date.get(..4).and_then(|year| year.parse::<u64>().ok()) {
+                Some(year) => {
+                    parsed.errors.extend(publication_year_error(year));
+                    parsed.publication_year = Some(year);
+                }
+                None => parsed
+                    .errors
+                    .push(format!("publication date `{date}` has no year")),
+            }
+        }
+
+        parsed.description = product.child("CollateralDetail").and_then(|collateral| {
+            let text = collateral
+                .child_with("TextContent", "TextType", &[DESCRIPTION, SHORT_DESCRIPTION])?
+                .child("Text")?;
+            let content = match text.attribute("textformat") {
+                // HTML and XHTML may arrive escaped rather than as embedded elements.
+                Some("02" | "05") => strip_markup(&text.content),
+                _ => text.content.clone(),
+            };
+            let content = collapse_whitespace(&content);
+            (!content.is_empty()).then_some(content)
+        });
+        parsed
+    }
+
+    /// A new book, or `None` when the product lacks something only an existing book can supply.
+    fn to_book(&self) -> Option<Book> {
+        let now = Utc::now();
+        let mut book = Book {
+            id: 0,
+            title: self.title.clone(),
+            author: String::new(),
+            title_sort: String::new(),
+            author_sort: String::new(),
+            contributors: self.contributors.clone(),
+            publication_year: self.publication_year?,
+            subjects: self.subjects.clone(),
+            isbn: self.isbn.clone(),
+            description: self.description.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+        Some(book)
+    }
+
+    /// Overlays the product on `book`, keeping whatever the product does not mention.
+    fn apply(&self, book: &mut Book) {
+        book.title = self.title.clone();
+        if !self.contributors.is_empty() {
+            book.contributors = self.contributors.clone();
+            book.author = String::new();
+        }
+        if !self.subjects.is_empty() {
+            book.subjects = self.subjects.clone();
+        }
+        if let Some(year) = self.publication_year {
+            book.publication_year = year;
+        }
+        if self.description.is_some() {
+            book.description = self.description.clone();
+        }
+        book.normalize_contributors();
+        book.normalize_subjects();
+        book.refresh_sort_keys();
+    }
+}
+
+/// Reads every `Product` of an ONIX 3.0 message, in reference names or short tags.
+fn parse_onix(data: &[u8]) -> Result<Vec<OnixProduct>, LibraryErrorStatus> {
+    let data = data.strip_prefix("\u{feff}".as_bytes()).unwrap_or(data);
+    let xml = std::str::from_utf8(data).map_err(|_| invalid("ONIX message is not UTF-8"))?;
+    let mut reader = Reader::from_str(xml);
+    let mut stack = Vec::<Element>::new();
+    let mut products = vec![];
+    let mut root_seen = false;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|error| invalid(format!("malformed XML: {error}")))?;
+        let (element, closed) = match event {
+            Event::Start(start) => (Element::open(&start)?, false),
+            Event::Empty(start) => (Element::open(&start)?, true),
+            Event::End(_) => match stack.pop() {
+                Some(element) => (element, true),
+                None => return Err(invalid("unbalanced closing tag")),
+            },
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|error| invalid(format!("malformed XML: {error}")))?;
+                for open in &mut stack {
+                    open.content.push_str(&text);
+                }
+                continue;
+            }
+            Event::CData(text) => {
+                let text = String::from_utf8_lossy(&text);
+                for open in &mut stack {
+                    open.content.push_str(&text);
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        if !root_seen {
+            if element.name != "ONIXMessage" {
+                return Err(invalid(format!(
+                    "expected an ONIXMessage, found `{}`",
+                    element.name
+                )));
+            }
+            if element
+                .attribute("release")
+                .is_some_and(|release| !release.starts_with('3'))
+            {
+                return Err(invalid("only ONIX 3.0 messages are supported"));
+            }
+            root_seen = true;
+        }
+        if !closed {
+            stack.push(element);
+            continue;
+        }
+        // Block-level XHTML must not run words together once the tags are gone.
+        for open in &mut stack {
+            open.content.push(' ');
+        }
+        match (element.name.as_str(), stack.last_mut()) {
+            // Products are converted as soon as they close, so only one is held at a time.
+            ("Product", _) => products.push(OnixProduct::from_element(&element)),
+            (_, Some(parent)) => parent.children.push(element),
+            (_, None) => {}
+        }
+    }
+    if !root_seen {
+        return Err(invalid("the message is empty"));
+    }
+    if !stack.is_empty() {
+        return Err(invalid("the message ends before its elements are closed"));
+    }
+    Ok(products)
+}
+
+enum Change {
+    Insert(Book),
+    Update(Book),
+}
+
+impl Library {
+    /// Upserts the products of an ONIX 3.0 message by ISBN. Unlike CSV and MARC imports, a bad
+    /// product only skips itself; the rest are applied in a single transaction.
+    pub async fn import_onix(
+        &mut self,
+        data: &[u8],
+        dry_run: bool,
+        database: &DatabaseConnection,
+    ) -> Result<OnixReport, LibraryErrorStatus> {
+        let mut products = parse_onix(data)?;
+
+        let mut first_seen = HashMap::<String, usize>::new();
+        for (index, product) in products.iter_mut().enumerate() {
+            if !product.errors.is_empty() {
+                continue;
+            }
+            match first_seen.get(&product.isbn) {
+                Some(earlier) => product.errors.push(format!(
+                    "isbn {} already appears in product {earlier}",
+                    product.isbn
+                )),
+                None => {
+                    first_seen.insert(product.isbn.clone(), index + 1);
+                }
+            }
+        }
+
+        let isbns = first_seen.into_keys().collect::<Vec<_>>();
+        let mut existing = HashMap::new();
+        for chunk in isbns.chunks(CONFLICT_BATCH_SIZE) {
+            let db_result = book::Entity::find()
+                .filter(book::Column::Isbn.is_in(chunk.to_vec()))
+                .all(database)
+                .await;
+            if let Err(error) = &db_result {
+                warn!("failed to look up books for ONIX import: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            let mut books = db_result.unwrap();
+            Self::load_book_details(&mut books, database).await?;
+            existing.extend(books.into_iter().map(|book| (book.isbn.clone(), book)));
+        }
+
+        let mut report = OnixReport {
+            dry_run,
+            ..OnixReport::default()
+        };
+        let mut changes = vec![];
+        for mut product in products {
+            let change = match existing.remove(&product.isbn) {
+                _ if !product.errors.is_empty() => None,
+                Some(book) => {
+                    let mut updated = book.clone();
+                    product.apply(&mut updated);
+                    if updated == book {
+                        product.errors.push("already up to date".to_string());
+                        None
+                    } else {
+                        updated.updated_at = Utc::now();
+                        Some(Change::Update(updated))
+                    }
+                }
+                None => match product.to_book() {
+                    Some(book) => Some(Change::Insert(book)),
+                    None => {
+                        product.errors.push("no publication date".to_string());
+                        None
+                    }
+                },
+            };
+            let outcome = match &change {
+                Some(Change::Insert(_)) => OnixOutcome::New,
+                Some(Change::Update(_)) => OnixOutcome::Updated,
+                None => OnixOutcome::Skipped,
+            };
+            match outcome {
+                OnixOutcome::New => report.new += 1,
+                OnixOutcome::Updated => report.updated += 1,
+                OnixOutcome::Skipped => report.skipped += 1,
+            }
+            report.products.push(OnixProductReport {
+                record_reference: product.record_reference,
+                isbn: product.isbn,
+                title: product.title,
+                outcome,
+                reasons: product.errors,
+            });
+            changes.extend(change);
+        }
+        if dry_run || changes.is_empty() {
+            return Ok(report);
+        }
+
+        let txn = database.begin().await;
+        if let Err(error) = &txn {
+            warn!("failed to begin ONIX import transaction: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let txn = txn.unwrap();
+        trace!("applying {} ONIX products", changes.len());
+        for change in &mut changes {
+            match change {
+                Change::Insert(book) => Self::insert_book(book, &txn).await?,
+                Change::Update(book) => {
+                    let db_result = book
+                        .clone()
+                        .into_active_model()
+                        .reset_all()
+                        .update(&txn)
+                        .await;
+                    if let Err(error) = db_result {
+                        warn!("failed to update book {}: {error}", book.id);
+                        return Err(LibraryErrorStatus::DatabaseError);
+                    }
+                    Self::save_contributors(book.id, &book.contributors, &txn).await?;
+                    Self::save_subjects(book.id, &book.subjects, &txn).await?;
+                }
+            }
+        }
+        if let Err(error) = txn.commit().await {
+            warn!("failed to commit ONIX import: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let books = changes
+            .into_iter()
+            .map(|(Change::Insert(book) | Change::Update(book))| book)
+            .collect::<Vec<_>>();
+        self.index_books(&books);
+        let mut cache = self.books.lock().await;
+        for book in books {
+            cache.insert(book.isbn.clone(), book);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+const REFERENCE_MESSAGE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ONIXMessage release="3.0" xmlns="http://ns.editeur.org/onix/3.0/reference">
+  <Header><Sender><SenderName>Example Press</SenderName></Sender></Header>
+  <Product>
+    <RecordReference>com.example.0001</RecordReference>
+    <NotificationType>03</NotificationType>
+    <ProductIdentifier><ProductIDType>01</ProductIDType><IDValue>EX-1</IDValue></ProductIdentifier>
+    <ProductIdentifier><ProductIDType>15</ProductIDType><IDValue>978-0-14-143951-8</IDValue></ProductIdentifier>
+    <DescriptiveDetail>
+      <TitleDetail>
+        <TitleType>01</TitleType>
+        <TitleElement>
+          <TitleElementLevel>01</TitleElementLevel>
+          <TitlePrefix>The</TitlePrefix>
+          <TitleWithoutPrefix>Annotated Pride &amp; Prejudice</TitleWithoutPrefix>
+          <Subtitle>A Novel</Subtitle>
+        </TitleElement>
+      </TitleDetail>
+      <Contributor>
+        <SequenceNumber>2</SequenceNumber>
+        <ContributorRole>B01</ContributorRole>
+        <NamesBeforeKey>David M.</NamesBeforeKey>
+        <KeyNames>Shapard</KeyNames>
+      </Contributor>
+      <Contributor>
+        <SequenceNumber>1</SequenceNumber>
+        <ContributorRole>A01</ContributorRole>
+        <PersonName>Jane Austen</PersonName>
+      </Contributor>
+      <Contributor>
+        <SequenceNumber>3</SequenceNumber>
+        <ContributorRole>A15</ContributorRole>
+        <PersonName>Someone Else</PersonName>
+      </Contributor>
+      <Subject><SubjectSchemeIdentifier>10</SubjectSchemeIdentifier><SubjectHeadingText>Courtship</SubjectHeadingText></Subject>
+      <Subject><SubjectSchemeIdentifier>20</SubjectSchemeIdentifier><SubjectHeadingText>Regency; England</SubjectHeadingText></Subject>
+    </DescriptiveDetail>
+    <CollateralDetail>
+      <TextContent>
+        <TextType>03</TextType>
+        <Text textformat="05"><p>An <em>annotated</em> edition.</p><p>With maps.</p></Text>
+      </TextContent>
+    </CollateralDetail>
+    <PublishingDetail>
+      <PublishingDate><PublishingDateRole>19</PublishingDateRole><Date>20120101</Date></PublishingDate>
+      <PublishingDate><PublishingDateRole>01</PublishingDateRole><Date dateformat="00">20120410</Date></PublishingDate>
+    </PublishingDetail>
+  </Product>
+</ONIXMessage>"#;
+
+#[cfg(test)]
+const SHORT_MESSAGE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ONIXmessage release="3.0">
+  <product>
+    <a001>short-1</a001>
+    <a002>03</a002>
+    <productidentifier><b221>03</b221><b244>9780345391803</b244></productidentifier>
+    <descriptivedetail>
+      <titledetail><b202>01</b202><titleelement><x409>01</x409><b203>The Hitchhiker's Guide to the Galaxy</b203></titleelement></titledetail>
+      <contributor><b034>1</b034><b035>A01</b035><b036>Douglas Adams</b036></contributor>
+    </descriptivedetail>
+    <collateraldetail>
+      <textcontent><x426>02</x426><d104 textformat="02">&lt;b&gt;Don't panic.&lt;/b&gt;</d104></textcontent>
+    </collateraldetail>
+  </product>
+  <product>
+    <a001>short-2</a001>
+    <a002>05</a002>
+    <productidentifier><b221>15</b221><b244>9780000000002</b244></productidentifier>
+  </product>
+  <product>
+    <a001>short-3</a001>
+    <a002>03</a002>
+    <descriptivedetail>
+      <titledetail><b202>01</b202><titleelement><x409>01</x409><b203>No identifier</b203></titleelement></titledetail>
+    </descriptivedetail>
+  </product>
+</ONIXmessage>"#;
+
+#[test]
+fn test_parse_onix_reference_names() {
+    let products = parse_onix(REFERENCE_MESSAGE.as_bytes()).unwrap();
+    assert_eq!(products.len(), 1);
+    let product = &products[0];
+    assert_eq!(product.record_reference, "com.example.0001");
+    assert_eq!(product.isbn, "9780141439518");
+    assert_eq!(product.title, "The Annotated Pride & Prejudice: A Novel");
+    assert_eq!(
+        product.contributors,
+        vec![
+            Credit {
+                name: "Jane Austen".to_string(),
+                role: ContributorRole::Author,
+            },
+            Credit {
+                name: "David M. Shapard".to_string(),
+                role: ContributorRole::Editor,
+            },
+        ]
+    );
+    assert_eq!(product.subjects, ["Courtship", "Regency", "England"]);
+    assert_eq!(product.publication_year, Some(2012));
+    assert_eq!(
+        product.description.as_deref(),
+        Some("An annotated edition. With maps.")
+    );
+    assert!(product.errors.is_empty());
+}
+
+#[test]
+fn test_parse_onix_short_tags() {
+    let products = parse_onix(SHORT_MESSAGE.as_bytes()).unwrap();
+    assert_eq!(products.len(), 3);
+    assert_eq!(products[0].isbn, "9780345391803");
+    assert_eq!(products[0].title, "The Hitchhiker's Guide to the Galaxy");
+    assert_eq!(products[0].description.as_deref(), Some("Don't panic."));
+    assert_eq!(products[0].publication_year, None);
+    assert!(products[0].errors.is_empty());
+    assert_eq!(
+        products[1].errors,
+        ["deletion notices are not applied", "no title"]
+    );
+    assert_eq!(products[2].errors, ["no ISBN-13"]);
+
+    assert!(matches!(
+        parse_onix(b"<ONIXMessage release=\"2.1\"></ONIXMessage>"),
+        Err(LibraryErrorStatus::OnixInvalid(_))
+    ));
+    assert!(matches!(
+        parse_onix(b"<collection></collection>"),
+        Err(LibraryErrorStatus::OnixInvalid(_))
+    ));
+    assert!(matches!(
+        parse_onix(b"<ONIXMessage><Product>"),
+        Err(LibraryErrorStatus::OnixInvalid(_))
+    ));
+}
+
+#[test]
+fn test_onix_upsert_merge() {
+    let products = parse_onix(SHORT_MESSAGE.as_bytes()).unwrap();
+    let product = &products[0];
+    // Without a publication date the product cannot create a book, only update one.
+    assert!(product.to_book().is_none());
+
+    let now = Utc::now();
+    let existing = Book {
+        id: 7,
+        title: "Hitchhiker's Guide".to_string(),
+        author: "Douglas Adams".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![Credit {
+            name: "Douglas Adams".to_string(),
+            role: ContributorRole::Author,
+        }],
+        publication_year: 1979,
+        subjects: vec!["Science fiction".to_string()],
+        isbn: "9780345391803".to_string(),
+        description: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let mut updated = existing.clone();
+    product.apply(&mut updated);
+    assert_eq!(updated.title, "The Hitchhiker's Guide to the Galaxy");
+    assert_eq!(updated.author, "Douglas Adams");
+    assert_eq!(updated.publication_year, 1979);
+    assert_eq!(updated.subjects, ["Science fiction"]);
+    assert_eq!(updated.description.as_deref(), Some("Don't panic."));
+
+    let mut again = updated.clone();
+    product.apply(&mut again);
+    assert_eq!(again, updated);
+
+    let products = parse_onix(REFERENCE_MESSAGE.as_bytes()).unwrap();
+    let book = products[0].to_book().unwrap();
+    assert_eq!(book.author, "Jane Austen");
+    assert_eq!(book.publication_year, 2012);
+}
@@ -0,0 +1,903 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::warn;
+use quick_xml::escape::escape;
+use sea_orm::{
+    sea_query::{Alias, Expr, Order, Query},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    model::request::search::BookSearch,
+    orm::{
+        book::{self, Book},
+        book_contributor::{self, ContributorRole},
+        contributor,
+    },
+};
+
+use super::{
+    cursor::page_offset, facet::count_of, search::credited_books, search_condition,
+    sort::author_sort_key, Library, LibraryErrorStatus,
+};
+
+/// Entries per page of an acquisition feed or of the author list.
+pub const OPDS_PAGE_SIZE: u64 = 50;
+
+const NAVIGATION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+const ACQUISITION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const OPDS_JSON_TYPE: &str = "application/opds+json";
+const OPENSEARCH_TYPE: &str = "application/opensearchdescription+xml";
+const BORROW_REL: &str = "http://opds-spec.org/acquisition/borrow";
+const NEW_REL: &str = "http://opds-spec.org/sort/new";
+const IMAGE_REL: &str = "http://opds-spec.org/image";
+const THUMBNAIL_REL: &str = "http://opds-spec.org/image/thumbnail";
+// OpenSearch caps `ShortName` at 16 characters.
+const SHORT_NAME_LENGTH: usize = 16;
+
+/// Names the catalog in feeds and anchors every link in them.
+#[derive(Debug, Clone)]
+pub struct OpdsCatalog {
+    pub name: String,
+    /// The public URL of the server, without a trailing slash.
+    pub base_url: String,
+}
+
+impl OpdsCatalog {
+    fn root(&self, format: OpdsFormat) -> String {
+        match format {
+            OpdsFormat::Atom => format!("{}/opds", self.base_url),
+            OpdsFormat::Json => format!("{}/opds/v2", self.base_url),
+        }
+    }
+
+    fn book_url(&self, id: u64) -> String {
+        format!("{}/books/{id}", self.base_url)
+    }
+
+    /// The OpenSearch description behind the `search` link of every Atom feed. Search terms go
+    /// to `BookSearch::query`, so the advanced query syntax works from e-reader apps too.
+    pub fn opensearch_description(&self) -> String {
+        let short_name = self
+            .name
+            .chars()
+            .take(SHORT_NAME_LENGTH)
+            .collect::<String>();
+        let url = |format, media_type| {
+            format!(
+                "  <Url type=\"{media_type}\" template=\"{}\"/>\n",
+                escape(format!(
+                    "{}/search?query={{searchTerms}}",
+                    self.root(format)
+                ))
+            )
+        };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n  \
+             <ShortName>{}</ShortName>\n  \
+             <Description>Search the {} catalog</Description>\n  \
+             <InputEncoding>UTF-8</InputEncoding>\n  \
+             <OutputEncoding>UTF-8</OutputEncoding>\n\
+             {}{}</OpenSearchDescription>\n",
+            escape(&short_name),
+            escape(&self.name),
+            url(OpdsFormat::Atom, ACQUISITION_TYPE),
+            url(OpdsFormat::Json, OPDS_JSON_TYPE),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpdsFormat {
+    /// OPDS 1.2, an Atom feed.
+    Atom,
+    /// OPDS 2.0, a JSON document.
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub enum OpdsRequest {
+    Root,
+    NewArrivals { page: u64 },
+    Authors { page: u64 },
+    Author { id: u64, page: u64 },
+    Years,
+    Year { year: u64, page: u64 },
+    Search { search: BookSearch, page: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeedKind {
+    Navigation,
+    Acquisition,
+}
+
+impl FeedKind {
+    fn atom_type(self) -> &'static str {
+        match self {
+            Self::Navigation => NAVIGATION_TYPE,
+            Self::Acquisition => ACQUISITION_TYPE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NavigationEntry {
+    title: String,
+    /// Relative to the catalog root, like `OpdsFeed::path`.
+    path: String,
+    rel: &'static str,
+    kind: FeedKind,
+    /// How many books the linked feed holds, if known.
+    count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FeedPage {
+    number: u64,
+    /// Entries across all pages.
+    total: u64,
+}
+
+impl FeedPage {
+    fn last(self) -> u64 {
+        self.total.div_ceil(OPDS_PAGE_SIZE).max(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Publication {
+    book: Book,
+    has_cover: bool,
+}
+
+/// One navigation or acquisition feed, independent of the format it is served in.
+#[derive(Debug, Clone)]
+pub struct OpdsFeed {
+    /// Relative to the catalog root, e.g. `/authors/3`.
+    path: String,
+    /// The URL-encoded search parameters carried into pagination links.
+    query: String,
+    title: String,
+    kind: FeedKind,
+    updated: DateTime<Utc>,
+    page: Option<FeedPage>,
+    navigation: Vec<NavigationEntry>,
+    publications: Vec<Publication>,
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn atom_link(rel: &str, href: &str, media_type: &str) -> String {
+    format!(
+        "  <link rel=\"{}\" href=\"{}\" type=\"{}\"/>\n",
+        escape(rel),
+        escape(href),
+        escape(media_type)
+    )
+}
+
+fn json_link(rel: &str, href: &str, media_type: &str) -> Value {
+    json!({ "rel": rel, "href": href, "type": media_type })
+}
+
+fn book_count(count: u64) -> String {
+    match count {
+        1 => "1 book".to_string(),
+        count => format!("{count} books"),
+    }
+}
+
+impl OpdsFeed {
+    fn navigation(path: &str, title: &str, entries: Vec<NavigationEntry>) -> Self {
+        Self {
+            path: path.to_string(),
+            query: String::new(),
+            title: title.to_string(),
+            kind: FeedKind::Navigation,
+            updated: Utc::now(),
+            page: None,
+            navigation: entries,
+            publications: vec![],
+        }
+    }
+
+    fn acquisition(path: &str, title: &str) -> Self {
+        Self {
+            kind: FeedKind::Acquisition,
+            ..Self::navigation(path, title, vec![])
+        }
+    }
+
+    pub fn content_type(&self, format: OpdsFormat) -> &'static str {
+        match format {
+            OpdsFormat::Atom => self.kind.atom_type(),
+            OpdsFormat::Json => OPDS_JSON_TYPE,
+        }
+    }
+
+    /// The URL of page `number` of this feed; the first page carries no `page` parameter.
+    fn href(&self, catalog: &OpdsCatalog, format: OpdsFormat, number: u64) -> String {
+        let mut parameters = vec![];
+        if !self.query.is_empty() {
+            parameters.push(self.query.clone());
+        }
+        if number > 1 {
+            parameters.push(format!("page={number}"));
+        }
+        let mut href = format!("{}{}", catalog.root(format), self.path);
+        if !parameters.is_empty() {
+            href.push('?');
+            href.push_str(&parameters.join("&"));
+        }
+        href
+    }
+
+    /// `first`, `previous`, `next` and `last`, as far as they exist.
+    fn pagination(&self) -> Vec<(&'static str, u64)> {
+        let Some(page) = self.page else {
+            return vec![];
+        };
+        let mut links = vec![("first", 1)];
+        if page.number > 1 {
+            links.push(("previous", page.number - 1));
+        }
+        if page.number < page.last() {
+            links.push(("next", page.number + 1));
+        }
+        links.push(("last", page.last()));
+        links
+    }
+
+    pub fn render(&self, format: OpdsFormat, catalog: &OpdsCatalog) -> String {
+        match format {
+            OpdsFormat::Atom => self.to_atom(catalog),
+            OpdsFormat::Json => self.to_json(catalog),
+        }
+    }
+
+    fn to_atom(&self, catalog: &OpdsCatalog) -> String {
+        let format = OpdsFormat::Atom;
+        let this = self.href(catalog, format, self.page.map_or(1, |page| page.number));
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\" \
+             xmlns:dc=\"http://purl.org/dc/terms/\" \
+             xmlns:opds=\"http://opds-spec.org/2010/catalog\" \
+             xmlns:opensearch=\"http://a9.com/-/spec/opensearch/1.1/\" \
+             xmlns:thr=\"http://purl.org/syndication/thread/1.0\">\n",
+        );
+        xml.push_str(&format!(
+            "  <id>{}</id>\n  <title>{}</title>\n  <updated>{}</updated>\n",
+            escape(self.href(catalog, format, 1)),
+            escape(&self.title),
+            timestamp(&self.updated)
+        ));
+        xml.push_str(&format!(
+            "  <author>\n    <name>{}</name>\n    <uri>{}</uri>\n  </author>\n",
+            escape(&catalog.name),
+            escape(&catalog.base_url)
+        ));
+        xml.push_str(&atom_link("self", &this, self.kind.atom_type()));
+        xml.push_str(&atom_link("start", &catalog.root(format), NAVIGATION_TYPE));
+        xml.push_str(&atom_link(
+            "search",
+            &format!("{}/opensearch.xml", catalog.root(format)),
+            OPENSEARCH_TYPE,
+        ));
+        for (rel, number) in self.pagination() {
+            xml.push_str(&atom_link(
+                rel,
+                &self.href(catalog, format, number),
+                self.kind.atom_type(),
+            ));
+        }
+        if let Some(page) = self.page {
+            xml.push_str(&format!(
+                "  <opensearch:totalResults>{}</opensearch:totalResults>\n  \
+                 <opensearch:itemsPerPage>{OPDS_PAGE_SIZE}</opensearch:itemsPerPage>\n  \
+                 <opensearch:startIndex>{}</opensearch:startIndex>\n",
+                page.total,
+                (page.number - 1) * OPDS_PAGE_SIZE + 1
+            ));
+        }
+
+        for entry in &self.navigation {
+            let href = format!("{}{}", catalog.root(format), entry.path);
+            xml.push_str(&format!(
+                "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <updated>{}</updated>\n",
+                escape(&entry.title),
+                escape(&href),
+                timestamp(&self.updated)
+            ));
+            let count = entry
+                .count
+                .map(|count| format!(" thr:count=\"{count}\""))
+                .unwrap_or_default();
+            xml.push_str(&format!(
+                "    <link rel=\"{}\" href=\"{}\" type=\"{}\"{count}/>\n",
+                entry.rel,
+                escape(&href),
+                entry.kind.atom_type()
+            ));
+            if let Some(count) = entry.count {
+                xml.push_str(&format!(
+                    "    <content type=\"text\">{}</content>\n",
+                    book_count(count)
+                ));
+            }
+            xml.push_str("  </entry>\n");
+        }
+
+        for Publication { book, has_cover } in &self.publications {
+            let url = catalog.book_url(book.id);
+            xml.push_str(&format!(
+                "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <updated>{}</updated>\n",
+                escape(&book.title),
+                escape(&url),
+                timestamp(&book.updated_at)
+            ));
+            for credit in &book.contributors {
+                let element = match credit.role {
+                    ContributorRole::Author => "author",
+                    _ => "contributor",
+                };
+                xml.push_str(&format!(
+                    "    <{element}>\n      <name>{}</name>\n    </{element}>\n",
+                    escape(&credit.name)
+                ));
+            }
+            xml.push_str(&format!(
+                "    <dc:identifier>urn:isbn:{}</dc:identifier>\n    \
+                 <dc:issued>{}</dc:issued>\n",
+                escape(&book.isbn),
+                book.publication_year
+            ));
+            if let Some(description) = &book.description {
+                xml.push_str(&format!("    <summary>{}</summary>\n", escape(description)));
+            }
+            for subject in &book.subjects {
+                xml.push_str(&format!(
+                    "    <category term=\"{0}\" label=\"{0}\"/>\n",
+                    escape(subject)
+                ));
+            }
+            let mut links = vec![
+                ("alternate", url.clone(), "application/json"),
+                (BORROW_REL, format!("{url}/holds"), "application/json"),
+            ];
+            if *has_cover {
+                links.push((IMAGE_REL, format!("{url}/cover"), ""));
+                links.push((
+                    THUMBNAIL_REL,
+                    format!("{url}/cover?size=thumbnail"),
+                    "image/jpeg",
+                ));
+            }
+            for (rel, href, media_type) in links {
+                let link = match media_type {
+                    "" => format!("  <link rel=\"{rel}\" href=\"{}\"/>\n", escape(&href)),
+                    media_type => atom_link(rel, &href, media_type),
+                };
+                xml.push_str("  ");
+                xml.push_str(&link);
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn to_json(&self, catalog: &OpdsCatalog) -> String {
+        let format = OpdsFormat::Json;
+        let this = self.href(catalog, format, self.page.map_or(1, |page| page.number));
+        let mut metadata = json!({
+            "title": self.title,
+            "modified": timestamp(&self.updated),
+        });
+        if let Some(page) = self.page {
+            metadata["numberOfItems"] = page.total.into();
+            metadata["itemsPerPage"] = OPDS_PAGE_SIZE.into();
+            metadata["currentPage"] = page.number.into();
+        }
+
+        let mut links = vec![
+            json_link("self", &this, OPDS_JSON_TYPE),
+            json_link("start", &catalog.root(format), OPDS_JSON_TYPE),
+            json!({
+                "rel": "search",
+                "href": format!("{}/search{{?query}}", catalog.root(format)),
+                "type": OPDS_JSON_TYPE,
+                "templated": true,
+            }),
+        ];
+        for (rel, number) in self.pagination() {
+            links.push(json_link(
+                rel,
+                &self.href(catalog, format, number),
+                OPDS_JSON_TYPE,
+            ));
+        }
+
+        let mut feed = json!({ "metadata": metadata, "links": links });
+        match self.kind {
+            FeedKind::Navigation => {
+                let navigation = self.navigation.iter().map(|entry| {
+                    let mut link = json!({
+                        "rel": entry.rel,
+                        "href": format!("{}{}", catalog.root(format), entry.path),
+                        "type": OPDS_JSON_TYPE,
+                        "title": entry.title,
+                    });
+                    if let Some(count) = entry.count {
+                        link["properties"] = json!({ "numberOfItems": count });
+                    }
+                    link
+                });
+                feed["navigation"] = navigation.collect::<Vec<_>>().into();
+            }
+            FeedKind::Acquisition => {
+                let publications = self
+                    .publications
+                    .iter()
+                    .map(|publication| json_publication(publication, catalog));
+                feed["publications"] = publications.collect::<Vec<_>>().into();
+            }
+        }
+        serde_json::to_string_pretty(&feed).unwrap_or_default()
+    }
+}
+
+fn json_publication(publication: &Publication, catalog: &OpdsCatalog) -> Value {
+    let book = &publication.book;
+    let url = catalog.book_url(book.id);
+    let mut metadata = json!({
+        "@type": "http://schema.org/Book",
+        "title": book.title,
+        "identifier": format!("urn:isbn:{}", book.isbn),
+        "modified": timestamp(&book.updated_at),
+        "published": book.publication_year.to_string(),
+    });
+    for (key, role) in [
+        ("author", ContributorRole::Author),
+        ("editor", ContributorRole::Editor),
+        ("translator", ContributorRole::Translator),
+        ("illustrator", ContributorRole::Illustrator),
+    ] {
+        let names = book
+            .contributors
+            .iter()
+            .filter(|credit| credit.role == role)
+            .map(|credit| json!({ "name": credit.name }))
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            metadata[key] = names.into();
+        }
+    }
+    if !book.subjects.is_empty() {
+        metadata["subject"] = book.subjects.clone().into();
+    }
+    if let Some(description) = &book.description {
+        metadata["description"] = description.as_str().into();
+    }
+
+    let mut entry = json!({
+        "metadata": metadata,
+        "links": [
+            json_link("alternate", &url, "application/json"),
+            json_link(BORROW_REL, &format!("{url}/holds"), "application/json"),
+        ],
+    });
+    if publication.has_cover {
+        entry["images"] = json!([
+            { "href": format!("{url}/cover") },
+            { "href": format!("{url}/cover?size=thumbnail"), "type": "image/jpeg" },
+        ]);
+    }
+    entry
+}
+
+impl Library {
+    pub async fn opds_feed(
+        &self,
+        request: OpdsRequest,
+        catalog: &OpdsCatalog,
+        database: &DatabaseConnection,
+    ) -> Result<OpdsFeed, LibraryErrorStatus> {
+        match request {
+            OpdsRequest::Root => Ok(OpdsFeed::navigation(
+                "",
+                &catalog.name,
+                vec![
+                    NavigationEntry {
+                        title: "New arrivals".to_string(),
+                        path: "/new".to_string(),
+                        rel: NEW_REL,
+                        kind: FeedKind::Acquisition,
+                        count: None,
+                    },
+                    NavigationEntry {
+                        title: "By author".to_string(),
+                        path: "/authors".to_string(),
+                        rel: "subsection",
+                        kind: FeedKind::Navigation,
+                        count: None,
+                    },
+                    NavigationEntry {
+                        title: "By year".to_string(),
+                        path: "/years".to_string(),
+                        rel: "subsection",
+                        kind: FeedKind::Navigation,
+                        count: None,
+                    },
+                ],
+            )),
+            OpdsRequest::NewArrivals { page } => {
+                let feed = OpdsFeed::acquisition("/new", "New arrivals");
+                self.opds_books(feed, Condition::all(), true, page, database)
+                    .await
+            }
+            OpdsRequest::Authors { page } => Self::opds_authors(page, database).await,
+            OpdsRequest::Author { id, page } => {
+                let db_result = contributor::Entity::find_by_id(id).one(database).await;
+                if let Err(error) = &db_result {
+                    warn!("failed to fetch contributor {id}: {error}");
+                    return Err(LibraryErrorStatus::DatabaseError);
+                }
+                let Some(author) = db_result.unwrap() else {
+                    return Err(LibraryErrorStatus::ContributorNotFound);
+                };
+                let credited = credited_books(
+                    Some(Expr::col((contributor::Entity, contributor::Column::Id)).eq(id)),
+                    Some(ContributorRole::Author),
+                );
+                let feed = OpdsFeed::acquisition(&format!("/authors/{id}"), &author.name);
+                self.opds_books(feed, Condition::all().add(credited), false, page, database)
+                    .await
+            }
+            OpdsRequest::Years => Self::opds_years(database).await,
+            OpdsRequest::Year { year, page } => {
+                let feed =
+                    OpdsFeed::acquisition(&format!("/years/{year}"), &format!("Published {year}"));
+                let condition = Condition::all().add(book::Column::PublicationYear.eq(year));
+                self.opds_books(feed, condition, false, page, database)
+                    .await
+            }
+            OpdsRequest::Search { search, page } => {
+                let condition = search_condition(&search)?;
+                let mut feed = OpdsFeed::acquisition("/search", "Search results");
+                feed.query = serde_urlencoded::to_string(&search).unwrap_or_default();
+                self.opds_books(feed, condition, false, page, database)
+                    .await
+            }
+        }
+    }
+
+    /// Fills `feed` with page `page` of the books matching `condition`, newest first or by title.
+    async fn opds_books(
+        &self,
+        mut feed: OpdsFeed,
+        condition: Condition,
+        newest_first: bool,
+        page: u64,
+        database: &DatabaseConnection,
+    ) -> Result<OpdsFeed, LibraryErrorStatus> {
+        let query = book::Entity::find().filter(condition);
+
+        let db_result = query.clone().count(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to count books for OPDS feed: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let total = db_result.unwrap();
+        let offset = page_offset(page, OPDS_PAGE_SIZE, total)?;
+
+        let query = if newest_first {
+            query.order_by_desc(book::Column::CreatedAt)
+        } else {
+            query.order_by_asc(book::Column::TitleSort)
+        };
+        let db_result = query
+            .order_by_asc(book::Column::Id)
+            .offset(offset)
+            .limit(OPDS_PAGE_SIZE)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books for OPDS feed: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+
+        if let Some(updated) = books.iter().map(|book| book.updated_at).max() {
+            feed.updated = updated;
+        }
+        feed.page = Some(FeedPage {
+            number: page,
+            total,
+        });
+        feed.publications = books
+            .into_iter()
+            .map(|book| Publication {
+                has_cover: self.has_cover(book.id),
+                book,
+            })
+            .collect();
+        Ok(feed)
+    }
+
+    /// Every credited author with a count of their books, in author sort order.
+    async fn opds_authors(
+        page: u64,
+        database: &DatabaseConnection,
+    ) -> Result<OpdsFeed, LibraryErrorStatus> {
+        let statement = Query::select()
+            .expr_as(
+                Expr::col((contributor::Entity, contributor::Column::Id)),
+                Alias::new("id"),
+            )
+            .expr_as(
+                Expr::col((contributor::Entity, contributor::Column::Name)),
+                Alias::new("name"),
+            )
+            .expr_as(
+                Expr::col((book_contributor::Entity, book_contributor::Column::Book))
+                    .count_distinct(),
+                Alias::new("count"),
+            )
+            .from(book_contributor::Entity)
+            .inner_join(
+                contributor::Entity,
+                Expr::col((contributor::Entity, contributor::Column::Id)).equals((
+                    book_contributor::Entity,
+                    book_contributor::Column::Contributor,
+                )),
+            )
+            .and_where(book_contributor::Column::Role.eq(ContributorRole::Author))
+            .group_by_col((contributor::Entity, contributor::Column::Id))
+            .group_by_col((contributor::Entity, contributor::Column::Name))
+            .to_owned();
+
+        let mut authors = vec![];
+        for row in Self::query_facet(&statement, database).await? {
+            let author = row
+                .try_get::<u64>("", "id")
+                .and_then(|id| Ok((id, row.try_get::<String>("", "name")?)));
+            if let Err(error) = &author {
+                warn!("failed to read OPDS author: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            let (id, name) = author.unwrap();
+            authors.push((author_sort_key(&name), id, name, count_of(&row)?));
+        }
+        // Contributors have no stored sort key, so the ordering happens here.
+        authors.sort();
+
+        let total = authors.len() as u64;
+        let offset = page_offset(page, OPDS_PAGE_SIZE, total)?;
+        let entries = authors
+            .into_iter()
+            .skip(offset as usize)
+            .take(OPDS_PAGE_SIZE as usize)
+            .map(|(_, id, name, count)| NavigationEntry {
+                title: name,
+                path: format!("/authors/{id}"),
+                rel: "subsection",
+                kind: FeedKind::Acquisition,
+                count: Some(count),
+            })
+            .collect();
+        let mut feed = OpdsFeed::navigation("/authors", "By author", entries);
+        feed.page = Some(FeedPage {
+            number: page,
+            total,
+        });
+        Ok(feed)
+    }
+
+    /// Every publication year with a count of its books, most recent first.
+    async fn opds_years(database: &DatabaseConnection) -> Result<OpdsFeed, LibraryErrorStatus> {
+        let year = Alias::new("year");
+        let statement = Query::select()
+            .expr_as(
+                Expr::col((book::Entity, book::Column::PublicationYear)),
+                year.clone(),
+            )
+            .expr_as(
+                Expr::col((book::Entity, book::Column::Id)).count(),
+                Alias::new("count"),
+            )
+            .from(book::Entity)
+            .group_by_col((book::Entity, book::Column::PublicationYear))
+            .order_by(year, Order::Desc)
+            .to_owned();
+
+        let mut entries = vec![];
+        for row in Self::query_facet(&statement, database).await? {
+            let year = row.try_get::<u64>("", "year");
+            if let Err(error) = &year {
+                warn!("failed to read OPDS year: {error}");
+                return Err(LibraryErrorStatus::DatabaseError);
+            }
+            let year = year.unwrap();
+            entries.push(NavigationEntry {
+                title: year.to_string(),
+                path: format!("/years/{year}"),
+                rel: "subsection",
+                kind: FeedKind::Acquisition,
+                count: Some(count_of(&row)?),
+            });
+        }
+        Ok(OpdsFeed::navigation("/years", "By year", entries))
+    }
+}
+
+#[cfg(test)]
+fn sample_catalog() -> OpdsCatalog {
+    OpdsCatalog {
+        name: "Springfield Public Library".to_string(),
+        base_url: "https://library.example.org".to_string(),
+    }
+}
+
+#[test]
+fn test_opds_navigation_feed() {
+    let catalog = sample_catalog();
+    let mut feed = OpdsFeed::navigation(
+        "/years",
+        "By year",
+        vec![NavigationEntry {
+            title: "1980".to_string(),
+            path: "/years/1980".to_string(),
+            rel: "subsection",
+            kind: FeedKind::Acquisition,
+            count: Some(3),
+        }],
+    );
+    feed.updated = DateTime::parse_from_rfc3339("2024-03-01T10:20:30Z")
+        .unwrap()
+        .into();
+
+    assert_eq!(feed.content_type(OpdsFormat::Atom), NAVIGATION_TYPE);
+    let atom = feed.render(OpdsFormat::Atom, &catalog);
+    assert!(atom.contains("<id>https://library.example.org/opds/years</id>"));
+    assert!(atom.contains("<updated>2024-03-01T10:20:30Z</updated>"));
+    assert!(atom.contains(
+        "<link rel=\"search\" href=\"https://library.example.org/opds/opensearch.xml\" \
+         type=\"application/opensearchdescription+xml\"/>"
+    ));
+    assert!(atom.contains(
+        "<link rel=\"subsection\" href=\"https://library.example.org/opds/years/1980\" \
+         type=\"application/atom+xml;profile=opds-catalog;kind=acquisition\" thr:count=\"3\"/>"
+    ));
+    assert!(atom.contains("<content type=\"text\">3 books</content>"));
+    assert!(!atom.contains("rel=\"next\""));
+
+    let json = serde_json::from_str::<Value>(&feed.render(OpdsFormat::Json, &catalog)).unwrap();
+    assert_eq!(
+        json["links"][0],
+        json!({
+            "rel": "self",
+            "href": "https://library.example.org/opds/v2/years",
+            "type": "application/opds+json",
+        })
+    );
+    assert_eq!(
+        json["navigation"],
+        json!([{
+            "rel": "subsection",
+            "href": "https://library.example.org/opds/v2/years/1980",
+            "type": "application/opds+json",
+            "title": "1980",
+            "properties": { "numberOfItems": 3 },
+        }])
+    );
+    assert!(json.get("publications").is_none());
+
+    let opensearch = catalog.opensearch_description();
+    assert!(opensearch.contains("<ShortName>Springfield Publ</ShortName>"));
+    assert!(opensearch
+        .contains("template=\"https://library.example.org/opds/search?query={searchTerms}\""));
+}
+
+#[test]
+fn test_opds_acquisition_feed() {
+    use crate::orm::book_contributor::Credit;
+
+    let updated_at = DateTime::parse_from_rfc3339("2024-03-01T10:20:30Z")
+        .unwrap()
+        .into();
+    let book = Book {
+        id: 7,
+        title: "Dune & Beyond".to_string(),
+        author: "Frank Herbert".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![
+            Credit {
+                name: "Frank Herbert".to_string(),
+                role: ContributorRole::Author,
+            },
+            Credit {
+                name: "John Schoenherr".to_string(),
+                role: ContributorRole::Illustrator,
+            },
+        ],
+        publication_year: 1965,
+        subjects: vec!["Science fiction".to_string()],
+        isbn: "9780441013593".to_string(),
+        description: None,
+        created_at: updated_at,
+        updated_at,
+    };
+    let mut feed = OpdsFeed::acquisition("/search", "Search results");
+    feed.query = "query=dune".to_string();
+    feed.updated = updated_at;
+    feed.page = Some(FeedPage {
+        number: 2,
+        total: 120,
+    });
+    feed.publications = vec![Publication {
+        book,
+        has_cover: true,
+    }];
+
+    let catalog = sample_catalog();
+    let atom = feed.render(OpdsFormat::Atom, &catalog);
+    for (rel, page) in [("first", ""), ("previous", ""), ("next", "&amp;page=3")] {
+        assert!(atom.contains(&format!(
+            "<link rel=\"{rel}\" href=\"https://library.example.org/opds/search?query=dune{page}\""
+        )));
+    }
+    assert!(atom.contains(
+        "<link rel=\"last\" href=\"https://library.example.org/opds/search?query=dune&amp;page=3\""
+    ));
+    assert!(atom.contains("<opensearch:startIndex>51</opensearch:startIndex>"));
+    assert!(atom.contains("<title>Dune &amp; Beyond</title>"));
+    assert!(atom.contains("<author>\n      <name>Frank Herbert</name>\n    </author>"));
+    assert!(atom.contains("<contributor>\n      <name>John Schoenherr</name>\n    </contributor>"));
+    assert!(atom.contains("<dc:identifier>urn:isbn:9780441013593</dc:identifier>"));
+    assert!(atom.contains(
+        "<link rel=\"http://opds-spec.org/acquisition/borrow\" \
+         href=\"https://library.example.org/books/7/holds\" type=\"application/json\"/>"
+    ));
+    assert!(atom.contains(
+        "<link rel=\"http://opds-spec.org/image/thumbnail\" \
+         href=\"https://library.example.org/books/7/cover?size=thumbnail\" type=\"image/jpeg\"/>"
+    ));
+
+    let json = serde_json::from_str::<Value>(&feed.render(OpdsFormat::Json, &catalog)).unwrap();
+    assert_eq!(
+        json["metadata"],
+        json!({
+            "title": "Search results",
+            "modified": "2024-03-01T10:20:30Z",
+            "numberOfItems": 120,
+            "itemsPerPage": 50,
+            "currentPage": 2,
+        })
+    );
+    assert_eq!(
+        json["links"][0]["href"],
+        "https://library.example.org/opds/v2/search?query=dune&page=2"
+    );
+    let publication = &json["publications"][0];
+    assert_eq!(
+        publication["metadata"]["author"],
+        json!([{ "name": "Frank Herbert" }])
+    );
+    assert_eq!(
+        publication["metadata"]["illustrator"],
+        json!([{ "name": "John Schoenherr" }])
+    );
+    assert_eq!(publication["metadata"]["published"], "1965");
+    assert_eq!(
+        publication["images"][1]["href"],
+        "https://library.example.org/books/7/cover?size=thumbnail"
+    );
+}
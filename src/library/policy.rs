@@ -0,0 +1,249 @@
+use log::{trace, warn};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::request::loan_policy::LoanPolicyRequest,
+    orm::{
+        copy::BookCopy,
+        policy::{self, LoanPolicy},
+        user,
+    },
+};
+
+use super::{Library, LibraryErrorStatus, MAX_LEDGER_AMOUNT};
+
+// Used for circulation when no configured rule matches; fines then fall back to `FINE_PER_DAY`.
+const DEFAULT_LOAN_DAYS: u32 = 14;
+const DEFAULT_MAX_RENEWALS: u32 = 2;
+const DEFAULT_MAX_LOANS: u32 = 25;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PolicyKey {
+    pub patron_category: String,
+    pub item_type: String,
+    pub branch: String,
+}
+
+impl LoanPolicy {
+    pub fn fallback() -> Self {
+        Self {
+            id: 0,
+            patron_category: None,
+            item_type: None,
+            branch: None,
+            loan_days: DEFAULT_LOAN_DAYS,
+            max_renewals: DEFAULT_MAX_RENEWALS,
+            max_loans: DEFAULT_MAX_LOANS,
+            fine_per_day: 0,
+        }
+    }
+
+    fn matches(&self, key: &PolicyKey) -> bool {
+        let field_matches = |rule: &Option<String>, value: &str| {
+            rule.as_ref()
+                .is_none_or(|rule| rule.eq_ignore_ascii_case(value))
+        };
+        field_matches(&self.patron_category, &key.patron_category)
+            && field_matches(&self.item_type, &key.item_type)
+            && field_matches(&self.branch, &key.branch)
+    }
+
+    // Patron category outranks item type, which outranks branch, so any two rules that both match are ordered.
+    fn specificity(&self) -> u8 {
+        (self.patron_category.is_some() as u8) << 2
+            | (self.item_type.is_some() as u8) << 1
+            | self.branch.is_some() as u8
+    }
+}
+
+/// Picks the most specific rule matching `key`, preferring the oldest rule on a tie.
+pub fn resolve_policy<'a>(rules: &'a [LoanPolicy], key: &PolicyKey) -> Option<&'a LoanPolicy> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(key))
+        .max_by(|a, b| a.specificity().cmp(&b.specificity()).then(b.id.cmp(&a.id)))
+}
+
+impl Library {
+    pub async fn get_policies(
+        &self,
+        database: &DatabaseConnection,
+    ) -> Result<Vec<LoanPolicy>, LibraryErrorStatus> {
+        let db_result = policy::Entity::find()
+            .order_by_asc(policy::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch loan policies: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn resolve_policy(
+        &self,
+        key: &PolicyKey,
+        database: &DatabaseConnection,
+    ) -> Result<Option<LoanPolicy>, LibraryErrorStatus> {
+        let rules = self.get_policies(database).await?;
+        Ok(resolve_policy(&rules, key).cloned())
+    }
+
+    pub(super) async fn policy_key_for<C: ConnectionTrait>(
+        borrower: u64,
+        copy: &BookCopy,
+        database: &C,
+    ) -> Result<PolicyKey, LibraryErrorStatus> {
+        let db_result = user::Entity::find_by_id(borrower).one(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch borrower: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let borrower = db_result.unwrap();
+        if borrower.is_none() {
+            return Err(LibraryErrorStatus::UserNotFound);
+        }
+        Ok(PolicyKey {
+            patron_category: borrower.unwrap().category,
+            item_type: copy.item_type.clone(),
+            branch: copy.branch.clone(),
+        })
+    }
+
+    pub async fn add_policy(
+        &self,
+        request: LoanPolicyRequest,
+        database: &DatabaseConnection,
+    ) -> Result<LoanPolicy, LibraryErrorStatus> {
+        if request.loan_days == 0 || request.fine_per_day > MAX_LEDGER_AMOUNT {
+            return Err(LibraryErrorStatus::PolicyInvalid);
+        }
+
+        trace!("inserting loan policy to db");
+        let db_result = policy::ActiveModel {
+            id: ActiveValue::NotSet,
+            patron_category: ActiveValue::Set(request.patron_category),
+            item_type: ActiveValue::Set(request.item_type),
+            branch: ActiveValue::Set(request.branch),
+            loan_days: ActiveValue::Set(request.loan_days),
+            max_renewals: ActiveValue::Set(request.max_renewals),
+            max_loans: ActiveValue::Set(request.max_loans),
+            fine_per_day: ActiveValue::Set(request.fine_per_day),
+        }
+        .insert(database)
+        .await;
+        if let Err(error) = &db_result {
+            warn!("failed to add loan policy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn update_policy(
+        &self,
+        id: u64,
+        request: LoanPolicyRequest,
+        database: &DatabaseConnection,
+    ) -> Result<LoanPolicy, LibraryErrorStatus> {
+        if request.loan_days == 0 || request.fine_per_day > MAX_LEDGER_AMOUNT {
+            return Err(LibraryErrorStatus::PolicyInvalid);
+        }
+
+        let db_result = policy::Entity::find_by_id(id).one(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch loan policy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let old_policy = db_result.unwrap();
+        if old_policy.is_none() {
+            return Err(LibraryErrorStatus::PolicyNotFound);
+        }
+
+        let policy = LoanPolicy {
+            id,
+            patron_category: request.patron_category,
+            item_type: request.item_type,
+            branch: request.branch,
+            loan_days: request.loan_days,
+            max_renewals: request.max_renewals,
+            max_loans: request.max_loans,
+            fine_per_day: request.fine_per_day,
+        };
+
+        trace!("updating loan policy in db");
+        let db_result = policy
+            .into_active_model()
+            .reset_all()
+            .update(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to update loan policy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(db_result.unwrap())
+    }
+
+    pub async fn drop_policy(
+        &self,
+        id: u64,
+        database: &DatabaseConnection,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = policy::Entity::delete_by_id(id).exec(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to drop loan policy: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        if db_result.unwrap().rows_affected == 0 {
+            return Err(LibraryErrorStatus::PolicyNotFound);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn rule(
+    id: u64,
+    patron_category: Option<&str>,
+    item_type: Option<&str>,
+    branch: Option<&str>,
+) -> LoanPolicy {
+    LoanPolicy {
+        id,
+        patron_category: patron_category.map(str::to_string),
+        item_type: item_type.map(str::to_string),
+        branch: branch.map(str::to_string),
+        ..LoanPolicy::fallback()
+    }
+}
+
+#[test]
+fn test_resolve_policy() {
+    let rules = vec![
+        rule(1, None, None, None),
+        rule(2, None, Some("dvd"), None),
+        rule(3, Some("staff"), None, None),
+        rule(4, None, None, Some("north")),
+        rule(5, Some("staff"), None, None),
+    ];
+    let key = |patron_category: &str, item_type: &str, branch: &str| PolicyKey {
+        patron_category: patron_category.to_string(),
+        item_type: item_type.to_string(),
+        branch: branch.to_string(),
+    };
+
+    let resolved = |key| resolve_policy(&rules, &key).map(|rule| rule.id);
+    assert_eq!(resolved(key("standard", "book", "main")), Some(1));
+    assert_eq!(resolved(key("standard", "book", "north")), Some(4));
+    assert_eq!(resolved(key("standard", "DVD", "north")), Some(2));
+    assert_eq!(resolved(key("staff", "dvd", "north")), Some(3));
+    assert_eq!(
+        resolve_policy(&rules[1..2], &key("standard", "book", "main")),
+        None
+    );
+}
@@ -0,0 +1,587 @@
+use std::fmt::Display;
+
+use sea_orm::{
+    sea_query::{Expr, LikeExpr, SimpleExpr},
+    ColumnTrait, Condition,
+};
+
+use crate::orm::{book, book_subject, contributor};
+
+use super::{
+    normalize_isbn,
+    search::{credited_books, escape_like, subject_books},
+    strip_isbn,
+};
+
+/// A parsed catalog query such as
+/// `title:"hitchhiker" AND (author:adams OR author:pratchett) year:1979..1990 -isbn:978057*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryNode {
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+    Term {
+        /// `None` searches title, author and ISBN at once.
+        field: Option<QueryField>,
+        value: QueryValue,
+        position: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    Title,
+    Author,
+    Isbn,
+    Year,
+    Subject,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryValue {
+    /// A bare word; `*` matches any run of characters and `?` exactly one.
+    Word(String),
+    Phrase(String),
+    /// `from..to`, where either end may be left open.
+    Range(Option<String>, Option<String>),
+}
+
+/// A query that could not be parsed or compiled; `position` is the 0-based character offset of the
+/// offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuerySyntaxError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl QuerySyntaxError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl QueryField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "title" => Some(Self::Title),
+            "author" => Some(Self::Author),
+            "isbn" => Some(Self::Isbn),
+            "year" => Some(Self::Year),
+            "subject" => Some(Self::Subject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Minus,
+    Field(String),
+    Phrase(String),
+    Word(String),
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '"')
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, QuerySyntaxError> {
+    let chars = query.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        match chars[index] {
+            c if c.is_whitespace() => index += 1,
+            '(' => {
+                tokens.push((Token::LeftParen, start));
+                index += 1;
+            }
+            ')' => {
+                tokens.push((Token::RightParen, start));
+                index += 1;
+            }
+            '"' => {
+                let end = chars[start + 1..].iter().position(|c| *c == '"');
+                let Some(end) = end else {
+                    return Err(QuerySyntaxError::new(start, "unterminated phrase"));
+                };
+                let phrase = chars[start + 1..start + 1 + end].iter().collect();
+                tokens.push((Token::Phrase(phrase), start));
+                index = start + end + 2;
+            }
+            '-' if chars
+                .get(index + 1)
+                .is_some_and(|c| is_word_char(*c) || *c == '"' || *c == '(') =>
+            {
+                tokens.push((Token::Minus, start));
+                index += 1;
+            }
+            _ => {
+                while index < chars.len() && is_word_char(chars[index]) {
+                    if chars[index] == ':' && chars[start..index].iter().all(|c| c.is_alphabetic())
+                    {
+                        break;
+                    }
+                    index += 1;
+                }
+                let word = chars[start..index].iter().collect::<String>();
+                if chars.get(index) == Some(&':') {
+                    if word.is_empty() {
+                        return Err(QuerySyntaxError::new(
+                            start,
+                            "expected a field name before `:`",
+                        ));
+                    }
+                    tokens.push((Token::Field(word), start));
+                    index += 1;
+                } else {
+                    tokens.push((Token::Word(word), start));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// How many groups and negations may enclose a term. The parser, the compiler and dropping the
+/// tree all recurse once per level, so this keeps hostile queries from exhausting the stack.
+const MAX_QUERY_DEPTH: usize = 32;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+    /// Groups and negations currently open.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    /// Runs `parse` one nesting level deeper, refusing to go past `MAX_QUERY_DEPTH`.
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: impl FnOnce(&mut Self) -> Result<QueryNode, QuerySyntaxError>,
+    ) -> Result<QueryNode, QuerySyntaxError> {
+        if self.depth == MAX_QUERY_DEPTH {
+            return Err(QuerySyntaxError::new(position, "query nests too deeply"));
+        }
+        self.depth += 1;
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn parse_or(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut nodes = vec![self.parse_and()?];
+        while self.peek_keyword("OR") {
+            self.next();
+            nodes.push(self.parse_and()?);
+        }
+        Ok(if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            QueryNode::Or(nodes)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut nodes = vec![self.parse_unary()?];
+        loop {
+            if self.peek_keyword("AND") {
+                self.next();
+            } else if self.peek().is_none()
+                || self.peek_keyword("OR")
+                || self.peek() == Some(&Token::RightParen)
+            {
+                break;
+            }
+            // Adjacent terms without an operator are implicitly ANDed.
+            nodes.push(self.parse_unary()?);
+        }
+        Ok(if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            QueryNode::And(nodes)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        if self.peek() == Some(&Token::Minus) || self.peek_keyword("NOT") {
+            let position = self.position();
+            self.next();
+            let node = self.nested(position, Self::parse_unary)?;
+            return Ok(QueryNode::Not(Box::new(node)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let position = self.position();
+        match self.next() {
+            Some((Token::LeftParen, _)) => {
+                let node = self.nested(position, Self::parse_or)?;
+                match self.next() {
+                    Some((Token::RightParen, _)) => Ok(node),
+                    _ => Err(QuerySyntaxError::new(
+                        self.end,
+                        format!("expected `)` to close the group opened at position {position}"),
+                    )),
+                }
+            }
+            Some((Token::Field(name), _)) => {
+                let Some(field) = QueryField::from_name(&name) else {
+                    return Err(QuerySyntaxError::new(
+                        position,
+                        format!("unknown field `{name}`"),
+                    ));
+                };
+                let value_position = self.position();
+                let value = match self.next() {
+                    Some((Token::Phrase(phrase), _)) => QueryValue::Phrase(phrase),
+                    Some((Token::Word(word), _))
+                        if !matches!(word.as_str(), "AND" | "OR" | "NOT") =>
+                    {
+                        parse_word(&word, value_position)?
+                    }
+                    _ => {
+                        return Err(QuerySyntaxError::new(
+                            value_position,
+                            format!("expected a value after `{name}:`"),
+                        ))
+                    }
+                };
+                Ok(QueryNode::Term {
+                    field: Some(field),
+                    value,
+                    position,
+                })
+            }
+            Some((Token::Phrase(phrase), _)) => Ok(QueryNode::Term {
+                field: None,
+                value: QueryValue::Phrase(phrase),
+                position,
+            }),
+            Some((Token::Word(word), _)) if !matches!(word.as_str(), "AND" | "OR" | "NOT") => {
+                Ok(QueryNode::Term {
+                    field: None,
+                    value: parse_word(&word, position)?,
+                    position,
+                })
+            }
+            Some((Token::Word(word), _)) => Err(QuerySyntaxError::new(
+                position,
+                format!("unexpected `{word}`"),
+            )),
+            Some((Token::RightParen, _)) => Err(QuerySyntaxError::new(position, "unexpected `)`")),
+            Some((Token::Minus, _)) => Err(QuerySyntaxError::new(position, "unexpected `-`")),
+            None => Err(QuerySyntaxError::new(position, "unexpected end of query")),
+        }
+    }
+}
+
+fn parse_word(word: &str, position: usize) -> Result<QueryValue, QuerySyntaxError> {
+    let Some((from, to)) = word.split_once("..") else {
+        return Ok(QueryValue::Word(word.to_string()));
+    };
+    let bound = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    if from.is_empty() && to.is_empty() {
+        return Err(QuerySyntaxError::new(
+            position,
+            "a range needs at least one bound",
+        ));
+    }
+    Ok(QueryValue::Range(bound(from), bound(to)))
+}
+
+/// Parses the catalog query language. A blank query matches every book.
+pub fn parse_query(query: &str) -> Result<QueryNode, QuerySyntaxError> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        index: 0,
+        end: query.chars().count(),
+        depth: 0,
+    };
+    if parser.peek().is_none() {
+        return Ok(QueryNode::And(vec![]));
+    }
+
+    let node = parser.parse_or()?;
+    if let Some((token, position)) = parser.next() {
+        let message = match token {
+            Token::RightParen => "unexpected `)`".to_string(),
+            token => format!("unexpected {token:?}"),
+        };
+        return Err(QuerySyntaxError::new(position, message));
+    }
+    Ok(node)
+}
+
+fn has_wildcard(word: &str) -> bool {
+    word.contains(['*', '?'])
+}
+
+/// Converts `*` and `?` to their LIKE equivalents, escaping everything else.
+fn wildcard_pattern(word: &str) -> String {
+    word.chars()
+        .map(|c| match c {
+            '*' => "%".to_string(),
+            '?' => "_".to_string(),
+            c => escape_like(&c.to_string()),
+        })
+        .collect()
+}
+
+impl QueryNode {
+    /// Compiles the query into a condition on the `book` table.
+    pub fn to_condition(&self) -> Result<Condition, QuerySyntaxError> {
+        match self {
+            Self::And(nodes) => nodes.iter().try_fold(Condition::all(), |condition, node| {
+                Ok(condition.add(node.to_condition()?))
+            }),
+            Self::Or(nodes) => nodes.iter().try_fold(Condition::any(), |condition, node| {
+                Ok(condition.add(node.to_condition()?))
+            }),
+            Self::Not(node) => Ok(node.to_condition()?.not()),
+            Self::Term {
+                field: Some(field),
+                value,
+                position,
+            } => Ok(Condition::all().add(term_condition(*field, value, *position)?)),
+            Self::Term {
+                field: None,
+                value,
+                position,
+            } => [QueryField::Title, QueryField::Author, QueryField::Isbn]
+                .into_iter()
+                .try_fold(Condition::any(), |condition, field| {
+                    Ok(condition.add(term_condition(field, value, *position)?))
+                }),
+        }
+    }
+}
+
+fn parse_year(value: &str, position: usize) -> Result<u64, QuerySyntaxError> {
+    value
+        .parse()
+        .map_err(|_| QuerySyntaxError::new(position, format!("`{value}` is not a year")))
+}
+
+fn term_condition(
+    field: QueryField,
+    value: &QueryValue,
+    position: usize,
+) -> Result<SimpleExpr, QuerySyntaxError> {
+    if field == QueryField::Year {
+        let year = book::Column::PublicationYear;
+        return match value {
+            QueryValue::Word(word) if has_wildcard(word) => Err(QuerySyntaxError::new(
+                position,
+                "wildcards are not supported for `year`",
+            )),
+            QueryValue::Word(word) | QueryValue::Phrase(word) => {
+                Ok(year.eq(parse_year(word, position)?))
+            }
+            QueryValue::Range(from, to) => {
+                let from = from
+                    .as_deref()
+                    .map(|from| parse_year(from, position))
+                    .transpose()?;
+                let to = to
+                    .as_deref()
+                    .map(|to| parse_year(to, position))
+                    .transpose()?;
+                Ok(match (from, to) {
+                    (Some(from), Some(to)) => year.between(from, to),
+                    (Some(from), None) => year.gte(from),
+                    (None, Some(to)) => year.lte(to),
+                    (None, None) => unreachable!("ranges always have a bound"),
+                })
+            }
+        };
+    }
+
+    let column = match field {
+        QueryField::Title => Expr::col((book::Entity, book::Column::Title)),
+        QueryField::Author => Expr::col((contributor::Entity, contributor::Column::Name)),
+        QueryField::Isbn => Expr::col((book::Entity, book::Column::Isbn)),
+        QueryField::Subject => Expr::col((book_subject::Entity, book_subject::Column::Subject)),
+        QueryField::Year => unreachable!("handled above"),
+    };
+    // ISBNs are stored canonically, so typed separators and ISBN-10s are normalized first.
+    let normalize = |value: &str| match field {
+        QueryField::Isbn => normalize_isbn(value).unwrap_or_else(|| strip_isbn(value)),
+        _ => value.to_string(),
+    };
+
+    let expr = match value {
+        // An ISBN wildcard is anchored so `978057*` means "starts with"; text wildcards match
+        // anywhere, like plain words do.
+        QueryValue::Word(word) if has_wildcard(word) => {
+            let pattern = wildcard_pattern(&normalize(word));
+            let pattern = match field {
+                QueryField::Isbn => pattern,
+                _ => format!("%{pattern}%"),
+            };
+            column.like(LikeExpr::new(pattern).escape('\\'))
+        }
+        QueryValue::Word(word) | QueryValue::Phrase(word) => {
+            column.like(LikeExpr::new(format!("%{}%", escape_like(&normalize(word)))).escape('\\'))
+        }
+        QueryValue::Range(from, to) => {
+            let from = from.as_deref().map(normalize);
+            let to = to.as_deref().map(normalize);
+            match (from, to) {
+                (Some(from), Some(to)) => column.between(from, to),
+                (Some(from), None) => column.gte(from),
+                (None, Some(to)) => column.lte(to),
+                (None, None) => unreachable!("ranges always have a bound"),
+            }
+        }
+    };
+
+    Ok(match field {
+        QueryField::Author => credited_books(Some(expr), None),
+        QueryField::Subject => subject_books(expr),
+        _ => expr,
+    })
+}
+
+#[cfg(test)]
+fn term(field: Option<QueryField>, value: QueryValue, position: usize) -> QueryNode {
+    QueryNode::Term {
+        field,
+        value,
+        position,
+    }
+}
+
+#[test]
+fn test_parse_query() {
+    let word = |word: &str| QueryValue::Word(word.to_string());
+    assert_eq!(
+        parse_query(
+            r#"title:"hitchhiker" AND (author:adams OR author:pratchett) year:1979..1990 -isbn:978057*"#
+        ),
+        Ok(QueryNode::And(vec![
+            term(
+                Some(QueryField::Title),
+                QueryValue::Phrase("hitchhiker".to_string()),
+                0
+            ),
+            QueryNode::Or(vec![
+                term(Some(QueryField::Author), word("adams"), 24),
+                term(Some(QueryField::Author), word("pratchett"), 40),
+            ]),
+            term(
+                Some(QueryField::Year),
+                QueryValue::Range(Some("1979".to_string()), Some("1990".to_string())),
+                58
+            ),
+            QueryNode::Not(Box::new(term(Some(QueryField::Isbn), word("978057*"), 75))),
+        ]))
+    );
+    assert_eq!(
+        parse_query("NOT dune year:..1970"),
+        Ok(QueryNode::And(vec![
+            QueryNode::Not(Box::new(term(None, word("dune"), 4))),
+            term(
+                Some(QueryField::Year),
+                QueryValue::Range(None, Some("1970".to_string())),
+                9
+            ),
+        ]))
+    );
+    assert_eq!(parse_query("  "), Ok(QueryNode::And(vec![])));
+}
+
+#[test]
+fn test_parse_query_errors() {
+    let position = |query: &str| parse_query(query).unwrap_err().position;
+    assert_eq!(position("title:\"dune"), 6);
+    assert_eq!(position("(author:adams OR dune"), 21);
+    assert_eq!(position("dune )"), 5);
+    assert_eq!(position("publisher:tor"), 0);
+    assert_eq!(position("title: AND dune"), 7);
+    assert_eq!(position("dune OR"), 7);
+    assert_eq!(position("year:.."), 5);
+
+    let nested = |depth| format!("{}dune{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(parse_query(&nested(MAX_QUERY_DEPTH)).is_ok());
+    let error = parse_query(&nested(MAX_QUERY_DEPTH + 1)).unwrap_err();
+    assert_eq!(error.message, "query nests too deeply");
+    assert_eq!(error.position, MAX_QUERY_DEPTH);
+    assert_eq!(position(&"(".repeat(100_000)), MAX_QUERY_DEPTH);
+    assert_eq!(
+        position(&format!("{}dune", "-".repeat(100_000))),
+        MAX_QUERY_DEPTH
+    );
+    assert_eq!(
+        position(&"NOT ".repeat(MAX_QUERY_DEPTH + 1)),
+        4 * MAX_QUERY_DEPTH
+    );
+    assert_eq!(
+        parse_query("year:nineteen")
+            .unwrap()
+            .to_condition()
+            .unwrap_err()
+            .position,
+        0
+    );
+}
+
+#[test]
+fn test_query_condition() {
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    let sql = |query| {
+        book::Entity::find()
+            .filter(parse_query(query).unwrap().to_condition().unwrap())
+            .build(DbBackend::MySql)
+            .to_string()
+    };
+
+    let compiled = sql(
+        r#"title:"hitchhiker" (author:adams OR author:pratchett) year:1979..1990 -isbn:978057*"#,
+    );
+    assert!(compiled.contains("`book`.`title` LIKE '%hitchhiker%'"));
+    assert!(compiled.contains("`contributor`.`name` LIKE '%adams%'"));
+    assert!(compiled.contains(" OR "));
+    assert!(compiled.contains("`book`.`publication_year` BETWEEN 1979 AND 1990"));
+    assert!(compiled.contains("NOT `book`.`isbn` LIKE '978057%'"));
+
+    let anywhere = sql("h?tch*");
+    assert!(anywhere.contains("`book`.`title` LIKE '%h_tch%%'"));
+    assert!(anywhere.contains("`book`.`isbn` LIKE 'h_tch%'"));
+}
@@ -0,0 +1,194 @@
+use sea_orm::{
+    sea_query::{Expr, LikeExpr, Query, SimpleExpr},
+    ColumnTrait, Condition,
+};
+
+use crate::{
+    model::request::search::BookSearch,
+    orm::{
+        book::{self, Book},
+        book_contributor::{self, ContributorRole},
+        book_subject, contributor,
+    },
+};
+
+use super::{
+    facet::available_condition, normalize_isbn, parse_query, strip_isbn, Facets, LibraryErrorStatus,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct BookPage {
+    pub books: Vec<Book>,
+    /// Every book matching the search, across all pages.
+    pub total: u64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    /// "Did you mean" titles and names, only offered by fuzzy searches without exact hits.
+    pub suggestions: Vec<String>,
+    pub facets: Option<Facets>,
+}
+
+/// Escapes `%`, `_` and the escape character itself so user input only ever matches literally.
+pub(super) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn like_contains(value: &str) -> LikeExpr {
+    LikeExpr::new(format!("%{}%", escape_like(value))).escape('\\')
+}
+
+/// Books crediting a contributor whose name satisfies `name`, optionally only in `role`.
+pub(super) fn credited_books(
+    name: Option<SimpleExpr>,
+    role: Option<ContributorRole>,
+) -> SimpleExpr {
+    let mut credited = Query::select();
+    credited
+        .column((book_contributor::Entity, book_contributor::Column::Book))
+        .from(book_contributor::Entity)
+        .inner_join(
+            contributor::Entity,
+            Expr::col((contributor::Entity, contributor::Column::Id)).equals((
+                book_contributor::Entity,
+                book_contributor::Column::Contributor,
+            )),
+        );
+    if let Some(name) = name {
+        credited.and_where(name);
+    }
+    if let Some(role) = role {
+        credited.and_where(book_contributor::Column::Role.eq(role));
+    }
+    book::Column::Id.in_subquery(credited.to_owned())
+}
+
+/// Books with a subject heading satisfying `subject`.
+pub(super) fn subject_books(subject: SimpleExpr) -> SimpleExpr {
+    book::Column::Id.in_subquery(
+        Query::select()
+            .column(book_subject::Column::Book)
+            .from(book_subject::Entity)
+            .and_where(subject)
+            .to_owned(),
+    )
+}
+
+/// Translates a `BookSearch` into a condition on the `book` table.
+pub fn search_condition(search: &BookSearch) -> Result<Condition, LibraryErrorStatus> {
+    let mut condition = Condition::all();
+    if let Some(title) = &search.title {
+        condition = condition
+            .add(Expr::col((book::Entity, book::Column::Title)).like(like_contains(title)));
+    }
+    if search.author.is_some() || search.role.is_some() {
+        let name = search.author.as_ref().map(|author| {
+            Expr::col((contributor::Entity, contributor::Column::Name)).like(like_contains(author))
+        });
+        condition = condition.add(credited_books(name, search.role));
+    }
+    if let Some(isbn) = &search.isbn {
+        let isbn = normalize_isbn(isbn).unwrap_or_else(|| strip_isbn(isbn));
+        condition =
+            condition.add(Expr::col((book::Entity, book::Column::Isbn)).like(like_contains(&isbn)));
+    }
+    if let Some(subject) = &search.subject {
+        condition = condition.add(subject_books(book_subject::Column::Subject.eq(subject)));
+    }
+    if let Some(decade) = search.decade {
+        let start = decade - decade % 10;
+        condition =
+            condition.add(book::Column::PublicationYear.between(start, start.saturating_add(9)));
+    }
+    if let Some(available) = search.available {
+        condition = condition.add(available_condition(available));
+    }
+    if let Some(query) = &search.query {
+        let query = parse_query(query).map_err(LibraryErrorStatus::QueryInvalid)?;
+        condition = condition.add(
+            query
+                .to_condition()
+                .map_err(LibraryErrorStatus::QueryInvalid)?,
+        );
+    }
+    Ok(condition)
+}
+
+#[test]
+fn test_search_condition() {
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    let sql = |search: BookSearch| {
+        book::Entity::find()
+            .filter(search_condition(&search).expect("search should compile"))
+            .build(DbBackend::MySql)
+            .to_string()
+    };
+
+    let everything = sql(BookSearch {
+        title: None,
+        author: None,
+        role: None,
+        isbn: None,
+        fuzzy: None,
+        subject: None,
+        decade: None,
+        available: None,
+        facets: None,
+        query: None,
+    });
+    assert!(!everything.contains("LIKE"));
+
+    let filtered = sql(BookSearch {
+        title: Some("100%_sure".to_string()),
+        author: Some("Adams".to_string()),
+        role: Some(ContributorRole::Author),
+        isbn: Some("0-575-07484-1".to_string()),
+        fuzzy: None,
+        subject: None,
+        decade: None,
+        available: None,
+        facets: None,
+        query: None,
+    });
+    assert!(filtered.contains(r"`book`.`title` LIKE '%100\\%\\_sure%' ESCAPE '\\'"));
+    assert!(filtered.contains("`contributor`.`name` LIKE '%Adams%'"));
+    assert!(filtered.contains("`book_contributor`.`role` = 'author'"));
+    assert!(filtered.contains("`book`.`isbn` LIKE '%9780575074842%'"));
+
+    let faceted = sql(BookSearch {
+        title: None,
+        author: None,
+        role: None,
+        isbn: None,
+        fuzzy: None,
+        subject: Some("Science fiction".to_string()),
+        decade: Some(1979),
+        available: Some(false),
+        facets: Some(true),
+        query: None,
+    });
+    assert!(faceted.contains("`subject` = 'Science fiction'"));
+    assert!(faceted.contains("`book`.`publication_year` BETWEEN 1970 AND 1979"));
+    assert!(faceted.contains("`book`.`id` NOT IN (SELECT `book` FROM `copy`"));
+
+    let last_decade = sql(BookSearch {
+        title: None,
+        author: None,
+        role: None,
+        isbn: None,
+        fuzzy: None,
+        subject: None,
+        decade: Some(u64::MAX),
+        available: None,
+        facets: None,
+        query: None,
+    });
+    let start = u64::MAX - u64::MAX % 10;
+    assert!(last_decade.contains(&format!(
+        "`book`.`publication_year` BETWEEN {start} AND {}",
+        u64::MAX
+    )));
+}
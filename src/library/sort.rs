@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use deunicode::deunicode;
+use sea_orm::{ColumnTrait, Condition};
+use serde_json::Value;
+
+use crate::{
+    model::request::sort::{Sort, SortField},
+    orm::book::{self, Book},
+};
+
+use super::{split_author, Cursor, LibraryErrorStatus};
+
+// Matches the width of the `title_sort` and `author_sort` columns.
+const SORT_KEY_LEN: usize = 255;
+// Dropped from the front of titles, but only when more words follow: "A" still sorts under "a".
+pub(super) const LEADING_ARTICLES: [&str; 18] = [
+    "the", "a", "an", "der", "die", "das", "ein", "eine", "le", "la", "les", "un", "une", "el",
+    "los", "las", "il", "lo",
+];
+
+/// Lower-cases, folds accents to ASCII and drops punctuation so "Gödel, Escher" sorts as "godel escher".
+fn fold(text: &str) -> String {
+    let folded = deunicode(text)
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '\'')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>();
+    folded
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(SORT_KEY_LEN)
+        .collect()
+}
+
+/// The stored key titles are sorted by: "The Hobbit" under "hobbit", "L'Étranger" under "etranger".
+pub fn title_sort_key(title: &str) -> String {
+    // The elided "L'" is glued to the next word, so it never shows up as a word of its own.
+    if let Some(rest) = title
+        .strip_prefix("L'")
+        .or_else(|| title.strip_prefix("l'"))
+    {
+        if !fold(rest).is_empty() {
+            return fold(rest);
+        }
+    }
+    let folded = fold(title);
+    match folded.split_once(' ') {
+        Some((article, rest)) if LEADING_ARTICLES.contains(&article) => rest.to_string(),
+        _ => folded,
+    }
+}
+
+/// The stored key authors are sorted by: the first credited name, surname first, so "Douglas
+/// Adams; Eoin Colfer" sorts under "adams douglas". Names already written "Surname, Given" are kept.
+pub fn author_sort_key(author: &str) -> String {
+    let Some(first) = split_author(author).into_iter().next() else {
+        return String::new();
+    };
+    if first.contains(',') {
+        return fold(&first);
+    }
+    match first.rsplit_once(' ') {
+        Some((given, surname)) => fold(&format!("{surname} {given}")),
+        None => fold(&first),
+    }
+}
+
+impl Book {
+    /// Recomputes `title_sort` and `author_sort`; call after the title or credits change.
+    pub(super) fn refresh_sort_keys(&mut self) {
+        self.title_sort = title_sort_key(&self.title);
+        self.author_sort = author_sort_key(&self.author);
+    }
+}
+
+pub(super) fn sort_column(field: SortField) -> book::Column {
+    match field {
+        SortField::Title => book::Column::TitleSort,
+        SortField::Author => book::Column::AuthorSort,
+        SortField::PublicationYear => book::Column::PublicationYear,
+        SortField::CreatedAt => book::Column::CreatedAt,
+        SortField::UpdatedAt => book::Column::UpdatedAt,
+    }
+}
+
+/// The value a cursor records for `book` so the next page can resume right after it.
+pub(super) fn sort_value(sort: Option<Sort>, book: &Book) -> Value {
+    match sort.map(|sort| sort.field) {
+        None => book.id.into(),
+        Some(SortField::Title) => book.title_sort.clone().into(),
+        Some(SortField::Author) => book.author_sort.clone().into(),
+        Some(SortField::PublicationYear) => book.publication_year.into(),
+        Some(SortField::CreatedAt) => book.created_at.to_rfc3339().into(),
+        Some(SortField::UpdatedAt) => book.updated_at.to_rfc3339().into(),
+    }
+}
+
+fn key_of(field: SortField, key: &Value) -> Result<sea_orm::Value, LibraryErrorStatus> {
+    let value = match field {
+        SortField::Title | SortField::Author => key.as_str().map(|key| key.to_string().into()),
+        SortField::PublicationYear => key.as_u64().map(Into::into),
+        SortField::CreatedAt | SortField::UpdatedAt => key
+            .as_str()
+            .and_then(|key| DateTime::parse_from_rfc3339(key).ok())
+            .map(|key| key.with_timezone(&Utc).into()),
+    };
+    value.ok_or(LibraryErrorStatus::CursorInvalid)
+}
+
+/// Books strictly past `cursor` when scanning in `descending` order of (sort key, id).
+pub(super) fn keyset_condition(
+    sort: Option<Sort>,
+    cursor: &Cursor,
+    descending: bool,
+) -> Result<Condition, LibraryErrorStatus> {
+    let past_id = if descending {
+        book::Column::Id.lt(cursor.id)
+    } else {
+        book::Column::Id.gt(cursor.id)
+    };
+    let Some(sort) = sort else {
+        return Ok(Condition::all().add(past_id));
+    };
+
+    let column = sort_column(sort.field);
+    let key = key_of(sort.field, &cursor.key)?;
+    let past_key = if descending {
+        column.lt(key.clone())
+    } else {
+        column.gt(key.clone())
+    };
+    Ok(Condition::any()
+        .add(past_key)
+        .add(Condition::all().add(column.eq(key)).add(past_id)))
+}
+
+#[test]
+fn test_sort_keys() {
+    assert_eq!(title_sort_key("The Hobbit"), "hobbit");
+    assert_eq!(title_sort_key("Der Zauberberg"), "zauberberg");
+    assert_eq!(title_sort_key("Le Petit Prince"), "petit prince");
+    assert_eq!(title_sort_key("L'Étranger"), "etranger");
+    assert_eq!(title_sort_key("Gödel, Escher, Bach"), "godel escher bach");
+    assert_eq!(title_sort_key("A"), "a");
+    assert_eq!(
+        title_sort_key("Theory of Everything"),
+        "theory of everything"
+    );
+    assert_eq!(
+        title_sort_key("The Hitchhiker's Guide to the Galaxy"),
+        "hitchhikers guide to the galaxy"
+    );
+    assert!(title_sort_key("Émile") < title_sort_key("Fahrenheit 451"));
+
+    assert_eq!(
+        author_sort_key("Douglas Adams; Eoin Colfer"),
+        "adams douglas"
+    );
+    assert_eq!(author_sort_key("Tolkien, J. R. R."), "tolkien j r r");
+    assert_eq!(author_sort_key("Homer"), "homer");
+    assert_eq!(author_sort_key(""), "");
+}
+
+#[test]
+fn test_keyset_condition() {
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    let sql = |sort: Option<Sort>, cursor: Cursor, descending| {
+        book::Entity::find()
+            .filter(keyset_condition(sort, &cursor, descending).expect("cursor should compile"))
+            .build(DbBackend::MySql)
+            .to_string()
+    };
+
+    let by_id = sql(None, Cursor::after(7.into(), 7, None), false);
+    assert!(by_id.ends_with("WHERE `book`.`id` > 7"));
+
+    let sort = Some("-title".parse().unwrap());
+    let by_title = sql(sort, Cursor::after("hobbit".into(), 7, sort), true);
+    assert!(by_title.contains(
+        "`book`.`title_sort` < 'hobbit' OR (`book`.`title_sort` = 'hobbit' AND `book`.`id` < 7)"
+    ));
+
+    let sort = Some("publication_year".parse().unwrap());
+    assert!(matches!(
+        keyset_condition(sort, &Cursor::after("1979".into(), 7, sort), false),
+        Err(LibraryErrorStatus::CursorInvalid)
+    ));
+}
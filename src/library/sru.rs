@@ -0,0 +1,502 @@
+use log::warn;
+use quick_xml::escape::escape;
+use sea_orm::{
+    DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use crate::orm::book::{self, Book};
+
+use super::{
+    cql::parse_cql,
+    dublin_core::{dc_elements, DC_NAMESPACE},
+    Library, LibraryErrorStatus,
+};
+
+const DEFAULT_MAXIMUM_RECORDS: u64 = 10;
+const DC_SCHEMA: &str = "info:srw/schema/1/dc-v1.1";
+const EXPLAIN_SCHEMA: &str = "http://explain.z3950.org/dtd/2.0/";
+const DIAGNOSTIC_URI: &str = "info:srw/diagnostic/1/";
+
+// SRU diagnostics raised outside of CQL parsing.
+const UNSUPPORTED_OPERATION: u32 = 4;
+const UNSUPPORTED_VERSION: u32 = 5;
+const UNSUPPORTED_PARAMETER_VALUE: u32 = 6;
+const MANDATORY_PARAMETER_MISSING: u32 = 7;
+const QUERY_SYNTAX_ERROR: u32 = 10;
+const FIRST_RECORD_OUT_OF_RANGE: u32 = 61;
+const UNKNOWN_SCHEMA: u32 = 66;
+const UNSUPPORTED_RECORD_PACKING: u32 = 71;
+
+/// Describes this server in `explain` responses and limits what a client may request.
+#[derive(Debug, Clone)]
+pub struct SruServer {
+    pub name: String,
+    /// The public URL of the server, without a trailing slash.
+    pub base_url: String,
+    /// The largest `maximumRecords` honored; larger requests are quietly capped.
+    pub max_records: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SruVersion {
+    /// SRU 1.1 and 1.2 share their response schema.
+    V1(&'static str),
+    V2,
+}
+
+impl SruVersion {
+    fn number(self) -> &'static str {
+        match self {
+            Self::V1(number) => number,
+            Self::V2 => "2.0",
+        }
+    }
+
+    fn namespace(self) -> &'static str {
+        match self {
+            Self::V1(_) => "http://www.loc.gov/zing/srw/",
+            Self::V2 => "http://docs.oasis-open.org/ns/search-ws/sruResponse",
+        }
+    }
+
+    fn diagnostic_namespace(self) -> &'static str {
+        match self {
+            Self::V1(_) => "http://www.loc.gov/zing/srw/diagnostic/",
+            Self::V2 => "http://docs.oasis-open.org/ns/search-ws/diagnostic",
+        }
+    }
+
+    /// SRU 2.0 renamed `recordPacking` to `recordXMLEscaping`.
+    fn packing_element(self) -> &'static str {
+        match self {
+            Self::V1(_) => "recordPacking",
+            Self::V2 => "recordXMLEscaping",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Diagnostic {
+    code: u32,
+    details: String,
+}
+
+impl Diagnostic {
+    fn new(code: u32, details: impl Into<String>) -> Self {
+        Self {
+            code,
+            details: details.into(),
+        }
+    }
+
+    fn to_xml(&self, version: SruVersion) -> String {
+        format!(
+            "<diag:diagnostic xmlns:diag=\"{}\"><diag:uri>{DIAGNOSTIC_URI}{}</diag:uri>\
+             <diag:details>{}</diag:details></diag:diagnostic>",
+            version.diagnostic_namespace(),
+            self.code,
+            escape(&self.details)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SruOperation {
+    Explain,
+    SearchRetrieve,
+}
+
+/// The validated parameters of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SruArguments {
+    operation: SruOperation,
+    query: Option<String>,
+    start_record: u64,
+    maximum_records: u64,
+    /// Whether records are embedded as XML rather than as an escaped string.
+    xml_packing: bool,
+}
+
+fn parameter<'a>(pairs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Picks the version to answer in. A request without `operation` follows SRU 2.0, which dropped
+/// the parameter; clients of older versions always send it.
+fn sru_version(pairs: &[(String, String)]) -> Result<SruVersion, Diagnostic> {
+    match parameter(pairs, "version") {
+        Some("1.1") => Ok(SruVersion::V1("1.1")),
+        Some("1.2") => Ok(SruVersion::V1("1.2")),
+        Some("2.0") => Ok(SruVersion::V2),
+        Some(version) => Err(Diagnostic::new(UNSUPPORTED_VERSION, version)),
+        None if parameter(pairs, "operation").is_some() => Ok(SruVersion::V1("1.2")),
+        None => Ok(SruVersion::V2),
+    }
+}
+
+impl SruArguments {
+    fn parse(
+        pairs: &[(String, String)],
+        version: SruVersion,
+        server: &SruServer,
+    ) -> Result<Self, Diagnostic> {
+        let query = parameter(pairs, "query").map(str::to_string);
+        let operation = match (parameter(pairs, "operation"), version) {
+            (Some("searchRetrieve"), _) => SruOperation::SearchRetrieve,
+            (Some("explain"), _) => SruOperation::Explain,
+            (Some(operation), _) => return Err(Diagnostic::new(UNSUPPORTED_OPERATION, operation)),
+            (None, SruVersion::V1(_)) => {
+                return Err(Diagnostic::new(MANDATORY_PARAMETER_MISSING, "operation"))
+            }
+            (None, SruVersion::V2) if query.is_some() => SruOperation::SearchRetrieve,
+            (None, SruVersion::V2) => SruOperation::Explain,
+        };
+        if operation == SruOperation::SearchRetrieve && query.is_none() {
+            return Err(Diagnostic::new(MANDATORY_PARAMETER_MISSING, "query"));
+        }
+
+        let number = |name: &str, default: u64| match parameter(pairs, name) {
+            Some(value) => value
+                .parse::<u64>()
+                .map_err(|_| Diagnostic::new(UNSUPPORTED_PARAMETER_VALUE, name)),
+            None => Ok(default),
+        };
+        let start_record = number("startRecord", 1)?;
+        if start_record == 0 {
+            return Err(Diagnostic::new(UNSUPPORTED_PARAMETER_VALUE, "startRecord"));
+        }
+        let maximum_records = number("maximumRecords", DEFAULT_MAXIMUM_RECORDS)?;
+
+        if let Some(schema) = parameter(pairs, "recordSchema") {
+            if schema != "dc" && schema != DC_SCHEMA {
+                return Err(Diagnostic::new(UNKNOWN_SCHEMA, schema));
+            }
+        }
+        let packing = parameter(pairs, version.packing_element()).unwrap_or("xml");
+        let xml_packing = match packing {
+            "xml" => true,
+            "string" => false,
+            _ => return Err(Diagnostic::new(UNSUPPORTED_RECORD_PACKING, packing)),
+        };
+
+        Ok(Self {
+            operation,
+            query,
+            start_record,
+            maximum_records: maximum_records.min(server.max_records),
+            xml_packing,
+        })
+    }
+}
+
+fn response(version: SruVersion, element: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <srw:{element} xmlns:srw=\"{}\"><srw:version>{}</srw:version>{body}</srw:{element}>\n",
+        version.namespace(),
+        version.number()
+    )
+}
+
+fn record(
+    version: SruVersion,
+    schema: &str,
+    xml_packing: bool,
+    data: &str,
+    position: Option<u64>,
+) -> String {
+    let packing = version.packing_element();
+    let data = if xml_packing {
+        data.to_string()
+    } else {
+        escape(data).into_owned()
+    };
+    let position = position
+        .map(|position| format!("<srw:recordPosition>{position}</srw:recordPosition>"))
+        .unwrap_or_default();
+    format!(
+        "<srw:record><srw:recordSchema>{schema}</srw:recordSchema>\
+         <srw:{packing}>{}</srw:{packing}><srw:recordData>{data}</srw:recordData>{position}\
+         </srw:record>",
+        if xml_packing { "xml" } else { "string" }
+    )
+}
+
+fn dc_record(server: &SruServer, book: &Book) -> String {
+    format!(
+        "<srw_dc:dc xmlns:srw_dc=\"info:srw/schema/1/dc-schema\" xmlns:dc=\"{DC_NAMESPACE}\">{}\
+         </srw_dc:dc>",
+        dc_elements(book, &server.base_url)
+    )
+}
+
+fn search_response(
+    version: SruVersion,
+    total: u64,
+    records: &str,
+    next: Option<u64>,
+    diagnostic: Option<Diagnostic>,
+) -> String {
+    let mut body = format!("<srw:numberOfRecords>{total}</srw:numberOfRecords>");
+    if !records.is_empty() {
+        body.push_str(&format!("<srw:records>{records}</srw:records>"));
+    }
+    if let Some(next) = next {
+        body.push_str(&format!(
+            "<srw:nextRecordPosition>{next}</srw:nextRecordPosition>"
+        ));
+    }
+    if let Some(diagnostic) = diagnostic {
+        body.push_str(&format!(
+            "<srw:diagnostics>{}</srw:diagnostics>",
+            diagnostic.to_xml(version)
+        ));
+    }
+    response(version, "searchRetrieveResponse", &body)
+}
+
+fn explain_response(version: SruVersion, server: &SruServer, xml_packing: bool) -> String {
+    let authority = server
+        .base_url
+        .split_once("://")
+        .map_or(server.base_url.as_str(), |(_, rest)| rest);
+    let (authority, path) = authority.split_once('/').unwrap_or((authority, ""));
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port),
+        None if server.base_url.starts_with("https") => (authority, "443"),
+        None => (authority, "80"),
+    };
+    let database = [path, "sru"]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let indexes = [
+        ("Title", "dc", "title"),
+        ("Creator", "dc", "creator"),
+        ("ISBN", "bath", "isbn"),
+        ("Publication year", "dc", "date"),
+        ("Subject", "dc", "subject"),
+        ("Title, creator or ISBN", "cql", "serverChoice"),
+    ]
+    .into_iter()
+    .map(|(title, set, name)| {
+        format!("<index><title>{title}</title><map><name set=\"{set}\">{name}</name></map></index>")
+    })
+    .collect::<String>();
+    let explain = format!(
+        "<explain xmlns=\"{EXPLAIN_SCHEMA}\">\
+         <serverInfo protocol=\"SRU\" version=\"{}\"><host>{}</host><port>{}</port>\
+         <database>{}</database></serverInfo>\
+         <databaseInfo><title>{}</title></databaseInfo>\
+         <indexInfo>\
+         <set name=\"cql\" identifier=\"info:srw/cql-context-set/1/cql-v1.2\"/>\
+         <set name=\"dc\" identifier=\"info:srw/cql-context-set/1/dc-v1.1\"/>\
+         <set name=\"bath\" identifier=\"http://zing.z3950.org/cql/bath/2.0/\"/>\
+         {indexes}</indexInfo>\
+         <schemaInfo><schema identifier=\"{DC_SCHEMA}\" name=\"dc\" sort=\"false\">\
+         <title>Dublin Core</title></schema></schemaInfo>\
+         <configInfo><default type=\"numberOfRecords\">{DEFAULT_MAXIMUM_RECORDS}</default>\
+         <setting type=\"maximumRecords\">{}</setting></configInfo>\
+         </explain>",
+        version.number(),
+        escape(host),
+        escape(port),
+        escape(&database),
+        escape(&server.name),
+        server.max_records
+    );
+    response(
+        version,
+        "explainResponse",
+        &record(version, EXPLAIN_SCHEMA, xml_packing, &explain, None),
+    )
+}
+
+impl Library {
+    /// Answers one SRU request given its raw parameters with a complete response document.
+    /// Diagnostics are part of the document; only failures of the library itself are `Err`.
+    pub async fn sru_response(
+        &self,
+        pairs: &[(String, String)],
+        server: &SruServer,
+        database: &DatabaseConnection,
+    ) -> Result<String, LibraryErrorStatus> {
+        let version = match sru_version(pairs) {
+            Ok(version) => version,
+            Err(diagnostic) => {
+                let version = SruVersion::V1("1.2");
+                return Ok(search_response(version, 0, "", None, Some(diagnostic)));
+            }
+        };
+        let arguments = match SruArguments::parse(pairs, version, server) {
+            Ok(arguments) => arguments,
+            Err(diagnostic) => return Ok(search_response(version, 0, "", None, Some(diagnostic))),
+        };
+        if arguments.operation == SruOperation::Explain {
+            return Ok(explain_response(version, server, arguments.xml_packing));
+        }
+
+        let query = arguments.query.as_deref().unwrap_or_default();
+        let condition = parse_cql(query)
+            .map_err(|error| Diagnostic::new(error.diagnostic, error.message))
+            .and_then(|node| {
+                node.to_condition()
+                    .map_err(|error| Diagnostic::new(QUERY_SYNTAX_ERROR, error.to_string()))
+            });
+        let condition = match condition {
+            Ok(condition) => condition,
+            Err(diagnostic) => return Ok(search_response(version, 0, "", None, Some(diagnostic))),
+        };
+
+        let query = book::Entity::find().filter(condition);
+        let db_result = query.clone().count(database).await;
+        if let Err(error) = &db_result {
+            warn!("failed to count books for SRU: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let total = db_result.unwrap();
+        if arguments.start_record > total && total > 0 {
+            let diagnostic = Diagnostic::new(
+                FIRST_RECORD_OUT_OF_RANGE,
+                arguments.start_record.to_string(),
+            );
+            return Ok(search_response(version, total, "", None, Some(diagnostic)));
+        }
+
+        let db_result = query
+            .order_by_asc(book::Column::Id)
+            .offset(arguments.start_record - 1)
+            .limit(arguments.maximum_records)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch books for SRU: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        let mut books = db_result.unwrap();
+        Self::load_book_details(&mut books, database).await?;
+
+        let records = books
+            .iter()
+            .zip(arguments.start_record..)
+            .map(|(book, position)| {
+                let data = dc_record(server, book);
+                record(
+                    version,
+                    DC_SCHEMA,
+                    arguments.xml_packing,
+                    &data,
+                    Some(position),
+                )
+            })
+            .collect::<String>();
+        let next = arguments.start_record + books.len() as u64;
+        let next = (next <= total && !books.is_empty()).then_some(next);
+        Ok(search_response(version, total, &records, next, None))
+    }
+}
+
+#[cfg(test)]
+fn server() -> SruServer {
+    SruServer {
+        name: "ABLE & friends".to_string(),
+        base_url: "https://library.example.org/catalog".to_string(),
+        max_records: 50,
+    }
+}
+
+#[test]
+fn test_sru_arguments() {
+    let parse = |query: &str| {
+        let pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap();
+        let version = sru_version(&pairs)?;
+        SruArguments::parse(&pairs, version, &server()).map(|arguments| (version, arguments))
+    };
+    let code = |query| parse(query).unwrap_err().code;
+
+    let (version, arguments) =
+        parse("operation=searchRetrieve&version=1.2&query=dune&maximumRecords=500").unwrap();
+    assert_eq!(version, SruVersion::V1("1.2"));
+    assert_eq!(arguments.operation, SruOperation::SearchRetrieve);
+    assert_eq!((arguments.start_record, arguments.maximum_records), (1, 50));
+
+    let (version, arguments) = parse("query=dune&startRecord=11&recordXMLEscaping=string").unwrap();
+    assert_eq!(version, SruVersion::V2);
+    assert_eq!(arguments.operation, SruOperation::SearchRetrieve);
+    assert_eq!(arguments.start_record, 11);
+    assert!(!arguments.xml_packing);
+    assert_eq!(parse("").unwrap().1.operation, SruOperation::Explain);
+
+    assert_eq!(code("version=3.0&query=dune"), UNSUPPORTED_VERSION);
+    assert_eq!(
+        code("operation=scan&scanClause=dune"),
+        UNSUPPORTED_OPERATION
+    );
+    assert_eq!(code("version=1.2&query=dune"), MANDATORY_PARAMETER_MISSING);
+    assert_eq!(
+        code("operation=searchRetrieve"),
+        MANDATORY_PARAMETER_MISSING
+    );
+    assert_eq!(
+        code("query=dune&startRecord=0"),
+        UNSUPPORTED_PARAMETER_VALUE
+    );
+    assert_eq!(
+        code("query=dune&maximumRecords=lots"),
+        UNSUPPORTED_PARAMETER_VALUE
+    );
+    assert_eq!(code("query=dune&recordSchema=marcxml"), UNKNOWN_SCHEMA);
+    assert_eq!(
+        code("operation=searchRetrieve&query=dune&recordPacking=json"),
+        UNSUPPORTED_RECORD_PACKING
+    );
+}
+
+#[test]
+fn test_sru_responses() {
+    let version = SruVersion::V1("1.2");
+    let diagnostic = Diagnostic::new(QUERY_SYNTAX_ERROR, "unexpected <end>");
+    assert_eq!(
+        search_response(version, 0, "", None, Some(diagnostic)),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <srw:searchRetrieveResponse xmlns:srw=\"http://www.loc.gov/zing/srw/\">\
+         <srw:version>1.2</srw:version><srw:numberOfRecords>0</srw:numberOfRecords>\
+         <srw:diagnostics><diag:diagnostic xmlns:diag=\"http://www.loc.gov/zing/srw/diagnostic/\">\
+         <diag:uri>info:srw/diagnostic/1/10</diag:uri><diag:details>unexpected &lt;end&gt;\
+         </diag:details></diag:diagnostic></srw:diagnostics></srw:searchRetrieveResponse>\n"
+    );
+
+    let escaped = record(
+        SruVersion::V2,
+        DC_SCHEMA,
+        false,
+        "<dc:title>A</dc:title>",
+        Some(3),
+    );
+    assert_eq!(
+        escaped,
+        "<srw:record><srw:recordSchema>info:srw/schema/1/dc-v1.1</srw:recordSchema>\
+         <srw:recordXMLEscaping>string</srw:recordXMLEscaping>\
+         <srw:recordData>&lt;dc:title&gt;A&lt;/dc:title&gt;</srw:recordData>\
+         <srw:recordPosition>3</srw:recordPosition></srw:record>"
+    );
+
+    let explain = explain_response(SruVersion::V2, &server(), true);
+    for fragment in [
+        "xmlns:srw=\"http://docs.oasis-open.org/ns/search-ws/sruResponse\"",
+        "<host>library.example.org</host><port>443</port><database>catalog/sru</database>",
+        "<title>ABLE &amp; friends</title>",
+        "<name set=\"dc\">creator</name>",
+        "<setting type=\"maximumRecords\">50</setting>",
+    ] {
+        assert!(
+            explain.contains(fragment),
+            "{fragment} missing from {explain}"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+use sea_orm::{ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::orm::{book::Book, book_subject};
+
+use super::{Library, LibraryErrorStatus};
+
+impl Book {
+    /// Trims subject headings and drops blanks and repeats, keeping the first spelling.
+    pub(super) fn normalize_subjects(&mut self) {
+        let mut seen = HashSet::new();
+        self.subjects = self
+            .subjects
+            .iter()
+            .map(|subject| subject.trim().to_string())
+            .filter(|subject| !subject.is_empty() && seen.insert(subject.to_lowercase()))
+            .collect();
+    }
+}
+
+impl Library {
+    pub(super) async fn load_subjects<C: ConnectionTrait>(
+        books: &mut [Book],
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        if books.is_empty() {
+            return Ok(());
+        }
+
+        let db_result = book_subject::Entity::find()
+            .filter(book_subject::Column::Book.is_in(books.iter().map(|book| book.id)))
+            .order_by_asc(book_subject::Column::Id)
+            .all(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to fetch book subjects: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+
+        let mut subjects = HashMap::<u64, Vec<String>>::new();
+        for row in db_result.unwrap() {
+            subjects.entry(row.book).or_default().push(row.subject);
+        }
+        for book in books {
+            book.subjects = subjects.remove(&book.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    pub(super) async fn save_subjects<C: ConnectionTrait>(
+        book_id: u64,
+        subjects: &[String],
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        let db_result = book_subject::Entity::delete_many()
+            .filter(book_subject::Column::Book.eq(book_id))
+            .exec(database)
+            .await;
+        if let Err(error) = &db_result {
+            warn!("failed to clear book subjects: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        if subjects.is_empty() {
+            return Ok(());
+        }
+
+        let rows = subjects.iter().map(|subject| book_subject::ActiveModel {
+            id: ActiveValue::NotSet,
+            book: ActiveValue::Set(book_id),
+            subject: ActiveValue::Set(subject.clone()),
+        });
+        if let Err(error) = book_subject::Entity::insert_many(rows).exec(database).await {
+            warn!("failed to add book subjects: {error}");
+            return Err(LibraryErrorStatus::DatabaseError);
+        }
+        Ok(())
+    }
+
+    /// Fills in the credits and subjects that live outside the `book` table.
+    pub(super) async fn load_book_details<C: ConnectionTrait>(
+        books: &mut [Book],
+        database: &C,
+    ) -> Result<(), LibraryErrorStatus> {
+        Self::load_contributors(books, database).await?;
+        Self::load_subjects(books, database).await
+    }
+}
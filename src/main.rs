@@ -1,5 +1,7 @@
 use std::{
-    env,
+    env, fs,
+    io::{self, Write},
+    process,
     sync::Arc,
     thread::{sleep, spawn},
     time::Duration,
@@ -8,6 +10,7 @@ use std::{
 use ::log::{error, info, warn};
 use config::Config;
 use dotenv::dotenv;
+use library::{CoverStore, OpenLibrary, SearchIndex};
 use routes::init_router;
 use sea_orm::Database;
 use state::create_state;
@@ -19,6 +22,7 @@ use tower_governor::{
 pub mod auth;
 pub mod config;
 pub mod database;
+pub mod jobs;
 pub mod library;
 pub mod model;
 pub mod orm;
@@ -41,6 +45,7 @@ async fn main() {
     env_logger::init();
 
     let config = Config::from_env();
+    #[allow(clippy::unnecessary_unwrap)]
     if config.is_err() {
         error!("Failed to initialize config: `{}`", config.unwrap_err());
         return;
@@ -57,7 +62,84 @@ async fn main() {
         return;
     }
     let connection = connection.unwrap();
-    let state = create_state(connection);
+
+    let search_index = SearchIndex::open(config.search_index_dir());
+    if let Err(error) = &search_index {
+        error!("Failed to open search index: {error}");
+        return;
+    }
+    let covers = CoverStore::open(config.cover_dir());
+    if let Err(error) = &covers {
+        error!("Failed to open cover directory: {error}");
+        return;
+    }
+    let user_agent = format!(
+        "{}/{} ({})",
+        config.catalog_name(),
+        env!("CARGO_PKG_VERSION"),
+        config.admin_email()
+    );
+    let metadata = OpenLibrary::new(config.metadata_url(), &user_agent);
+    if let Err(error) = &metadata {
+        error!("Failed to initialize metadata provider: {error}");
+        return;
+    }
+    let mut state = create_state(
+        connection,
+        search_index.unwrap(),
+        covers.unwrap(),
+        Arc::new(metadata.unwrap()),
+        config.clone(),
+    );
+
+    if env::args().nth(1).as_deref() == Some("reindex") {
+        info!("Rebuilding search index.");
+        let result = state.library().rebuild_search_index(&state.db()).await;
+        if let Err(error) = result {
+            error!("Failed to rebuild search index: {error}");
+        }
+        return;
+    }
+
+    if env::args().nth(1).as_deref() == Some("import-onix") {
+        let Some(path) = env::args().nth(2) else {
+            error!("Usage: able import-onix <file> [--dry-run]");
+            return;
+        };
+        let dry_run = env::args().skip(3).any(|arg| arg == "--dry-run");
+        let data = fs::read(&path);
+        if let Err(error) = &data {
+            error!("Failed to read `{path}`: {error}");
+            return;
+        }
+        info!("Importing ONIX message `{path}`.");
+        let database = state.db();
+        let result = state
+            .library_mut()
+            .import_onix(&data.unwrap(), dry_run, &database)
+            .await;
+        match result {
+            Ok(report) => {
+                info!(
+                    "ONIX import: {} new, {} updated, {} skipped.",
+                    report.new, report.updated, report.skipped
+                );
+                // The report goes to stdout so it can be kept or piped on, apart from the logs.
+                let mut stdout = io::stdout().lock();
+                let written = serde_json::to_writer_pretty(&mut stdout, &report)
+                    .map_err(io::Error::from)
+                    .and_then(|()| writeln!(stdout));
+                if let Err(error) = written {
+                    error!("Failed to write ONIX import report: {error}");
+                    process::exit(1);
+                }
+            }
+            Err(error) => error!("Failed to import ONIX message: {error}"),
+        }
+        return;
+    }
+
+    jobs::spawn_jobs(state.clone(), &config);
     let app = init_router(state);
     let governor_config = Arc::new(
         GovernorConfigBuilder::default()
@@ -80,6 +162,7 @@ async fn main() {
     let target_bind = format!("{}:{}", config.bind_address(), config.bind_port());
     info!("Initializing server at http://{target_bind}.");
     let server = TcpListener::bind(target_bind).await;
+    #[allow(clippy::unnecessary_unwrap)]
     if server.is_err() {
         error!("Failed to bind TcpListener: `{}`", server.unwrap_err());
         return;
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AddBookRequest {
+    /// Fills a blank title, author, year, subject list or description from the metadata
+    /// provider before the book is saved.
+    pub enrich: Option<bool>,
+}
@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct CheckoutRequest {
+    pub copy: u64,
+    pub borrower: u64,
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use crate::library::CitationFormat;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CiteQuery {
+    pub format: CitationFormat,
+}
+
+/// Options for `GET /books/cite`. Without `ids`, every book matching the `BookSearch` parameters
+/// sent alongside is cited.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CiteBooksQuery {
+    pub format: CitationFormat,
+    /// Comma-separated book ids, cited in the order given.
+    pub ids: Option<String>,
+}
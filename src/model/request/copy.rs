@@ -0,0 +1,15 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::orm::copy::{CopyCondition, CopyStatus};
+
+#[derive(Deserialize, Debug)]
+pub struct CopyRequest {
+    pub barcode: String,
+    pub shelf_location: String,
+    pub condition: CopyCondition,
+    pub acquired_on: NaiveDate,
+    pub status: Option<CopyStatus>,
+    pub item_type: Option<String>,
+    pub branch: Option<String>,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::library::CoverSize;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CoverQuery {
+    /// Defaults to the image as uploaded.
+    pub size: Option<CoverSize>,
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Options for `POST /books/import`. Each `*_column` names the CSV header holding that field and
+/// defaults to the field's own name, e.g. `title` or `publication_year`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImportRequest {
+    /// Validates every row and reports the outcome without writing anything.
+    pub dry_run: Option<bool>,
+    pub title_column: Option<String>,
+    /// Several authors may be separated by `;`, `&` or `and`.
+    pub author_column: Option<String>,
+    pub publication_year_column: Option<String>,
+    pub isbn_column: Option<String>,
+    /// Subject headings separated by `;`.
+    pub subjects_column: Option<String>,
+}
+
+/// Options for `POST /books/import/marc`, which takes binary MARC 21 or MARCXML.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MarcImportRequest {
+    /// Validates every record and reports the outcome without writing anything.
+    pub dry_run: Option<bool>,
+}
+
+/// Options for `POST /books/import/onix`, which takes an ONIX 3.0 message and upserts its
+/// products by ISBN.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OnixImportRequest {
+    /// Reports what each product would do without writing anything.
+    pub dry_run: Option<bool>,
+}
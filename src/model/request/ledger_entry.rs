@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+use crate::orm::ledger::LedgerEntryKind;
+
+#[derive(Deserialize, Debug)]
+pub struct PostLedgerEntryRequest {
+    pub kind: LedgerEntryKind,
+    pub amount: u64,
+    pub reason: String,
+    pub loan: Option<u64>,
+}
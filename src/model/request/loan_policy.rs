@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct LoanPolicyRequest {
+    pub patron_category: Option<String>,
+    pub item_type: Option<String>,
+    pub branch: Option<String>,
+    pub loan_days: u32,
+    pub max_renewals: u32,
+    pub max_loans: u32,
+    pub fine_per_day: u64,
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoanFilter {
+    pub borrower: Option<u64>,
+    pub active: Option<bool>,
+}
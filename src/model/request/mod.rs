@@ -1,5 +1,17 @@
+pub mod add_book;
+pub mod checkout;
+pub mod cite;
+pub mod copy;
+pub mod cover;
+pub mod import;
+pub mod ledger_entry;
+pub mod loan_policy;
+pub mod loans;
 pub mod login;
+pub mod opds;
 pub mod pagination;
+pub mod place_hold;
 pub mod search;
 pub mod set_permissions;
+pub mod sort;
 pub mod user;
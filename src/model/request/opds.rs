@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OpdsQuery {
+    /// Follows the `next` and `previous` links of a feed; the first page when absent.
+    pub page: Option<u64>,
+}
+
+impl OpdsQuery {
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1)
+    }
+}
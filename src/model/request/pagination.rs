@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Pagination {
     page: Option<usize>,
     per_page: Option<usize>,
+    /// An opaque `next_cursor` or `prev_cursor` from a previous response; takes precedence over `page`.
+    cursor: Option<String>,
 }
 
 impl Pagination {
@@ -14,4 +16,14 @@ impl Pagination {
     pub fn per_page(&self) -> usize {
         self.per_page.unwrap_or(50)
     }
+
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Clamps `per_page` to `max` so a single request cannot ask for the whole catalog.
+    pub fn capped(mut self, max: usize) -> Self {
+        self.per_page = Some(self.per_page().min(max));
+        self
+    }
 }
@@ -0,0 +1,6 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct PlaceHoldRequest {
+    pub patron: Option<u64>,
+}
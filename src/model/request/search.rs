@@ -1,8 +1,28 @@
 use serde::{Deserialize, Serialize};
 
+use crate::orm::book_contributor::ContributorRole;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookSearch {
     pub title: Option<String>,
+    /// Matches any credited contributor, optionally narrowed to `role`.
     pub author: Option<String>,
+    pub role: Option<ContributorRole>,
     pub isbn: Option<String>,
+    /// Tolerates typos in `title` and `author`, ranking the closest matches first.
+    pub fuzzy: Option<bool>,
+    pub subject: Option<String>,
+    /// Any year within the decade, e.g. `1970` or `1979` for the 1970s.
+    pub decade: Option<u64>,
+    /// Whether at least one copy is on the shelf and available for loan.
+    pub available: Option<bool>,
+    /// Returns facet counts for the whole result set alongside the page.
+    pub facets: Option<bool>,
+    /// An advanced query such as `title:"dune" (author:herbert OR year:1965..1970) -isbn:978044*`.
+    pub query: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchQuery {
+    pub q: String,
 }
@@ -0,0 +1,101 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Title,
+    Author,
+    PublicationYear,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl SortField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Author => "author",
+            Self::PublicationYear => "publication_year",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+/// A listing order written as `title` for ascending or `-title` for descending.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Sort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (field, descending) = match value.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (value, false),
+        };
+        let field = match field {
+            "title" => SortField::Title,
+            "author" => SortField::Author,
+            "publication_year" => SortField::PublicationYear,
+            "created_at" => SortField::CreatedAt,
+            "updated_at" => SortField::UpdatedAt,
+            _ => return Err(format!("unknown sort field: {field}")),
+        };
+        Ok(Self { field, descending })
+    }
+}
+
+impl TryFrom<String> for Sort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Sort> for String {
+    fn from(sort: Sort) -> Self {
+        sort.to_string()
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.descending {
+            f.write_str("-")?;
+        }
+        f.write_str(self.field.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BookSorting {
+    /// Ties are always broken by id, so every order is stable across pages. Defaults to id order.
+    pub sort: Option<Sort>,
+}
+
+#[test]
+fn test_sort_round_trip() {
+    let sort = "-publication_year".parse::<Sort>().unwrap();
+    assert_eq!(
+        sort,
+        Sort {
+            field: SortField::PublicationYear,
+            descending: true
+        }
+    );
+    assert_eq!(sort.to_string(), "-publication_year");
+    assert_eq!(
+        serde_json::to_string(&"title".parse::<Sort>().unwrap()).unwrap(),
+        r#""title""#
+    );
+    assert!("isbn".parse::<Sort>().is_err());
+    assert!("--title".parse::<Sort>().is_err());
+}
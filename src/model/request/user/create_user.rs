@@ -4,4 +4,5 @@ use serde::Deserialize;
 pub struct CreateUserRequest {
     pub username: String,
     pub password: String,
+    pub category: Option<String>,
 }
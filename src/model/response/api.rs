@@ -6,6 +6,7 @@ pub enum ApiErrorCode {
     Unauthorized = 401,
     Forbidden = 403,
     NotFound = 404,
+    Conflict = 409,
     InternalServerError = 500,
 }
 
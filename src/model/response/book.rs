@@ -1,11 +1,15 @@
-use crate::orm::book::Book;
+use crate::{library::CopyCounts, orm::book::Book};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BookResponse {
     pub book: Book,
+    pub copies: CopyCounts,
+    pub hold_queue: u64,
 }
 
+#[cfg(test)]
+use crate::orm::book_contributor::{ContributorRole, Credit};
 #[cfg(test)]
 use chrono::Utc;
 
@@ -16,14 +20,22 @@ fn test_serialization() {
         id: 42,
         title: "Hitch Hiker's Guide to the Galaxy".to_string(),
         author: "Douglas Adams".to_string(),
+        title_sort: "hitch hikers guide to the galaxy".to_string(),
+        author_sort: "adams douglas".to_string(),
+        contributors: vec![Credit {
+            name: "Douglas Adams".to_string(),
+            role: ContributorRole::Author,
+        }],
         publication_year: 1979,
+        subjects: vec!["Science fiction".to_string()],
         isbn: "9780575074842".to_string(),
+        description: None,
         created_at: utc_now,
         updated_at: utc_now,
     };
 
     let expected = format!(
-        "{{\"id\":42,\"title\":\"Hitch Hiker's Guide to the Galaxy\",\"author\":\"Douglas Adams\",\"publication_year\":1979,\"isbn\":\"9780575074842\",\"created_at\":{},\"updated_at\":{}}}",
+        "{{\"id\":42,\"title\":\"Hitch Hiker's Guide to the Galaxy\",\"author\":\"Douglas Adams\",\"contributors\":[{{\"name\":\"Douglas Adams\",\"role\":\"author\"}}],\"publication_year\":1979,\"subjects\":[\"Science fiction\"],\"isbn\":\"9780575074842\",\"created_at\":{},\"updated_at\":{}}}",
         serde_json::to_string(&utc_now).expect("failed to serialize datetime"),
         serde_json::to_string(&utc_now).unwrap(),
     );
@@ -38,14 +50,22 @@ fn test_deserialization() {
         id: 42,
         title: "Hitch Hiker's Guide to the Galaxy".to_string(),
         author: "Douglas Adams".to_string(),
+        title_sort: String::new(),
+        author_sort: String::new(),
+        contributors: vec![Credit {
+            name: "Douglas Adams".to_string(),
+            role: ContributorRole::Author,
+        }],
         publication_year: 1979,
+        subjects: vec!["Science fiction".to_string()],
         isbn: "9780575074842".to_string(),
+        description: None,
         created_at: utc_now,
         updated_at: utc_now,
     };
 
     let actual = serde_json::from_str(format!(
-        "{{\"id\":42,\"title\":\"Hitch Hiker's Guide to the Galaxy\",\"author\":\"Douglas Adams\",\"publication_year\":1979,\"isbn\":\"9780575074842\",\"created_at\":{},\"updated_at\":{}}}",
+        "{{\"id\":42,\"title\":\"Hitch Hiker's Guide to the Galaxy\",\"author\":\"Douglas Adams\",\"contributors\":[{{\"name\":\"Douglas Adams\",\"role\":\"author\"}}],\"publication_year\":1979,\"subjects\":[\"Science fiction\"],\"isbn\":\"9780575074842\",\"created_at\":{},\"updated_at\":{}}}",
         serde_json::to_string(&utc_now).expect("failed to serialize datetime"),
         serde_json::to_string(&utc_now).unwrap(),
     ).as_str()).expect("failed to deserialize book json");
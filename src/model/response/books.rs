@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-use crate::orm::book::Book;
+use crate::{library::Facets, orm::book::Book};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GetBooksResponse {
     pub books: Vec<Book>,
+    pub total: u64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facets: Option<Facets>,
 }
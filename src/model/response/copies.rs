@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+use crate::{library::CopyCounts, orm::copy::BookCopy};
+
+#[derive(Serialize)]
+pub struct GetCopiesResponse {
+    pub copies: Vec<BookCopy>,
+    pub counts: CopyCounts,
+}
@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use crate::orm::copy::BookCopy;
+
+#[derive(Serialize)]
+pub struct CopyResponse {
+    pub copy: BookCopy,
+}
@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use crate::orm::hold::Hold;
+
+#[derive(Serialize)]
+pub struct HoldResponse {
+    pub hold: Hold,
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+use crate::library::{ImportRow, OnixProductReport};
+
+#[derive(Serialize)]
+pub struct ImportBooksResponse {
+    pub dry_run: bool,
+    pub committed: bool,
+    pub valid: usize,
+    pub invalid: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+#[derive(Serialize)]
+pub struct OnixImportResponse {
+    pub dry_run: bool,
+    pub new: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub products: Vec<OnixProductReport>,
+}
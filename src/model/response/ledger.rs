@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+use crate::{library::LedgerTotals, orm::ledger::LedgerEntry};
+
+#[derive(Serialize)]
+pub struct GetLedgerResponse {
+    pub entries: Vec<LedgerEntry>,
+    pub totals: LedgerTotals,
+}
@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use crate::orm::ledger::LedgerEntry;
+
+#[derive(Serialize)]
+pub struct PostLedgerEntryResponse {
+    pub entry: LedgerEntry,
+}
@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use crate::orm::policy::LoanPolicy;
+
+#[derive(Serialize)]
+pub struct GetLoanPoliciesResponse {
+    pub policies: Vec<LoanPolicy>,
+}
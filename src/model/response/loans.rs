@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use crate::orm::loan::Loan;
+
+#[derive(Serialize)]
+pub struct GetLoansResponse {
+    pub loans: Vec<Loan>,
+}
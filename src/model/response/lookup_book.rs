@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+use crate::orm::book::Book;
+
+/// An unsaved book pre-filled from the metadata provider, for staff to check and then add.
+#[derive(Serialize)]
+pub struct LookupBookResponse {
+    pub book: Book,
+}
@@ -2,9 +2,26 @@ pub mod add_book;
 pub mod api;
 pub mod book;
 pub mod books;
+pub mod copies;
+pub mod copy;
 pub mod drop_book;
+pub mod drop_copy;
+pub mod drop_loan_policy;
 pub mod get_permissions;
+pub mod hold;
+pub mod holds;
+pub mod import;
+pub mod ledger;
+pub mod ledger_entry;
+pub mod loan;
+pub mod loan_policies;
+pub mod loan_policy;
+pub mod loans;
 pub mod login;
+pub mod lookup_book;
+pub mod resolve_policy;
+pub mod search;
+pub mod set_cover;
 pub mod set_permissions;
 pub mod update_book;
 pub mod user;
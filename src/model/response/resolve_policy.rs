@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+use crate::orm::policy::LoanPolicy;
+
+#[derive(Serialize)]
+pub struct ResolvePolicyResponse {
+    // `None` means no rule matched and the built-in defaults apply.
+    pub policy: Option<LoanPolicy>,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::library::SearchHit;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchBooksResponse {
+    pub hits: Vec<SearchHit>,
+    pub total: usize,
+}
@@ -2,6 +2,8 @@ use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::book_contributor::Credit;
+
 pub type Book = Model;
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
@@ -10,9 +12,26 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: u64,
     pub title: String,
+    #[serde(default)]
     pub author: String,
+    /// Derived from `title` by `Book::refresh_sort_keys` when the book is saved.
+    #[serde(skip)]
+    pub title_sort: String,
+    /// Derived from `author` by `Book::refresh_sort_keys` when the book is saved.
+    #[serde(skip)]
+    pub author_sort: String,
+    #[sea_orm(ignore)]
+    #[serde(default)]
+    pub contributors: Vec<Credit>,
     pub publication_year: u64,
+    #[sea_orm(ignore)]
+    #[serde(default)]
+    pub subjects: Vec<String>,
     pub isbn: String,
+    /// A blurb or summary, as supplied by ONIX feeds.
+    #[sea_orm(column_type = "Text", nullable)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
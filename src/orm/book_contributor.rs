@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type BookContributor = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "book_contributor")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub book: u64,
+    pub contributor: u64,
+    pub role: ContributorRole,
+    pub position: u32,
+}
+
+#[derive(
+    Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ContributorRole {
+    #[sea_orm(string_value = "author")]
+    Author,
+    #[sea_orm(string_value = "editor")]
+    Editor,
+    #[sea_orm(string_value = "translator")]
+    Translator,
+    #[sea_orm(string_value = "illustrator")]
+    Illustrator,
+}
+
+/// A contributor as it appears on a book, in credit order.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Credit {
+    pub name: String,
+    pub role: ContributorRole,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type BookSubject = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "book_subject")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub book: u64,
+    pub subject: String,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
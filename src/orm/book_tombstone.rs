@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Records that a book was dropped, so harvesters can learn about the deletion.
+pub type BookTombstone = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "book_tombstone")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    /// The id the book had; ids are never reused.
+    pub book: u64,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
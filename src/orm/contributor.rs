@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type Contributor = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "contributor")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type BookCopy = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "copy")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub book: u64,
+    pub barcode: String,
+    pub shelf_location: String,
+    pub condition: CopyCondition,
+    pub acquired_on: NaiveDate,
+    pub status: CopyStatus,
+    pub item_type: String,
+    pub branch: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum CopyCondition {
+    #[sea_orm(string_value = "new")]
+    New,
+    #[sea_orm(string_value = "good")]
+    Good,
+    #[sea_orm(string_value = "fair")]
+    Fair,
+    #[sea_orm(string_value = "poor")]
+    Poor,
+    #[sea_orm(string_value = "damaged")]
+    Damaged,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum CopyStatus {
+    #[sea_orm(string_value = "available")]
+    Available,
+    #[sea_orm(string_value = "on_loan")]
+    OnLoan,
+    #[sea_orm(string_value = "on_hold_shelf")]
+    OnHoldShelf,
+    #[sea_orm(string_value = "in_repair")]
+    InRepair,
+    #[sea_orm(string_value = "lost")]
+    Lost,
+    #[sea_orm(string_value = "withdrawn")]
+    Withdrawn,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type LedgerEntry = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "ledger_entry")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub user: u64,
+    pub kind: LedgerEntryKind,
+    /// Amount in the smallest currency unit; always positive, `kind` decides the direction.
+    pub amount: u64,
+    pub reason: String,
+    pub posted_by: Option<u64>,
+    pub loan: Option<u64>,
+    pub accrued_on: Option<NaiveDate>,
+    pub posted_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    #[sea_orm(string_value = "charge")]
+    Charge,
+    #[sea_orm(string_value = "payment")]
+    Payment,
+    #[sea_orm(string_value = "waiver")]
+    Waiver,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
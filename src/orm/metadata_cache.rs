@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What a metadata provider answered for an ISBN, kept so repeated lookups stay local.
+pub type MetadataCache = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "metadata_cache")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    /// The `MetadataProvider::name` that answered.
+    pub provider: String,
+    pub isbn: String,
+    /// The answer as JSON, or `None` when the provider did not know the ISBN.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub response: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
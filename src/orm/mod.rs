@@ -1,3 +1,13 @@
 pub mod book;
+pub mod book_contributor;
+pub mod book_subject;
+pub mod book_tombstone;
+pub mod contributor;
+pub mod copy;
+pub mod hold;
+pub mod ledger;
+pub mod loan;
+pub mod metadata_cache;
 pub mod permissions;
+pub mod policy;
 pub mod user;
@@ -27,6 +27,18 @@ pub enum Permission {
     UserDelete = 0b100000,
 
     PermissionsUpdate = 0b1000000,
+
+    LoanCheckout = 0b10000000,
+    LoanReturn = 0b100000000,
+    LoanRenew = 0b1000000000,
+
+    HoldManage = 0b10000000000,
+
+    LedgerCharge = 0b100000000000,
+    LedgerPayment = 0b1000000000000,
+    LedgerWaive = 0b10000000000000,
+
+    PolicyManage = 0b100000000000000,
 }
 
 impl BitAnd<Permission> for Model {
@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type LoanPolicy = Model;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "loan_policy")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u64,
+    pub patron_category: Option<String>,
+    pub item_type: Option<String>,
+    pub branch: Option<String>,
+    pub loan_days: u32,
+    pub max_renewals: u32,
+    pub max_loans: u32,
+    pub fine_per_day: u64,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+impl ActiveModelBehavior for ActiveModel {}
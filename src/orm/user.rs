@@ -23,6 +23,7 @@ pub struct Model {
     pub token: String,
     pub token_expiry: DateTime<Utc>,
     pub permission_id: u64,
+    pub category: String,
 }
 
 #[derive(Debug, EnumIter, DeriveRelation)]
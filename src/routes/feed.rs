@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    library::{FeedChannel, FeedFormat},
+    model::{
+        request::search::BookSearch,
+        response::api::{ApiError, ApiResponse},
+    },
+    state::AppState,
+};
+
+use super::login::CatalogReader;
+
+pub fn feed_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering feed router.");
+    Router::new()
+        .route("/new.atom", get(atom_feed))
+        .route("/new.rss", get(rss_feed))
+}
+
+type FeedResponse = Result<(StatusCode, HeaderMap, String), Json<ApiResponse<ApiError>>>;
+
+/// An IMF-fixdate as used by `Last-Modified`, e.g. `Sat, 02 Mar 2024 08:00:00 GMT`.
+fn http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+async fn atom_feed(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    OriginalUri(uri): OriginalUri,
+    Query(search): Query<BookSearch>,
+    request_headers: HeaderMap,
+) -> FeedResponse {
+    feed(
+        state,
+        FeedFormat::Atom,
+        uri.to_string(),
+        search,
+        request_headers,
+    )
+    .await
+}
+
+async fn rss_feed(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    OriginalUri(uri): OriginalUri,
+    Query(search): Query<BookSearch>,
+    request_headers: HeaderMap,
+) -> FeedResponse {
+    feed(
+        state,
+        FeedFormat::Rss,
+        uri.to_string(),
+        search,
+        request_headers,
+    )
+    .await
+}
+
+async fn feed(
+    state: Arc<Mutex<AppState>>,
+    format: FeedFormat,
+    path: String,
+    search: BookSearch,
+    request_headers: HeaderMap,
+) -> FeedResponse {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let config = state.config();
+    let arrivals = state.library().new_arrivals(&search, &database).await?;
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&http_date(&arrivals.last_modified)) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+    // HTTP dates have whole seconds, so the comparison must ignore anything finer.
+    let unchanged = request_headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| arrivals.last_modified.timestamp() <= since.timestamp());
+    if unchanged {
+        return Ok((StatusCode::NOT_MODIFIED, headers, String::new()));
+    }
+
+    let channel = FeedChannel {
+        name: config.catalog_name().to_string(),
+        base_url: config.public_base_url().to_string(),
+    };
+    let self_url = format!("{}{path}", channel.base_url);
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    Ok((
+        StatusCode::OK,
+        headers,
+        arrivals.render(format, &channel, &self_url),
+    ))
+}
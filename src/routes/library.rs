@@ -1,39 +1,99 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use axum::{
-    extract::{self, Query, State},
+    body::{Body, Bytes},
+    extract::{self, DefaultBodyLimit, OriginalUri, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::IntoResponse,
     routing::{delete, get, post, put},
     Json, Router,
 };
 use axum_macros::debug_handler;
+use futures::TryStreamExt;
 use log::debug;
 use tokio::sync::Mutex;
 
 use crate::{
-    library::LibraryErrorStatus,
+    library::{
+        bibliography, BookPage, CitationFormat, Library, LibraryErrorStatus, LinkedDataFormat,
+        MarcRecord, MAX_COVER_BYTES, MAX_IMPORT_BYTES,
+    },
     model::{
-        request::{pagination::Pagination, search::BookSearch},
+        request::{
+            add_book::AddBookRequest,
+            cite::{CiteBooksQuery, CiteQuery},
+            copy::CopyRequest,
+            cover::CoverQuery,
+            import::{ImportRequest, MarcImportRequest, OnixImportRequest},
+            pagination::Pagination,
+            place_hold::PlaceHoldRequest,
+            search::{BookSearch, SearchQuery},
+            sort::BookSorting,
+        },
         response::{
             add_book::AddBookResponse,
             api::{ApiError, ApiErrorCode, ApiResponse},
             book::BookResponse,
             books::GetBooksResponse,
+            copies::GetCopiesResponse,
+            copy::CopyResponse,
             drop_book::DropBookResponse,
+            drop_copy::DropCopyResponse,
+            hold::HoldResponse,
+            holds::GetHoldsResponse,
+            import::{ImportBooksResponse, OnixImportResponse},
+            lookup_book::LookupBookResponse,
+            search::SearchBooksResponse,
+            set_cover::SetCoverResponse,
             update_book::UpdateBookResponse,
         },
     },
-    orm::book::Book,
+    orm::{book::Book, permissions::Permission},
     state::AppState,
 };
 
-use super::{login::ApiUser, Response};
+use super::{
+    login::{ApiUser, CatalogReader},
+    Response,
+};
 
 impl From<LibraryErrorStatus> for Json<ApiResponse<ApiError>> {
     fn from(value: LibraryErrorStatus) -> Self {
-        Json(ApiResponse::error(ApiError::new(
-            ApiErrorCode::InternalServerError,
-            value.to_string(),
-        )))
+        let code = match value {
+            LibraryErrorStatus::IdNotFound
+            | LibraryErrorStatus::CopyNotFound
+            | LibraryErrorStatus::ContributorNotFound
+            | LibraryErrorStatus::CoverNotFound
+            | LibraryErrorStatus::UserNotFound
+            | LibraryErrorStatus::LoanNotFound
+            | LibraryErrorStatus::HoldNotFound
+            | LibraryErrorStatus::PolicyNotFound
+            | LibraryErrorStatus::MetadataNotFound => ApiErrorCode::NotFound,
+            LibraryErrorStatus::IsbnInvalid
+            | LibraryErrorStatus::PaginationInvalid
+            | LibraryErrorStatus::CursorInvalid
+            | LibraryErrorStatus::QueryInvalid(_)
+            | LibraryErrorStatus::CoverInvalid
+            | LibraryErrorStatus::CoverTooLarge
+            | LibraryErrorStatus::ImportInvalid(_)
+            | LibraryErrorStatus::MarcInvalid(_)
+            | LibraryErrorStatus::OnixInvalid(_)
+            | LibraryErrorStatus::BibliographyTooLarge
+            | LibraryErrorStatus::BarcodeExists
+            | LibraryErrorStatus::RenewalLimitReached(_)
+            | LibraryErrorStatus::LoanLimitReached(_)
+            | LibraryErrorStatus::AmountInvalid
+            | LibraryErrorStatus::PolicyInvalid => ApiErrorCode::BadRequest,
+            LibraryErrorStatus::IsbnExists
+            | LibraryErrorStatus::CopyOnLoan
+            | LibraryErrorStatus::CopyUnavailable
+            | LibraryErrorStatus::LoanReturned
+            | LibraryErrorStatus::HoldExists
+            | LibraryErrorStatus::HoldClosed
+            | LibraryErrorStatus::CopyOnHold => ApiErrorCode::Conflict,
+            _ => ApiErrorCode::InternalServerError,
+        };
+        Json(ApiResponse::error(ApiError::new(code, value.to_string())))
     }
 }
 
@@ -41,42 +101,438 @@ pub fn library_router() -> Router<Arc<Mutex<AppState>>> {
     debug!("Registering library router");
     Router::new()
         .route("/", get(get_books))
-        .route("/{id}", get(get_book_by_id))
+        .route("/search", get(search_books))
+        .route(
+            "/import",
+            post(import_books).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route(
+            "/import/marc",
+            post(import_marc).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route(
+            "/import/onix",
+            post(import_onix).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route("/export.csv", get(export_books))
+        .route("/export.nt", get(export_ntriples))
+        .route("/cite", get(cite_books))
+        .route("/lookup/{isbn}", post(lookup_book))
+        .route("/{id}", get(get_book))
         .route("/{id}", post(add_book))
         .route("/{id}", put(update_book))
         .route("/{id}", delete(drop_book))
+        .route("/{id}/cite", get(cite_book))
+        .route("/{id}/cover", get(get_cover))
+        .route(
+            "/{id}/cover",
+            put(set_cover).layer(DefaultBodyLimit::max(MAX_COVER_BYTES)),
+        )
+        .route("/{id}/copies", get(get_copies))
+        .route("/{id}/copies", post(add_copy))
+        .route("/{id}/copies/{copy_id}", get(get_copy))
+        .route("/{id}/copies/{copy_id}", put(update_copy))
+        .route("/{id}/copies/{copy_id}", delete(drop_copy))
+        .route("/{id}/holds", get(get_holds))
+        .route("/{id}/holds", post(place_hold))
+        .route("/{id}/holds/{hold_id}", delete(cancel_hold))
+}
+
+pub async fn lookup_book(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(isbn): extract::Path<String>,
+) -> Response<LookupBookResponse> {
+    // The provider may take seconds to answer, so it is consulted without holding the state.
+    let (database, provider) = {
+        let state = state.lock().await;
+        (state.db(), state.library().metadata_provider())
+    };
+    caller
+        .assert_permission(database.clone(), Permission::BookAdd)
+        .await?;
+
+    let book = Library::book_draft(provider.as_deref(), &isbn, &database).await?;
+    Ok(Json(ApiResponse::success(LookupBookResponse { book })))
 }
 
 #[debug_handler]
 pub async fn add_book(
     State(state): State<Arc<Mutex<AppState>>>,
     ApiUser(_): ApiUser,
-    extract::Json(book): extract::Json<Book>,
+    request: Query<AddBookRequest>,
+    extract::Json(mut book): extract::Json<Book>,
 ) -> Response<AddBookResponse> {
-    let mut state = state.lock().await;
+    if request.enrich == Some(true) {
+        let (database, provider) = {
+            let state = state.lock().await;
+            (state.db(), state.library().metadata_provider())
+        };
+        Library::enrich_book(provider.as_deref(), &mut book, &database).await?;
+    }
 
+    let mut state = state.lock().await;
     let database = state.db();
     state.library_mut().add_book(book, &database).await?;
     Ok(Json(ApiResponse::success(AddBookResponse)))
 }
 
+/// Builds RFC 8288 `Link` headers pointing at the first, previous and next pages of `uri`.
+fn pagination_links(uri: &Uri, page: &BookPage) -> HeaderMap {
+    let params = serde_urlencoded::from_str::<Vec<(String, String)>>(uri.query().unwrap_or(""))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, _)| key != "cursor" && key != "page")
+        .collect::<Vec<_>>();
+    let link = |cursor: Option<&String>, rel: &str| {
+        let mut params = params.clone();
+        if let Some(cursor) = cursor {
+            params.push(("cursor".to_string(), cursor.clone()));
+        }
+        let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+        if query.is_empty() {
+            format!("<{}>; rel=\"{rel}\"", uri.path())
+        } else {
+            format!("<{}?{query}>; rel=\"{rel}\"", uri.path())
+        }
+    };
+
+    let mut links = vec![link(None, "first")];
+    if let Some(cursor) = &page.prev_cursor {
+        links.push(link(Some(cursor), "prev"));
+    }
+    if let Some(cursor) = &page.next_cursor {
+        links.push(link(Some(cursor), "next"));
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        headers.insert(header::LINK, value);
+    }
+    headers
+}
+
 pub async fn get_books(
     State(state): State<Arc<Mutex<AppState>>>,
     ApiUser(_): ApiUser,
+    OriginalUri(uri): OriginalUri,
     pagination: Query<Pagination>,
     search: Query<BookSearch>,
-) -> Response<GetBooksResponse> {
+    sorting: Query<BookSorting>,
+) -> Result<(HeaderMap, Json<ApiResponse<GetBooksResponse>>), Json<ApiResponse<ApiError>>> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let pagination = pagination.0.capped(state.config().max_per_page());
+    let page = state
+        .library()
+        .get_books(&database, pagination, search.0, sorting.0)
+        .await?;
+    let links = pagination_links(&uri, &page);
+    Ok((
+        links,
+        Json(ApiResponse::success(GetBooksResponse {
+            books: page.books,
+            total: page.total,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+            suggestions: page.suggestions,
+            facets: page.facets,
+        })),
+    ))
+}
+
+pub async fn import_books(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    request: Query<ImportRequest>,
+    body: Bytes,
+) -> Response<ImportBooksResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookAdd)
+        .await?;
+
+    let report = state
+        .library_mut()
+        .import_books(&body, &request.0, &database)
+        .await?;
+    Ok(Json(ApiResponse::success(ImportBooksResponse {
+        dry_run: report.dry_run,
+        committed: report.committed,
+        valid: report.valid,
+        invalid: report.invalid,
+        rows: report.rows,
+    })))
+}
+
+pub async fn import_marc(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    request: Query<MarcImportRequest>,
+    body: Bytes,
+) -> Response<ImportBooksResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookAdd)
+        .await?;
+
+    let report = state
+        .library_mut()
+        .import_marc(&body, request.dry_run == Some(true), &database)
+        .await?;
+    Ok(Json(ApiResponse::success(ImportBooksResponse {
+        dry_run: report.dry_run,
+        committed: report.committed,
+        valid: report.valid,
+        invalid: report.invalid,
+        rows: report.rows,
+    })))
+}
+
+pub async fn import_onix(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    request: Query<OnixImportRequest>,
+    body: Bytes,
+) -> Response<OnixImportResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookAdd)
+        .await?;
+    caller
+        .assert_permission(database.clone(), Permission::BookUpdate)
+        .await?;
+
+    let report = state
+        .library_mut()
+        .import_onix(&body, request.dry_run == Some(true), &database)
+        .await?;
+    Ok(Json(ApiResponse::success(OnixImportResponse {
+        dry_run: report.dry_run,
+        new: report.new,
+        updated: report.updated,
+        skipped: report.skipped,
+        products: report.products,
+    })))
+}
+
+pub async fn export_books(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+) -> (HeaderMap, Body) {
+    // Only the connection is kept, so the export does not hold the state lock while it streams.
+    let database = state.lock().await.db();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/csv; charset=utf-8"),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static("attachment; filename=\"catalog.csv\""),
+    );
+    let rows = Library::export_books(database).map_err(|error| io::Error::other(error.to_string()));
+    (headers, Body::from_stream(rows))
+}
+
+/// The whole catalog as BIBFRAME N-Triples, for linked-data consumers to load in one go.
+pub async fn export_ntriples(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+) -> (HeaderMap, Body) {
+    let (database, base_url) = {
+        let state = state.lock().await;
+        (state.db(), state.config().public_base_url().to_string())
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(LinkedDataFormat::NTriples.media_type()),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static("attachment; filename=\"catalog.nt\""),
+    );
+    let statements = Library::export_ntriples(database, base_url)
+        .map_err(|error| io::Error::other(error.to_string()));
+    (headers, Body::from_stream(statements))
+}
+
+fn citation_file(books: &[Book], format: CitationFormat, name: &str) -> (HeaderMap, String) {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    let disposition = format!("attachment; filename=\"{name}.{}\"", format.extension());
+    if let Ok(disposition) = HeaderValue::from_str(&disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    (headers, bibliography(books, format))
+}
+
+pub async fn cite_book(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    query: Query<CiteQuery>,
+) -> Result<(HeaderMap, String), Json<ApiResponse<ApiError>>> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    let book = state.library_mut().get_book_by_id(id, &database).await?;
+    Ok(citation_file(&[book], query.format, &format!("book-{id}")))
+}
+
+pub async fn cite_books(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    query: Query<CiteBooksQuery>,
+    search: Query<BookSearch>,
+) -> Result<(HeaderMap, String), Json<ApiResponse<ApiError>>> {
     let mut state = state.lock().await;
 
     let database = state.db();
-    Ok(Json(ApiResponse::success(GetBooksResponse {
-        books: state
-            .library_mut()
-            .get_books(&database, pagination.0, search.0)
-            .await?,
+    let books = match &query.ids {
+        Some(ids) => {
+            let ids = ids
+                .split(',')
+                .map(|id| id.trim().parse::<u64>())
+                .collect::<Result<Vec<_>, _>>();
+            let Ok(ids) = ids else {
+                return Err(Json(ApiResponse::error(ApiError::new(
+                    ApiErrorCode::BadRequest,
+                    "invalid book id".to_string(),
+                ))));
+            };
+            state
+                .library_mut()
+                .get_books_by_ids(&ids, &database)
+                .await?
+        }
+        None => {
+            state
+                .library()
+                .get_books_for_bibliography(&search, &database)
+                .await?
+        }
+    };
+    Ok(citation_file(&books, query.format, "bibliography"))
+}
+
+pub async fn search_books(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    pagination: Query<Pagination>,
+    search: Query<SearchQuery>,
+) -> Response<SearchBooksResponse> {
+    let state = state.lock().await;
+
+    let pagination = pagination.0.capped(state.config().max_per_page());
+    let (hits, total) = state.library().search_books(&search.q, pagination).await?;
+    Ok(Json(ApiResponse::success(SearchBooksResponse {
+        hits,
+        total,
     })))
 }
 
+/// A book rendered as something other than the API's JSON response.
+enum BookRepresentation {
+    Marc21,
+    MarcXml,
+    LinkedData(LinkedDataFormat),
+}
+
+impl BookRepresentation {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Marc21 => "application/marc",
+            Self::MarcXml => "application/marcxml+xml",
+            Self::LinkedData(format) => format.media_type(),
+        }
+    }
+}
+
+/// Serves `/{id}` as JSON, `/{id}.mrc` and `/{id}.marcxml` as MARC, and JSON-LD or BIBFRAME
+/// when the `Accept` header asks for them.
+pub async fn get_book(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<String>,
+    request_headers: HeaderMap,
+) -> axum::response::Response {
+    let (id, representation) = if let Some(id) = id.strip_suffix(".mrc") {
+        (id, Some(BookRepresentation::Marc21))
+    } else if let Some(id) = id.strip_suffix(".marcxml") {
+        (id, Some(BookRepresentation::MarcXml))
+    } else {
+        let linked_data = request_headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(LinkedDataFormat::negotiate);
+        (id.as_str(), linked_data.map(BookRepresentation::LinkedData))
+    };
+    let Ok(id) = id.parse::<u64>() else {
+        return Json(ApiResponse::<ApiError>::error(ApiError::new(
+            ApiErrorCode::BadRequest,
+            "invalid book id".to_string(),
+        )))
+        .into_response();
+    };
+    let vary = [(header::VARY, "Accept")];
+    let Some(representation) = representation else {
+        return (
+            vary,
+            get_book_by_id(State(state), ApiUser(caller), extract::Path(id)).await,
+        )
+            .into_response();
+    };
+
+    let mut state = state.lock().await;
+    let database = state.db();
+    let book = match state.library_mut().get_book_by_id(id, &database).await {
+        Ok(book) => book,
+        Err(LibraryErrorStatus::DatabaseError) => {
+            return Json::<ApiResponse<ApiError>>::from(LibraryErrorStatus::DatabaseError)
+                .into_response()
+        }
+        Err(status) => {
+            return Json(ApiResponse::<ApiError>::error(ApiError::new(
+                ApiErrorCode::NotFound,
+                format!("library error: {status}"),
+            )))
+            .into_response()
+        }
+    };
+    let body = match &representation {
+        BookRepresentation::Marc21 => match MarcRecord::from_book(&book).to_marc21() {
+            Ok(record) => record,
+            Err(status) => return Json::<ApiResponse<ApiError>>::from(status).into_response(),
+        },
+        BookRepresentation::MarcXml => {
+            MarcRecord::to_marcxml(&[MarcRecord::from_book(&book)]).into_bytes()
+        }
+        BookRepresentation::LinkedData(format) => format
+            .render(&book, state.config().public_base_url())
+            .into_bytes(),
+    };
+    (
+        vary,
+        [(header::CONTENT_TYPE, representation.content_type())],
+        body,
+    )
+        .into_response()
+}
+
 pub async fn get_book_by_id(
     State(state): State<Arc<Mutex<AppState>>>,
     ApiUser(_): ApiUser,
@@ -86,8 +542,8 @@ pub async fn get_book_by_id(
 
     let database = state.db();
     let book = state.library_mut().get_book_by_id(id, &database).await;
-    if book.is_err() {
-        return match book.unwrap_err() {
+    if let Err(error) = book {
+        return match error {
             LibraryErrorStatus::DatabaseError => Err(Json(ApiResponse::error(ApiError::new(
                 ApiErrorCode::InternalServerError,
                 String::new(),
@@ -99,9 +555,16 @@ pub async fn get_book_by_id(
         };
     }
     let book = book.unwrap();
+    let copies = state.library().copy_counts(book.id, &database).await?;
+    let hold_queue = state
+        .library()
+        .hold_queue_length(book.id, &database)
+        .await?;
 
     Ok(Json(ApiResponse::success(BookResponse {
         book: book.clone(),
+        copies,
+        hold_queue,
     })))
 }
 
@@ -135,3 +598,219 @@ pub async fn drop_book(
     state.library_mut().drop_book(id, &database).await?;
     Ok(Json(ApiResponse::success(DropBookResponse)))
 }
+
+pub async fn set_cover(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    body: Bytes,
+) -> Response<SetCoverResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookUpdate)
+        .await?;
+
+    state.library_mut().set_cover(id, &body, &database).await?;
+    Ok(Json(ApiResponse::success(SetCoverResponse)))
+}
+
+pub async fn get_cover(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    query: Query<CoverQuery>,
+    request_headers: HeaderMap,
+) -> Result<(StatusCode, HeaderMap, Vec<u8>), Json<ApiResponse<ApiError>>> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    let size = query.0.size.unwrap_or_default();
+    let cover = state.library_mut().get_cover(id, size, &database).await?;
+
+    let etag = format!("\"{}\"", cover.version);
+    let mut headers = HeaderMap::new();
+    // Covers sit behind authentication, so only the client itself may cache them.
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=86400"),
+    );
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    let unchanged = request_headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag));
+    if unchanged {
+        return Ok((StatusCode::NOT_MODIFIED, headers, vec![]));
+    }
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(cover.content_type),
+    );
+    Ok((StatusCode::OK, headers, cover.bytes))
+}
+
+pub async fn get_copies(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<GetCopiesResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    let copies = state.library_mut().get_copies(id, &database).await?;
+    let counts = state.library().copy_counts(id, &database).await?;
+    Ok(Json(ApiResponse::success(GetCopiesResponse {
+        copies,
+        counts,
+    })))
+}
+
+pub async fn get_copy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    extract::Path((id, copy_id)): extract::Path<(u64, u64)>,
+) -> Response<CopyResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let copy = state.library().get_copy(id, copy_id, &database).await?;
+    Ok(Json(ApiResponse::success(CopyResponse { copy })))
+}
+
+pub async fn add_copy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    extract::Json(request): extract::Json<CopyRequest>,
+) -> Response<CopyResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookAdd)
+        .await?;
+
+    let copy = state.library_mut().add_copy(id, request, &database).await?;
+    Ok(Json(ApiResponse::success(CopyResponse { copy })))
+}
+
+pub async fn update_copy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path((id, copy_id)): extract::Path<(u64, u64)>,
+    extract::Json(request): extract::Json<CopyRequest>,
+) -> Response<CopyResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookUpdate)
+        .await?;
+
+    let copy = state
+        .library()
+        .update_copy(id, copy_id, request, &database)
+        .await?;
+    Ok(Json(ApiResponse::success(CopyResponse { copy })))
+}
+
+pub async fn drop_copy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path((id, copy_id)): extract::Path<(u64, u64)>,
+) -> Response<DropCopyResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::BookDelete)
+        .await?;
+
+    state.library().drop_copy(id, copy_id, &database).await?;
+    Ok(Json(ApiResponse::success(DropCopyResponse)))
+}
+
+pub async fn get_holds(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<GetHoldsResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::HoldManage)
+        .await?;
+
+    let holds = state.library_mut().get_holds(id, &database).await?;
+    Ok(Json(ApiResponse::success(GetHoldsResponse { holds })))
+}
+
+pub async fn place_hold(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    extract::Json(request): extract::Json<PlaceHoldRequest>,
+) -> Response<HoldResponse> {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    let patron = request.patron.unwrap_or(caller.id);
+    if patron != caller.id {
+        caller
+            .assert_permission(database.clone(), Permission::HoldManage)
+            .await?;
+    }
+
+    let hold = state
+        .library_mut()
+        .place_hold(id, patron, &database)
+        .await?;
+    Ok(Json(ApiResponse::success(HoldResponse { hold })))
+}
+
+pub async fn cancel_hold(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path((id, hold_id)): extract::Path<(u64, u64)>,
+) -> Response<HoldResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let hold = state.library().get_hold(id, hold_id, &database).await?;
+    if hold.patron != caller.id {
+        caller
+            .assert_permission(database.clone(), Permission::HoldManage)
+            .await?;
+    }
+
+    let hold = state.library().cancel_hold(id, hold_id, &database).await?;
+    Ok(Json(ApiResponse::success(HoldResponse { hold })))
+}
+
+#[test]
+fn test_pagination_links() {
+    let uri = "/books?author=adams&page=2&per_page=10"
+        .parse::<Uri>()
+        .unwrap();
+    let page = BookPage {
+        prev_cursor: Some("cHJldg".to_string()),
+        next_cursor: Some("bmV4dA".to_string()),
+        ..BookPage::default()
+    };
+    let links = pagination_links(&uri, &page);
+    assert_eq!(
+        links.get(header::LINK).unwrap(),
+        "</books?author=adams&per_page=10>; rel=\"first\", \
+         </books?author=adams&per_page=10&cursor=cHJldg>; rel=\"prev\", \
+         </books?author=adams&per_page=10&cursor=bmV4dA>; rel=\"next\""
+    );
+
+    let links = pagination_links(&"/books".parse().unwrap(), &BookPage::default());
+    assert_eq!(links.get(header::LINK).unwrap(), "</books>; rel=\"first\"");
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{self, Query, State},
+    routing::{get, post, put},
+    Json, Router,
+};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    model::{
+        request::{checkout::CheckoutRequest, loans::LoanFilter},
+        response::{api::ApiResponse, loan::LoanResponse, loans::GetLoansResponse},
+    },
+    orm::permissions::Permission,
+    state::AppState,
+};
+
+use super::{login::ApiUser, Response};
+
+pub fn loan_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering loan router.");
+    Router::new()
+        .route("/", get(get_loans))
+        .route("/", post(checkout))
+        .route("/{id}", get(get_loan))
+        .route("/{id}/return", put(return_loan))
+        .route("/{id}/renew", put(renew_loan))
+}
+
+pub async fn get_loans(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    filter: Query<LoanFilter>,
+) -> Response<GetLoansResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    if filter.borrower != Some(caller.id) {
+        caller
+            .assert_permission(database.clone(), Permission::LoanCheckout)
+            .await?;
+    }
+
+    let loans = state
+        .library()
+        .get_loans(filter.borrower, filter.active.unwrap_or(false), &database)
+        .await?;
+    Ok(Json(ApiResponse::success(GetLoansResponse { loans })))
+}
+
+pub async fn get_loan(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<LoanResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let loan = state.library().get_loan(id, &database).await?;
+    if loan.borrower != caller.id {
+        caller
+            .assert_permission(database.clone(), Permission::LoanCheckout)
+            .await?;
+    }
+    Ok(Json(ApiResponse::success(LoanResponse { loan })))
+}
+
+pub async fn checkout(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Json(request): extract::Json<CheckoutRequest>,
+) -> Response<LoanResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::LoanCheckout)
+        .await?;
+
+    let loan = state
+        .library()
+        .checkout(request.copy, request.borrower, &database)
+        .await?;
+    Ok(Json(ApiResponse::success(LoanResponse { loan })))
+}
+
+pub async fn return_loan(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<LoanResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::LoanReturn)
+        .await?;
+
+    let loan = state.library().return_loan(id, &database).await?;
+    Ok(Json(ApiResponse::success(LoanResponse { loan })))
+}
+
+pub async fn renew_loan(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<LoanResponse> {
+    let state = state.lock().await;
+
+    let database = state.db();
+    caller
+        .assert_permission(database.clone(), Permission::LoanRenew)
+        .await?;
+
+    let loan = state.library().renew_loan(id, &database).await?;
+    Ok(Json(ApiResponse::success(LoanResponse { loan })))
+}
@@ -65,6 +65,25 @@ impl FromRequestParts<Arc<Mutex<AppState>>> for ApiUser {
     }
 }
 
+/// Like `ApiUser`, but lets anonymous callers through when the catalog is public. Only admits the
+/// caller; catalog reads do not depend on who is asking.
+pub struct CatalogReader;
+impl FromRequestParts<Arc<Mutex<AppState>>> for CatalogReader {
+    type Rejection = Json<ApiResponse<ApiError>>;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &Arc<Mutex<AppState>>,
+    ) -> Result<Self, Self::Rejection> {
+        let public_catalog = state.lock().await.config().public_catalog();
+        if public_catalog && !parts.headers.contains_key(header::AUTHORIZATION) {
+            return Ok(CatalogReader);
+        }
+        ApiUser::from_request_parts(parts, state).await?;
+        Ok(CatalogReader)
+    }
+}
+
 async fn login(
     State(state): State<Arc<Mutex<AppState>>>,
     Form(login): Form<LoginRequest>,
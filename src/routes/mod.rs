@@ -2,9 +2,15 @@ use std::sync::Arc;
 
 use auth::auth_router;
 use axum::{Json, Router};
+use feed::feed_router;
 use library::library_router;
+use loan::loan_router;
 use log::trace;
 use login::login_router;
+use oai::oai_router;
+use opds::opds_router;
+use policy::policy_router;
+use sru::sru_router;
 use tokio::sync::Mutex;
 use user::user_router;
 
@@ -14,8 +20,14 @@ use crate::{
 };
 
 mod auth;
+mod feed;
 mod library;
+mod loan;
 mod login;
+mod oai;
+mod opds;
+mod policy;
+mod sru;
 mod user;
 
 pub type Response<T> = Result<Json<ApiResponse<T>>, Json<ApiResponse<ApiError>>>;
@@ -27,5 +39,11 @@ pub fn init_router(state: AppState) -> Router {
         .nest("/login", login_router())
         .nest("/auth", auth_router())
         .nest("/user", user_router())
+        .nest("/loans", loan_router())
+        .nest("/policies", policy_router())
+        .nest("/oai", oai_router())
+        .nest("/opds", opds_router())
+        .nest("/feeds", feed_router())
+        .nest("/sru", sru_router())
         .with_state(Arc::new(Mutex::new(state)))
 }
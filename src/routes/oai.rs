@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    routing::get,
+    Form, Json, Router,
+};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    library::OaiRepository,
+    model::response::api::{ApiError, ApiResponse},
+    state::AppState,
+};
+
+use super::login::CatalogReader;
+
+pub fn oai_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering OAI-PMH router.");
+    Router::new().route("/", get(oai_get).post(oai_post))
+}
+
+type OaiResponse =
+    Result<([(header::HeaderName, &'static str); 1], String), Json<ApiResponse<ApiError>>>;
+
+/// Harvesters may send their arguments in the query string or as a form body.
+async fn oai_get(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Query(arguments): Query<Vec<(String, String)>>,
+) -> OaiResponse {
+    oai(state, arguments).await
+}
+
+async fn oai_post(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Form(arguments): Form<Vec<(String, String)>>,
+) -> OaiResponse {
+    oai(state, arguments).await
+}
+
+async fn oai(state: Arc<Mutex<AppState>>, arguments: Vec<(String, String)>) -> OaiResponse {
+    let mut state = state.lock().await;
+
+    let database = state.db();
+    let config = state.config();
+    let repository = OaiRepository {
+        name: config.catalog_name().to_string(),
+        base_url: config.public_base_url().to_string(),
+        admin_email: config.admin_email().to_string(),
+    };
+    let xml = state
+        .library_mut()
+        .oai_response(&arguments, &repository, &database)
+        .await?;
+    Ok(([(header::CONTENT_TYPE, "text/xml; charset=utf-8")], xml))
+}
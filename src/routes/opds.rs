@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    routing::get,
+    Extension, Json, Router,
+};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    library::{OpdsCatalog, OpdsFormat, OpdsRequest},
+    model::{
+        request::{opds::OpdsQuery, search::BookSearch},
+        response::api::{ApiError, ApiResponse},
+    },
+    state::AppState,
+};
+
+use super::login::CatalogReader;
+
+/// OPDS 1.2 feeds are served under `/opds`, and the same feeds as OPDS 2.0 under `/opds/v2`.
+pub fn opds_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering OPDS router.");
+    Router::new()
+        .route("/opensearch.xml", get(opensearch_description))
+        .merge(feed_router().layer(Extension(OpdsFormat::Atom)))
+        .nest("/v2", feed_router().layer(Extension(OpdsFormat::Json)))
+}
+
+fn feed_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route("/", get(root))
+        .route("/new", get(new_arrivals))
+        .route("/authors", get(authors))
+        .route("/authors/{id}", get(author))
+        .route("/years", get(years))
+        .route("/years/{year}", get(year))
+        .route("/search", get(search))
+}
+
+type OpdsResponse =
+    Result<([(header::HeaderName, &'static str); 1], String), Json<ApiResponse<ApiError>>>;
+
+fn catalog(state: &AppState) -> OpdsCatalog {
+    let config = state.config();
+    OpdsCatalog {
+        name: config.catalog_name().to_string(),
+        base_url: config.public_base_url().to_string(),
+    }
+}
+
+async fn feed(
+    state: Arc<Mutex<AppState>>,
+    format: OpdsFormat,
+    request: OpdsRequest,
+) -> OpdsResponse {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let catalog = catalog(&state);
+    let feed = state
+        .library()
+        .opds_feed(request, &catalog, &database)
+        .await?;
+    Ok((
+        [(header::CONTENT_TYPE, feed.content_type(format))],
+        feed.render(format, &catalog),
+    ))
+}
+
+async fn opensearch_description(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+) -> OpdsResponse {
+    let state = state.lock().await;
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "application/opensearchdescription+xml; charset=utf-8",
+        )],
+        catalog(&state).opensearch_description(),
+    ))
+}
+
+async fn root(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+) -> OpdsResponse {
+    feed(state, format, OpdsRequest::Root).await
+}
+
+async fn new_arrivals(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+    Query(query): Query<OpdsQuery>,
+) -> OpdsResponse {
+    let request = OpdsRequest::NewArrivals { page: query.page() };
+    feed(state, format, request).await
+}
+
+async fn authors(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+    Query(query): Query<OpdsQuery>,
+) -> OpdsResponse {
+    let request = OpdsRequest::Authors { page: query.page() };
+    feed(state, format, request).await
+}
+
+async fn author(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+    Path(id): Path<u64>,
+    Query(query): Query<OpdsQuery>,
+) -> OpdsResponse {
+    let request = OpdsRequest::Author {
+        id,
+        page: query.page(),
+    };
+    feed(state, format, request).await
+}
+
+async fn years(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+) -> OpdsResponse {
+    feed(state, format, OpdsRequest::Years).await
+}
+
+async fn year(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+    Path(year): Path<u64>,
+    Query(query): Query<OpdsQuery>,
+) -> OpdsResponse {
+    let request = OpdsRequest::Year {
+        year,
+        page: query.page(),
+    };
+    feed(state, format, request).await
+}
+
+/// The target of the OpenSearch template; any `BookSearch` parameter may be given as well.
+async fn search(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Extension(format): Extension<OpdsFormat>,
+    Query(query): Query<OpdsQuery>,
+    Query(search): Query<BookSearch>,
+) -> OpdsResponse {
+    let request = OpdsRequest::Search {
+        search,
+        page: query.page(),
+    };
+    feed(state, format, request).await
+}
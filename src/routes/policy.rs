@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{self, Query, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    library::PolicyKey,
+    model::{
+        request::loan_policy::LoanPolicyRequest,
+        response::{
+            api::ApiResponse, drop_loan_policy::DropLoanPolicyResponse,
+            loan_policies::GetLoanPoliciesResponse, loan_policy::LoanPolicyResponse,
+            resolve_policy::ResolvePolicyResponse,
+        },
+    },
+    orm::permissions::Permission,
+    state::AppState,
+};
+
+use super::{login::ApiUser, Response};
+
+pub fn policy_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering loan policy router.");
+    Router::new()
+        .route("/", get(get_policies))
+        .route("/", post(add_policy))
+        .route("/resolve", get(resolve_policy))
+        .route("/{id}", put(update_policy))
+        .route("/{id}", delete(drop_policy))
+}
+
+pub async fn get_policies(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+) -> Response<GetLoanPoliciesResponse> {
+    let state = state.lock().await;
+
+    let policies = state.library().get_policies(&state.db()).await?;
+    Ok(Json(ApiResponse::success(GetLoanPoliciesResponse {
+        policies,
+    })))
+}
+
+pub async fn resolve_policy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(_): ApiUser,
+    key: Query<PolicyKey>,
+) -> Response<ResolvePolicyResponse> {
+    let state = state.lock().await;
+
+    let policy = state.library().resolve_policy(&key, &state.db()).await?;
+    Ok(Json(ApiResponse::success(ResolvePolicyResponse { policy })))
+}
+
+pub async fn add_policy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Json(request): extract::Json<LoanPolicyRequest>,
+) -> Response<LoanPolicyResponse> {
+    let state = state.lock().await;
+
+    caller
+        .assert_permission(state.db(), Permission::PolicyManage)
+        .await?;
+
+    let policy = state.library().add_policy(request, &state.db()).await?;
+    Ok(Json(ApiResponse::success(LoanPolicyResponse { policy })))
+}
+
+pub async fn update_policy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+    extract::Json(request): extract::Json<LoanPolicyRequest>,
+) -> Response<LoanPolicyResponse> {
+    let state = state.lock().await;
+
+    caller
+        .assert_permission(state.db(), Permission::PolicyManage)
+        .await?;
+
+    let policy = state
+        .library()
+        .update_policy(id, request, &state.db())
+        .await?;
+    Ok(Json(ApiResponse::success(LoanPolicyResponse { policy })))
+}
+
+pub async fn drop_policy(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    extract::Path(id): extract::Path<u64>,
+) -> Response<DropLoanPolicyResponse> {
+    let state = state.lock().await;
+
+    caller
+        .assert_permission(state.db(), Permission::PolicyManage)
+        .await?;
+
+    state.library().drop_policy(id, &state.db()).await?;
+    Ok(Json(ApiResponse::success(DropLoanPolicyResponse)))
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    routing::get,
+    Form, Json, Router,
+};
+use log::debug;
+use tokio::sync::Mutex;
+
+use crate::{
+    library::SruServer,
+    model::response::api::{ApiError, ApiResponse},
+    state::AppState,
+};
+
+use super::login::CatalogReader;
+
+pub fn sru_router() -> Router<Arc<Mutex<AppState>>> {
+    debug!("Registering SRU router.");
+    Router::new().route("/", get(sru_get).post(sru_post))
+}
+
+type SruResponse =
+    Result<([(header::HeaderName, &'static str); 1], String), Json<ApiResponse<ApiError>>>;
+
+/// SRU clients send their parameters in the query string, or as a form body over POST.
+async fn sru_get(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Query(parameters): Query<Vec<(String, String)>>,
+) -> SruResponse {
+    sru(state, parameters).await
+}
+
+async fn sru_post(
+    State(state): State<Arc<Mutex<AppState>>>,
+    _: CatalogReader,
+    Form(parameters): Form<Vec<(String, String)>>,
+) -> SruResponse {
+    sru(state, parameters).await
+}
+
+async fn sru(state: Arc<Mutex<AppState>>, parameters: Vec<(String, String)>) -> SruResponse {
+    let state = state.lock().await;
+
+    let database = state.db();
+    let config = state.config();
+    let server = SruServer {
+        name: config.catalog_name().to_string(),
+        base_url: config.public_base_url().to_string(),
+        max_records: config.max_per_page() as u64,
+    };
+    let xml = state
+        .library()
+        .sru_response(&parameters, &server, &database)
+        .await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    ))
+}
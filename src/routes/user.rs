@@ -3,7 +3,7 @@ use std::sync::Arc;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use axum::{
     extract::{Path, State},
-    routing::put,
+    routing::{get, post, put},
     Json, Router,
 };
 use axum_login::tracing::warn;
@@ -14,13 +14,19 @@ use sea_orm::{ActiveValue, EntityTrait, IntoActiveModel, Set};
 use tokio::sync::Mutex;
 
 use crate::{
+    library::{LedgerTotals, LibraryErrorStatus},
     model::{
-        request::user::{
-            change_password::ChangeUserPasswordRequest, create_user::CreateUserRequest,
-            update_user::UpdateUserRequest,
+        request::{
+            ledger_entry::PostLedgerEntryRequest,
+            user::{
+                change_password::ChangeUserPasswordRequest, create_user::CreateUserRequest,
+                update_user::UpdateUserRequest,
+            },
         },
         response::{
             api::{ApiError, ApiErrorCode, ApiResponse},
+            ledger::GetLedgerResponse,
+            ledger_entry::PostLedgerEntryResponse,
             user::{
                 change_password::ChangeUserPasswordResponse, create_user::CreateUserResponse,
                 update_user::UpdateUserResponse,
@@ -28,6 +34,7 @@ use crate::{
         },
     },
     orm::{
+        ledger::LedgerEntryKind,
         permissions::{self, Permission},
         user,
     },
@@ -36,11 +43,15 @@ use crate::{
 
 use super::{login::ApiUser, Response};
 
+const DEFAULT_PATRON_CATEGORY: &str = "standard";
+
 pub fn user_router() -> Router<Arc<Mutex<AppState>>> {
     Router::new()
         .route("/", put(create_user))
         .route("/{id}", put(update_user))
         .route("/password/{id}", put(change_password))
+        .route("/{id}/ledger", get(get_ledger))
+        .route("/{id}/ledger", post(post_ledger_entry))
 }
 
 async fn change_password(
@@ -209,6 +220,11 @@ async fn create_user(
         enabled: ActiveValue::Set(false),
         created_at: ActiveValue::Set(Utc::now()),
         hash: ActiveValue::Set(hashed.to_string()),
+        category: ActiveValue::Set(
+            create_user_request
+                .category
+                .unwrap_or(DEFAULT_PATRON_CATEGORY.to_string()),
+        ),
         ..user::ActiveModel::default()
     })
     .exec_with_returning(&state.db())
@@ -253,3 +269,60 @@ async fn create_user(
         user_id: new_user.id.unwrap() as u64,
     })))
 }
+
+async fn get_ledger(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    Path(target_id): Path<u64>,
+) -> Response<GetLedgerResponse> {
+    let state = state.lock().await;
+
+    if caller.id != target_id {
+        caller
+            .assert_permission(state.db(), Permission::LedgerPayment)
+            .await?;
+    }
+
+    let entries = state.library().get_ledger(target_id, &state.db()).await?;
+    let totals = LedgerTotals::from_entries(&entries)?;
+    Ok(Json(ApiResponse::success(GetLedgerResponse {
+        entries,
+        totals,
+    })))
+}
+
+async fn post_ledger_entry(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiUser(caller): ApiUser,
+    Path(target_id): Path<u64>,
+    Json(request): Json<PostLedgerEntryRequest>,
+) -> Response<PostLedgerEntryResponse> {
+    let state = state.lock().await;
+
+    let required = match request.kind {
+        LedgerEntryKind::Charge => Permission::LedgerCharge,
+        LedgerEntryKind::Payment => Permission::LedgerPayment,
+        LedgerEntryKind::Waiver => Permission::LedgerWaive,
+    };
+    caller.assert_permission(state.db(), required).await?;
+
+    let db_result = user::Entity::find_by_id(target_id).one(&state.db()).await;
+    if let Err(error) = &db_result {
+        warn!("failed to query db: {error}");
+        return Err(Json(ApiResponse::error(ApiError::new(
+            ApiErrorCode::InternalServerError,
+            String::new(),
+        ))));
+    }
+    if db_result.unwrap().is_none() {
+        return Err(LibraryErrorStatus::UserNotFound.into());
+    }
+
+    let entry = state
+        .library()
+        .post_ledger_entry(target_id, request, caller.id, &state.db())
+        .await?;
+    Ok(Json(ApiResponse::success(PostLedgerEntryResponse {
+        entry,
+    })))
+}
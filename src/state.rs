@@ -1,8 +1,12 @@
-use crate::library::Library;
+use crate::{
+    config::Config,
+    library::{CoverStore, Library, MetadataProvider, SearchIndex},
+};
 use sea_orm::DatabaseConnection;
+use std::sync::Arc;
 
 #[derive(Clone)]
-pub struct AppState(Library, DatabaseConnection);
+pub struct AppState(Library, DatabaseConnection, Config);
 
 impl AppState {
     pub fn library(&self) -> &Library {
@@ -16,11 +20,27 @@ impl AppState {
         self.1.clone()
     }
 
-    pub fn new(library: Library, db: DatabaseConnection) -> Self {
-        Self(library, db)
+    pub fn config(&self) -> &Config {
+        &self.2
+    }
+
+    pub fn new(library: Library, db: DatabaseConnection, config: Config) -> Self {
+        Self(library, db, config)
     }
 }
 
-pub fn create_state(db_connection: DatabaseConnection) -> AppState {
-    AppState::new(Library::default(), db_connection)
+pub fn create_state(
+    db_connection: DatabaseConnection,
+    search_index: SearchIndex,
+    covers: CoverStore,
+    metadata: Arc<dyn MetadataProvider>,
+    config: Config,
+) -> AppState {
+    AppState::new(
+        Library::with_search_index(search_index)
+            .with_cover_store(covers)
+            .with_metadata_provider(metadata),
+        db_connection,
+        config,
+    )
 }